@@ -0,0 +1,36 @@
+/// Represents all the errors of the client
+#[derive(Debug)]
+pub enum Error {
+    SerdeJson(serde_json::Error),
+    Hex(hex::FromHexError),
+    InvalidValue,
+    Types(tzwitter_types::error::Error),
+}
+
+impl ToString for Error {
+    fn to_string(&self) -> String {
+        let err = match self {
+            Error::SerdeJson(_) => "Cannot convert from or to json",
+            Error::Hex(_) => "Cannot decode hex",
+            Error::InvalidValue => "The value does not have the expected encoding",
+            Error::Types(_) => "Error of the tzwitter types",
+        };
+        err.to_string()
+    }
+}
+
+macro_rules! register_error {
+    ($name:ident, $error:ty) => {
+        impl From<$error> for Error {
+            fn from(data: $error) -> Self {
+                Error::$name(data)
+            }
+        }
+    };
+}
+
+register_error!(SerdeJson, serde_json::Error);
+register_error!(Hex, hex::FromHexError);
+register_error!(Types, tzwitter_types::error::Error);
+
+pub type Result<A> = std::result::Result<A, Error>;
@@ -0,0 +1,184 @@
+//! Client of the tzwitter kernel
+//!
+//! It builds the messages of the kernel, signs them and encodes them for the rollup inbox.
+//! It also decodes the values of the durable state of the kernel.
+
+pub mod error;
+pub mod state;
+
+pub use tzwitter_types::signer;
+pub use tzwitter_types::{
+    message::{
+        AcceptOffer, Buy, Content, List, MakeOffer, Message, PostTweet, ReportTweet,
+        SendDirectMessage, Transfer,
+    },
+    nonce::Nonce,
+    public_key_hash::PublicKeyHash,
+};
+
+use error::Result;
+use tzwitter_types::MAGIC_BYTE;
+
+/// Builds the content posting a tweet
+pub fn post_tweet(author: PublicKeyHash, content: &str) -> Content {
+    Content::PostTweet(PostTweet {
+        author,
+        content: content.to_string(),
+    })
+}
+
+/// Builds the content liking a tweet
+pub fn like_tweet(tweet_id: u64) -> Content {
+    Content::LikeTweet(tweet_id)
+}
+
+/// Builds the content transferring a tweet
+pub fn transfer(tweet_id: u64, destination: PublicKeyHash) -> Content {
+    Content::Transfer(Transfer {
+        tweet_id,
+        destination,
+    })
+}
+
+/// Builds the content collecting a tweet on the layer 1
+pub fn collect(tweet_id: u64) -> Content {
+    Content::Collect(tweet_id)
+}
+
+/// Builds the content listing a tweet for sale, at a price in mutez
+pub fn list(tweet_id: u64, price: u64) -> Content {
+    Content::List(List { tweet_id, price })
+}
+
+/// Builds the content removing a tweet from sale
+pub fn unlist(tweet_id: u64) -> Content {
+    Content::Unlist(tweet_id)
+}
+
+/// Builds the content buying a listed tweet
+///
+/// The buy fails if the price of the listing is higher than the max price, in mutez
+pub fn buy(tweet_id: u64, max_price: u64) -> Content {
+    Content::Buy(Buy {
+        tweet_id,
+        max_price,
+    })
+}
+
+/// Builds the content offering a price in mutez for a tweet
+pub fn make_offer(tweet_id: u64, price: u64) -> Content {
+    Content::MakeOffer(MakeOffer { tweet_id, price })
+}
+
+/// Builds the content selling a tweet to the offer of a buyer
+pub fn accept_offer(tweet_id: u64, buyer: PublicKeyHash) -> Content {
+    Content::AcceptOffer(AcceptOffer { tweet_id, buyer })
+}
+
+/// Builds the content withdrawing mutez from the balance of the signer to the layer 1
+pub fn withdraw_balance(amount: u64) -> Content {
+    Content::WithdrawBalance(amount)
+}
+
+/// Builds the content reporting an abusive tweet
+pub fn report_tweet(tweet_id: u64, reason: &str) -> Content {
+    Content::ReportTweet(ReportTweet {
+        tweet_id,
+        reason: reason.to_string(),
+    })
+}
+
+/// Builds the content hiding a tweet, only the admin of the kernel can send it
+pub fn hide(tweet_id: u64) -> Content {
+    Content::Hide(tweet_id)
+}
+
+/// Builds the content sending a direct message
+///
+/// The ciphertext and the ephemeral public key are encrypted and encoded by the caller
+pub fn send_direct_message(to: PublicKeyHash, ciphertext: &str, ephemeral_pk: &str) -> Content {
+    Content::SendDirectMessage(SendDirectMessage {
+        to,
+        ciphertext: ciphertext.to_string(),
+        ephemeral_pk: ephemeral_pk.to_string(),
+    })
+}
+
+/// Builds the content deleting a direct message from the inbox of the signer
+pub fn delete_direct_message(index: u64) -> Content {
+    Content::DeleteDirectMessage(index)
+}
+
+/// Builds the content blocking the direct messages of an account
+pub fn block(account: PublicKeyHash) -> Content {
+    Content::Block(account)
+}
+
+/// Builds the content unblocking the direct messages of an account
+pub fn unblock(account: PublicKeyHash) -> Content {
+    Content::Unblock(account)
+}
+
+/// Encodes a message for the rollup inbox
+///
+/// The bytes are the payload of the external message, prefixed by the MAGIC_BYTE of tzwitter
+/// They can be sent with octez-client, or posted to the /operations endpoint of the sequencer
+pub fn encode_external(message: &Message) -> Result<Vec<u8>> {
+    let json = serde_json::to_vec(message)?;
+    let mut bytes = vec![MAGIC_BYTE];
+    bytes.extend(json);
+    Ok(bytes)
+}
+
+/// Encodes a message in hex, as expected by octez-client and the sequencer
+pub fn encode_external_hex(message: &Message) -> Result<String> {
+    let bytes = encode_external(message)?;
+    Ok(hex::encode(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signer::Signer;
+    use tzwitter_types::message::Payload;
+
+    #[test]
+    fn test_encode_external() {
+        let signer =
+            Signer::from_b58("edsk3QoqBuvdamxouPhin7swCvkQNgq4jP5KZPbwWNnwdZpSpJiEbq").unwrap();
+        let content = post_tweet(signer.public_key_hash(), "Hello world");
+        let message = signer.sign(Nonce(1), content);
+
+        let bytes = encode_external(&message).unwrap();
+        assert_eq!(bytes[0], MAGIC_BYTE);
+
+        // The kernel can deserialize the message
+        let decoded: Message = serde_json::from_slice(&bytes[1..]).unwrap();
+        assert_eq!(decoded.hash().to_string(), message.hash().to_string());
+    }
+
+    #[test]
+    fn test_encode_marketplace() {
+        let signer =
+            Signer::from_b58("edsk3QoqBuvdamxouPhin7swCvkQNgq4jP5KZPbwWNnwdZpSpJiEbq").unwrap();
+        let contents = [
+            list(0, 1_000),
+            unlist(0),
+            buy(0, 1_000),
+            make_offer(0, 500),
+            accept_offer(0, signer.public_key_hash()),
+            withdraw_balance(500),
+        ];
+
+        for (nonce, content) in contents.into_iter().enumerate() {
+            let kind = content.kind();
+            let message = signer.sign(Nonce(nonce as u64 + 1), content);
+            let bytes = encode_external(&message).unwrap();
+
+            // The kernel can deserialize the message
+            let decoded: Message = serde_json::from_slice(&bytes[1..]).unwrap();
+            assert_eq!(decoded.inner().content.kind(), kind);
+            assert_eq!(decoded.hash().to_string(), message.hash().to_string());
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! Decoding of the values of the durable state
+//!
+//! The values can be read from a rollup node, with
+//! /global/block/head/durable/wasm_2_0_0/value?key={path}
+//! or from the sequencer, with /state/value?path={path}
+//!
+//! The typed views of the tweets, accounts and receipts are in the read model of the kernel
+
+use tzwitter_types::public_key_hash::PublicKeyHash;
+pub use tzwitter_types::read_model;
+
+use crate::error::{Error, Result};
+
+/// Decodes the response of the rollup node
+///
+/// The rollup node returns the value as an hex encoded json string, or null if it does not exist
+pub fn decode_rollup_node_value(body: &str) -> Result<Option<Vec<u8>>> {
+    let value: Option<String> = serde_json::from_str(body)?;
+    match value {
+        None => Ok(None),
+        Some(value) => Ok(Some(hex::decode(value)?)),
+    }
+}
+
+/// Decodes the response of the sequencer
+///
+/// The sequencer returns the raw hex of the value, and a 404 if it does not exist
+pub fn decode_sequencer_value(status: u16, body: &str) -> Result<Option<Vec<u8>>> {
+    match status {
+        404 => Ok(None),
+        _ => Ok(Some(hex::decode(body.trim())?)),
+    }
+}
+
+/// Decodes an u64, encoded in big endian
+///
+/// It is used for the likes, the nonces and the counters
+pub fn decode_u64(value: &[u8]) -> Result<u64> {
+    read_model::decode_u64(value).map_err(|_| Error::InvalidValue)
+}
+
+/// Decodes an u32, encoded in big endian
+///
+/// It is used for the levels
+pub fn decode_u32(value: &[u8]) -> Result<u32> {
+    read_model::decode_u32(value).map_err(|_| Error::InvalidValue)
+}
+
+/// Decodes an utf8 string
+///
+/// It is used for the content of the tweets
+pub fn decode_string(value: &[u8]) -> Result<String> {
+    read_model::decode_string(value).map_err(|_| Error::InvalidValue)
+}
+
+/// Decodes a public key hash, stored as a b58 string
+pub fn decode_public_key_hash(value: &[u8]) -> Result<PublicKeyHash> {
+    read_model::decode_public_key_hash(value).map_err(|_| Error::InvalidValue)
+}
+
+/// Decodes a boolean, 0x01 is true and 0x00 is false
+///
+/// It is used for the success of the receipts
+pub fn decode_bool(value: &[u8]) -> Result<bool> {
+    read_model::decode_bool(value).map_err(|_| Error::InvalidValue)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_rollup_node_value() {
+        let value = decode_rollup_node_value("\"0000000000000002\"").unwrap();
+        assert_eq!(decode_u64(&value.unwrap()).unwrap(), 2);
+
+        let value = decode_rollup_node_value("null").unwrap();
+        assert!(value.is_none());
+    }
+
+    #[test]
+    fn test_decode_sequencer_value() {
+        let value = decode_sequencer_value(200, "747a3151464439").unwrap();
+        assert_eq!(decode_string(&value.unwrap()).unwrap(), "tz1QFD9");
+
+        let value = decode_sequencer_value(404, "").unwrap();
+        assert!(value.is_none());
+    }
+
+    #[test]
+    fn test_decode_public_key_hash() {
+        let value = b"tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv";
+        let pkh = decode_public_key_hash(value).unwrap();
+        assert_eq!(pkh.to_string(), "tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv");
+    }
+
+    #[test]
+    fn test_decode_invalid_u64() {
+        assert!(decode_u64(&[0x00, 0x01]).is_err());
+    }
+}
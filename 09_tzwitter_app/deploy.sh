@@ -17,8 +17,10 @@ which wasm-strip > /dev/null || (echo "wasm-strip should be installed" && echo "
 which ligo > /dev/null || (echo "wasm-strip should be installed" && echo "https://ligolang.org/docs/intro/installation?lang=jsligo" exit 1)
 
 # deploying the layer 1 contract
-MICHELSON=$(ligo compile contract smart_contract/dummy-fa2.jsligo)
-STORAGE=$(ligo compile storage smart_contract/dummy-fa2.jsligo initial_storage)
+MICHELSON=$(ligo compile contract smart_contract/fa2.jsligo)
+# The account deploying the contract is its admin, the only one allowed to set the rollup
+ADMIN=$(octez-client show address $account_alias | grep "Hash:" | awk '{print $2}')
+STORAGE=$(ligo compile storage smart_contract/fa2.jsligo "initial_storage(\"${ADMIN}\" as address)")
 
 export TZWITTER_L1_CONTRACT=$(octez-client originate contract tzwitter transferring 0 from $account_alias running "$MICHELSON" --init "$STORAGE" --burn-cap 1.0 --force | grep "New contract" | awk '{ print $3}')
 
@@ -53,10 +55,13 @@ KERNEL_INSTALLER=$(cat rollup/installer.hex)
 # Originate the kernel 
 SOR_ADDR=$(octez-client originate smart rollup from $account_alias \
   of kind wasm_2_0_0 \
-  of type bytes \
+  of type 'or (int %confirm) (pair %deposit address nat)' \
   with kernel "${KERNEL_INSTALLER}" \
   --burn-cap 999 | grep "Address:" | awk '{print $2}')
 
+# Notify the layer 1 contract of the rollup address, so it can confirm the mints
+octez-client transfer 0 from $account_alias to $TZWITTER_L1_CONTRACT --entrypoint setRollup --arg "\"${SOR_ADDR}\"" --burn-cap 1.0
+
 # # Setting up the rollup data directory
 octez-smart-rollup-node-alpha init operator config for "${SOR_ADDR}" with operators "$account_alias" --data-dir rollup
 
@@ -0,0 +1,30 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tezos_data_encoding::enc::{BinResult, BinWriter};
+use tezos_smart_rollup::testing::prelude::MockHost;
+use tzwitter_kernel::read_input;
+
+/// Bytes of an external message, written as they are
+struct External(Vec<u8>);
+
+impl BinWriter for External {
+    fn bin_write(&self, output: &mut Vec<u8>) -> BinResult {
+        output.extend_from_slice(&self.0);
+        Ok(())
+    }
+}
+
+// read_input should never panic, whatever the content of the inbox
+fuzz_target!(|messages: Vec<Vec<u8>>| {
+    let mut host = MockHost::default();
+    let size = messages.len();
+    for message in messages {
+        host.add_external(External(message));
+    }
+
+    // Each call consumes one input, the internal messages of the level included
+    for _ in 0..size + 4 {
+        let _ = read_input(&mut host);
+    }
+});
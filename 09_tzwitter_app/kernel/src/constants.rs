@@ -1,4 +1,4 @@
-pub const MAGIC_BYTE: u8 = 0x74;
+pub use tzwitter_types::MAGIC_BYTE;
 
 #[cfg(not(debug_assertions))]
 pub const L1_TOKEN_CONTRACT_ADDRESS: &str = env!("TZWITTER_L1_CONTRACT");
@@ -6,3 +6,52 @@ pub const L1_TOKEN_CONTRACT_ADDRESS: &str = env!("TZWITTER_L1_CONTRACT");
 pub const L1_TOKEN_CONTRACT_ADDRESS: &str = "KT1RycYvM4EVs6BAXWEsGXaAaRqiMP53KT4w";
 
 pub const L1_TOKEN_CONTRACT_ENTRYPOINT: &str = "mint";
+
+/// Entrypoint of the L1 contract sending the tez of a balance withdrawal
+pub const L1_WITHDRAW_ENTRYPOINT: &str = "withdraw";
+
+/// Number of levels an outbox message can be executed on the layer 1
+///
+/// It corresponds to the max_active_outbox_levels of the protocol
+/// Once this window is over, the withdrawal is expired and the tweet can be collected again
+pub const OUTBOX_EXPIRATION_WINDOW: u32 = 20_160;
+
+/// Default number of levels the events are kept
+pub const DEFAULT_EVENTS_RETENTION: u32 = 1_000;
+
+/// Default number of levels the receipts are kept
+pub const DEFAULT_RECEIPTS_RETENTION: u32 = 1_000;
+
+/// Maximum number of receipts deleted at each level
+///
+/// It bounds the ticks spent by the garbage collection of the receipts
+pub const RECEIPTS_GC_BUDGET: u32 = 100;
+
+/// Maximum number of levels whose events are deleted at each level
+///
+/// The pruning catches up with the retention period at this pace, when the retention is reduced
+pub const EVENTS_GC_BUDGET: u32 = 10;
+
+/// Maximum number of levels whose metrics are deleted at each level
+pub const METRICS_GC_BUDGET: u32 = 10;
+
+/// Maximum size of the ciphertext of a direct message, in bytes
+pub const MAX_CIPHERTEXT_SIZE: usize = 1_024;
+
+/// Maximum size of the ephemeral public key of a direct message, in bytes
+pub const MAX_EPHEMERAL_PK_SIZE: usize = 128;
+
+/// Maximum number of direct messages in the inbox of an account
+pub const INBOX_QUOTA: u64 = 100;
+
+/// Maximum number of direct messages an account can send at each level
+pub const DIRECT_MESSAGES_PER_LEVEL: u32 = 10;
+
+/// Maximum size of the reason of a report, in bytes
+pub const MAX_REPORT_REASON_SIZE: usize = 280;
+
+/// Default number of reports hiding a tweet
+pub const DEFAULT_REPORTS_THRESHOLD: u32 = 5;
+
+/// Share of the price of a sale paid to the author of the tweet, in basis points
+pub const ROYALTIES_BPS: u64 = 500;
@@ -0,0 +1,16 @@
+use crate::core::public_key_hash::PublicKeyHash;
+
+/// Configuration of the kernel
+///
+/// The configuration is read from the durable storage under /config
+/// It can be set by the installer, otherwise the default values are used
+pub struct Config {
+    /// Number of levels the events are kept
+    pub events_retention: u32,
+    /// Number of levels the receipts are kept
+    pub receipts_retention: u32,
+    /// Number of reports hiding a tweet
+    pub reports_threshold: u32,
+    /// Account allowed to hide any tweet
+    pub admin: Option<PublicKeyHash>,
+}
@@ -0,0 +1,13 @@
+use crate::core::public_key_hash::PublicKeyHash;
+
+/// A direct message stored in the inbox of its recipient
+///
+/// The ciphertext and the ephemeral public key are opaque to the kernel,
+/// the key agreement and the encryption are done by the clients
+pub struct DirectMessage {
+    pub from: PublicKeyHash,
+    pub ciphertext: String,
+    pub ephemeral_pk: String,
+    /// Level of the reception of the message
+    pub level: u32,
+}
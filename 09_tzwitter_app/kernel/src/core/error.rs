@@ -21,6 +21,7 @@ pub enum Error {
     Runtime(tezos_smart_rollup::host::RuntimeError),
     Ed25519Compact(ed25519_compact::Error),
     InvalidSignature,
+    InvalidSecretKey,
     InvalidNonce,
     PathError(tezos_smart_rollup::storage::path::PathError),
     StateDeserializarion,
@@ -32,6 +33,25 @@ pub enum Error {
     BigIntError,
     BinError(tezos_data_encoding::enc::BinError),
     EntrypointError(tezos_smart_rollup::types::EntrypointError),
+    WithdrawalNotFound,
+    SerdeJsonSerialization(serde_json_wasm::ser::Error),
+    SelfTransfer,
+    TweetBeingCollected,
+    NotListed,
+    OfferNotFound,
+    InsufficientBalance,
+    PriceTooHigh,
+    BalanceOverflow,
+    TweetHidden,
+    AlreadyReported,
+    NotAdmin,
+    DirectMessageTooLarge,
+    ReportReasonTooLarge,
+    InboxFull,
+    RateLimited,
+    SenderBlocked,
+    DirectMessageNotFound,
+    PublicKeyMismatch,
 }
 
 impl ToString for Error {
@@ -41,6 +61,7 @@ impl ToString for Error {
             Error::Runtime(_) => "Runtime error, caused by host function",
             Error::Ed25519Compact(_) => "Cannot deserialize Ed25519",
             Error::InvalidSignature => "Invalid signature",
+            Error::InvalidSecretKey => "Cannot decode the secret key",
             Error::InvalidNonce => "Invalid nonce",
             Error::PathError(_) => "Invalid path",
             Error::StateDeserializarion => "State deserialization",
@@ -52,6 +73,25 @@ impl ToString for Error {
             Error::BigIntError => "Cannot deserialize big int",
             Error::BinError(_) => "Cannot serialize michelson to binary",
             Error::EntrypointError(_) => "Not a correct entrypoint",
+            Error::WithdrawalNotFound => "The tweet is not being withdrawn",
+            Error::SerdeJsonSerialization(_) => "Cannot serialize to json",
+            Error::SelfTransfer => "The tweet cannot be transferred to its owner",
+            Error::TweetBeingCollected => "The tweet is being collected, it cannot be transferred",
+            Error::NotListed => "The tweet is not listed",
+            Error::OfferNotFound => "The offer does not exist",
+            Error::InsufficientBalance => "The balance of the account is too low",
+            Error::PriceTooHigh => "The price of the listing is higher than the maximum price",
+            Error::BalanceOverflow => "The balance of the account is too high",
+            Error::TweetHidden => "The tweet has been hidden by the moderation",
+            Error::AlreadyReported => "The tweet has already been reported by this account",
+            Error::NotAdmin => "Only the admin can hide a tweet",
+            Error::DirectMessageTooLarge => "The direct message is too large",
+            Error::ReportReasonTooLarge => "The reason of the report is too large",
+            Error::InboxFull => "The inbox of the recipient is full",
+            Error::RateLimited => "Too many direct messages sent at this level",
+            Error::SenderBlocked => "The recipient has blocked the sender",
+            Error::DirectMessageNotFound => "The direct message does not exist",
+            Error::PublicKeyMismatch => "The public key is not the one revealed by the account",
         };
         err.to_string()
     }
@@ -73,5 +113,28 @@ register_error!(PathError, tezos_smart_rollup::storage::path::PathError);
 register_error!(Runtime, tezos_smart_rollup::host::RuntimeError);
 register_error!(BinError, tezos_data_encoding::enc::BinError);
 register_error!(EntrypointError, tezos_smart_rollup::types::EntrypointError);
+register_error!(SerdeJsonSerialization, serde_json_wasm::ser::Error);
+
+impl From<tzwitter_types::error::Error> for Error {
+    fn from(error: tzwitter_types::error::Error) -> Self {
+        match error {
+            tzwitter_types::error::Error::FromUtf8(err) => Error::FromUtf8(err),
+            tzwitter_types::error::Error::Ed25519Compact(err) => Error::Ed25519Compact(err),
+            tzwitter_types::error::Error::InvalidSignature => Error::InvalidSignature,
+            tzwitter_types::error::Error::InvalidSecretKey => Error::InvalidSecretKey,
+            tzwitter_types::error::Error::StateDeserializarion => Error::StateDeserializarion,
+        }
+    }
+}
+
+impl From<tzwitter_pipeline::error::Error> for Error {
+    fn from(error: tzwitter_pipeline::error::Error) -> Self {
+        match error {
+            tzwitter_pipeline::error::Error::Signature(err) => Error::from(err),
+            tzwitter_pipeline::error::Error::InvalidNonce => Error::InvalidNonce,
+            tzwitter_pipeline::error::Error::PublicKeyMismatch => Error::PublicKeyMismatch,
+        }
+    }
+}
 
 pub type Result<A> = std::result::Result<A, Error>;
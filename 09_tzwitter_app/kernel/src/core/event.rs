@@ -0,0 +1,96 @@
+use serde::Serialize;
+
+use crate::core::hash::Blake2b;
+use crate::core::public_key_hash::PublicKeyHash;
+
+/// What happened to a tweet
+#[derive(Serialize)]
+pub enum EventKind {
+    TweetPosted {
+        tweet_id: u64,
+        author: PublicKeyHash,
+    },
+    TweetLiked {
+        tweet_id: u64,
+        account: PublicKeyHash,
+    },
+    TweetTransferred {
+        tweet_id: u64,
+        from: PublicKeyHash,
+        to: PublicKeyHash,
+    },
+    TweetCollected {
+        tweet_id: u64,
+        owner: PublicKeyHash,
+    },
+    TweetListed {
+        tweet_id: u64,
+        seller: PublicKeyHash,
+        price: u64,
+    },
+    TweetUnlisted {
+        tweet_id: u64,
+        seller: PublicKeyHash,
+    },
+    TweetSold {
+        tweet_id: u64,
+        seller: PublicKeyHash,
+        buyer: PublicKeyHash,
+        price: u64,
+    },
+    OfferMade {
+        tweet_id: u64,
+        buyer: PublicKeyHash,
+        price: u64,
+    },
+    TweetReported {
+        tweet_id: u64,
+        account: PublicKeyHash,
+    },
+    TweetHidden {
+        tweet_id: u64,
+    },
+    /// The content of the direct message is not part of the event
+    DirectMessageSent {
+        from: PublicKeyHash,
+        to: PublicKeyHash,
+        index: u64,
+    },
+    DirectMessageDeleted {
+        account: PublicKeyHash,
+        index: u64,
+    },
+    AccountBlocked {
+        account: PublicKeyHash,
+        blocked: PublicKeyHash,
+    },
+    AccountUnblocked {
+        account: PublicKeyHash,
+        unblocked: PublicKeyHash,
+    },
+    BalanceWithdrawn {
+        account: PublicKeyHash,
+        amount: u64,
+    },
+}
+
+/// Definition of an event
+///
+/// An event is emitted for each successfully processed message
+/// It is stored in JSON under /events/{level}/{n}, for instance:
+/// {"hash":"933d...8a2d","kind":{"TweetLiked":{"tweet_id":0,"account":{"Tz1":"tz1..."}}}}
+#[derive(Serialize)]
+pub struct Event {
+    hash: String,
+    kind: EventKind,
+}
+
+impl Event {
+    /// Creates an event from the hash of the processed message
+    pub fn new(hash: &Blake2b, kind: EventKind) -> Event {
+        Event {
+            hash: hash.to_string(),
+            kind,
+        }
+    }
+}
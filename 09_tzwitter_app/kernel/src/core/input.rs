@@ -0,0 +1,14 @@
+use crate::core::message::Message;
+use crate::core::public_key_hash::PublicKeyHash;
+
+/// Represents the inputs of the inbox processed by the kernel
+pub enum Input {
+    /// A message signed by a tzwitter user
+    Message(Message),
+    /// Beginning of a new Tezos level
+    StartOfLevel,
+    /// The layer 1 contract confirms that the tweet has been minted
+    MintConfirmation(u64),
+    /// The layer 1 contract credits the balance of an account, the amount is in mutez
+    Deposit { account: PublicKeyHash, amount: u64 },
+}
@@ -0,0 +1,122 @@
+use serde::Serialize;
+
+use crate::core::error::*;
+use crate::core::tweet::Tweet;
+
+/// Symbol of the minted tweets
+const SYMBOL: &str = "TZWT";
+
+/// An attribute of a token, as defined by TZIP-21
+#[derive(Serialize)]
+pub struct Attribute {
+    pub name: String,
+    pub value: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+impl Attribute {
+    /// Creates an integer attribute
+    fn integer<T: ToString>(name: &str, value: &T) -> Attribute {
+        Attribute {
+            name: name.to_string(),
+            value: value.to_string(),
+            kind: "integer".to_string(),
+        }
+    }
+}
+
+/// Metadata of a minted tweet, following TZIP-21
+///
+/// https://gitlab.com/tezos/tzip/-/blob/master/proposals/tzip-21/tzip-21.md
+pub struct TokenMetadata {
+    pub name: String,
+    pub description: String,
+    pub creators: Vec<String>,
+    pub attributes: Vec<Attribute>,
+}
+
+impl TokenMetadata {
+    /// Creates the metadata of a tweet
+    ///
+    /// The written level is unknown for the tweets posted before it was recorded
+    pub fn new(
+        tweet_id: &u64,
+        tweet: &Tweet,
+        written_level: Option<u32>,
+        collected_level: u32,
+    ) -> TokenMetadata {
+        let mut attributes = vec![Attribute::integer("likes", &tweet.likes)];
+        if let Some(written_level) = written_level {
+            attributes.push(Attribute::integer("written_level", &written_level));
+        }
+        attributes.push(Attribute::integer("collected_level", &collected_level));
+
+        TokenMetadata {
+            name: format!("Tzwitter #{}", tweet_id),
+            description: tweet.content.clone(),
+            creators: vec![tweet.author.to_string()],
+            attributes,
+        }
+    }
+
+    /// Returns the token_info of the TZIP-12 token metadata
+    ///
+    /// Strings are encoded in utf-8, arrays are encoded in JSON
+    /// The entries are sorted by key, as required by a Michelson map
+    pub fn token_info(&self) -> Result<Vec<(String, Vec<u8>)>> {
+        let creators = serde_json_wasm::to_string(&self.creators)?;
+        let attributes = serde_json_wasm::to_string(&self.attributes)?;
+
+        let mut token_info = vec![
+            ("attributes".to_string(), attributes.into_bytes()),
+            ("creators".to_string(), creators.into_bytes()),
+            ("decimals".to_string(), "0".as_bytes().to_vec()),
+            (
+                "description".to_string(),
+                self.description.as_bytes().to_vec(),
+            ),
+            ("isBooleanAmount".to_string(), "true".as_bytes().to_vec()),
+            ("name".to_string(), self.name.as_bytes().to_vec()),
+            ("symbol".to_string(), SYMBOL.as_bytes().to_vec()),
+        ];
+        token_info.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(token_info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TokenMetadata;
+    use crate::core::{public_key_hash::PublicKeyHash, tweet::Tweet};
+
+    #[test]
+    fn test_token_info() {
+        let tweet = Tweet {
+            author: PublicKeyHash::from_b58("tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv").unwrap(),
+            content: "Hello world".to_string(),
+            likes: 3,
+        };
+        let metadata = TokenMetadata::new(&0, &tweet, Some(10), 12);
+        let token_info = metadata.token_info().unwrap();
+
+        let get = |key: &str| {
+            token_info
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| String::from_utf8(v.clone()).unwrap())
+                .unwrap()
+        };
+
+        assert_eq!(get("name"), "Tzwitter #0");
+        assert_eq!(get("description"), "Hello world");
+        assert_eq!(
+            get("creators"),
+            "[\"tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv\"]"
+        );
+        assert_eq!(
+            get("attributes"),
+            "[{\"name\":\"likes\",\"value\":\"3\",\"type\":\"integer\"},{\"name\":\"written_level\",\"value\":\"10\",\"type\":\"integer\"},{\"name\":\"collected_level\",\"value\":\"12\",\"type\":\"integer\"}]"
+        );
+    }
+}
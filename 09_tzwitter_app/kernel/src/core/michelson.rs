@@ -0,0 +1,102 @@
+use nom::{
+    bytes::complete::tag,
+    combinator::map,
+    multi::many0,
+    sequence::{pair, preceded},
+};
+use tezos_data_encoding::{
+    enc::{BinResult, BinWriter},
+    encoding::{Encoding, HasEncoding},
+    nom::{dynamic, NomReader, NomResult},
+};
+use tezos_smart_rollup::michelson::Michelson;
+
+/// Micheline tag of a sequence
+const SEQUENCE_TAG: u8 = 0x02;
+/// Micheline tag of a primitive with two arguments and without annotations
+const PRIM_2_ARGS_NO_ANNOTS_TAG: u8 = 0x07;
+/// Michelson code of the Elt primitive
+const ELT_PRIM_CODE: u8 = 0x04;
+
+/// Michelson map
+///
+/// A map is encoded as a sequence of `Elt key value`
+/// The elements should be sorted by key, the layer 1 rejects unsorted maps
+#[derive(Debug, PartialEq, Eq)]
+pub struct MichelsonMap<K, V>(pub Vec<(K, V)>);
+
+impl<K: Michelson, V: Michelson> Michelson for MichelsonMap<K, V> {}
+
+impl<K, V> HasEncoding for MichelsonMap<K, V> {
+    fn encoding() -> Encoding {
+        Encoding::Custom
+    }
+}
+
+impl<K: BinWriter, V: BinWriter> BinWriter for MichelsonMap<K, V> {
+    fn bin_write(&self, output: &mut Vec<u8>) -> BinResult {
+        let mut elements = Vec::default();
+        for (key, value) in &self.0 {
+            elements.push(PRIM_2_ARGS_NO_ANNOTS_TAG);
+            elements.push(ELT_PRIM_CODE);
+            key.bin_write(&mut elements)?;
+            value.bin_write(&mut elements)?;
+        }
+
+        output.push(SEQUENCE_TAG);
+        output.extend_from_slice(&(elements.len() as u32).to_be_bytes());
+        output.append(&mut elements);
+        Ok(())
+    }
+}
+
+impl<K: NomReader, V: NomReader> NomReader for MichelsonMap<K, V> {
+    fn nom_read(input: &[u8]) -> NomResult<Self> {
+        let elt = preceded(
+            tag(&[PRIM_2_ARGS_NO_ANNOTS_TAG, ELT_PRIM_CODE][..]),
+            pair(K::nom_read, V::nom_read),
+        );
+        map(
+            preceded(tag(&[SEQUENCE_TAG][..]), dynamic(many0(elt))),
+            MichelsonMap,
+        )(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tezos_data_encoding::{enc::BinWriter, nom::NomReader};
+    use tezos_smart_rollup::michelson::{MichelsonBytes, MichelsonString};
+
+    use super::MichelsonMap;
+
+    #[test]
+    fn test_map_encoding() {
+        let map = MichelsonMap(vec![(
+            MichelsonString("a".to_string()),
+            MichelsonBytes(vec![0xff]),
+        )]);
+        let mut output = Vec::default();
+        map.bin_write(&mut output).unwrap();
+
+        // { Elt "a" 0xff }
+        let expected = hex::decode("020000000e07040100000001610a00000001ff").unwrap();
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn test_map_decoding() {
+        let bytes = hex::decode("020000000e07040100000001610a00000001ff").unwrap();
+        let (remaining, map) =
+            MichelsonMap::<MichelsonString, MichelsonBytes>::nom_read(&bytes).unwrap();
+
+        assert!(remaining.is_empty());
+        assert_eq!(
+            map,
+            MichelsonMap(vec![(
+                MichelsonString("a".to_string()),
+                MichelsonBytes(vec![0xff])
+            )])
+        );
+    }
+}
@@ -1,10 +1,17 @@
-pub mod account;
+pub mod config;
+pub mod direct_message;
 pub mod error;
-pub mod hash;
-pub mod message;
-pub mod nonce;
-pub mod public_key;
-pub mod public_key_hash;
-pub mod receipt;
-pub mod signature;
+pub mod event;
+pub mod input;
+pub mod metadata;
+pub mod michelson;
 pub mod tweet;
+pub mod withdrawal;
+
+// The types shared with the clients
+pub use tzwitter_types::{
+    hash, listing, message, metrics, nonce, public_key, public_key_hash, signature,
+};
+
+// The accounts and the receipts handled by the pipeline
+pub use tzwitter_pipeline::{account, receipt};
@@ -0,0 +1,12 @@
+use crate::core::public_key_hash::PublicKeyHash;
+
+/// An outbox message minting a tweet on the layer 1
+///
+/// The level and the index identify the outbox message,
+/// they are needed by the user to execute it on the layer 1
+pub struct Withdrawal {
+    pub level: u32,
+    pub index: u32,
+    pub tweet_id: u64,
+    pub owner: PublicKeyHash,
+}
@@ -1,51 +1,245 @@
-use crate::core::message::{Content, Message};
+use crate::core::account::Account;
+use crate::core::event::{Event, EventKind};
+use crate::core::hash::Blake2b;
+use crate::core::input::Input;
+use crate::core::message::{AcceptOffer, Buy, Content, List, MakeOffer, ReportTweet, Transfer};
+use crate::core::metrics::Metrics;
+use crate::core::public_key::PublicKey;
 use crate::core::public_key_hash::PublicKeyHash;
 use crate::core::receipt::Receipt;
 
 // src/lib.rs
-use storage::{read_account, store_account, store_receipt};
+use pipeline::{process, Application};
+use storage::{read_config, store_event};
 use tezos_smart_rollup::{kernel_entry, prelude::*};
 
-mod constants;
-mod core;
+pub mod constants;
+pub mod core;
 mod stages;
 mod storage;
+#[cfg(test)]
+mod testing;
+
+/// The read model is shared with the clients
+pub use tzwitter_types::read_model;
+
+/// The pipeline of the signed messages
+pub use tzwitter_pipeline as pipeline;
 
 use crate::core::error::*;
 use stages::{
-    create_tweet, like_tweet, read_input, transfer_tweet, verify_nonce, verify_signature,
-    withdraw_tweet,
+    accept_offer, block_account, buy_tweet, collect_receipts, confirm_withdrawal, create_tweet,
+    deposit, expire_withdrawals, hide_tweet, like_tweet, list_tweet, make_offer, prune_events,
+    prune_metrics, remove_direct_message, report_tweet, send_direct_message, transfer_tweet,
+    unblock_account, unlist_tweet, withdraw_balance, withdraw_tweet,
 };
 
-/// A step is processing only one message from the inbox
+/// The tzwitter application
 ///
-/// It will execute several sub steps:
-/// - verify the signature of the message
-/// - verify the nonce of the message
-/// - handle the message
-fn step<R: Runtime>(host: &mut R, message: Message, level: u32) -> Result<()> {
-    let public_key = message.public_key();
-    let public_key_hash = PublicKeyHash::from(public_key);
-    debug_msg!(host, "Message is deserialized\n");
-
-    let inner = verify_signature(message)?;
-    debug_msg!(host, "Signature is correct\n");
-
-    // Verify the nonce
-    let account = read_account(host, public_key_hash)?;
-    let content = verify_nonce(inner, account.nonce())?;
-    let account = account.increment_nonce();
-    let _ = store_account(host, &account)?;
-
-    // Interpret the message
-    match content {
-        Content::PostTweet(post_tweet) => create_tweet(host, &account, post_tweet)?,
-        Content::LikeTweet(tweet_id) => like_tweet(host, &account, &tweet_id)?,
-        Content::Transfer(transfer) => transfer_tweet(host, &account, &transfer)?,
-        Content::Collect(twwet_id) => withdraw_tweet(host, level, &account, &twwet_id)?,
-    };
+/// Its messages go through the signature and nonce stages of the pipeline
+pub struct Tzwitter;
+
+impl Application for Tzwitter {
+    type Content = Content;
+    type Error = Error;
+
+    fn read_account<R: Runtime>(host: &mut R, public_key_hash: PublicKeyHash) -> Result<Account> {
+        storage::read_account(host, public_key_hash)
+    }
+
+    fn store_account<R: Runtime>(host: &mut R, account: &Account) -> Result<()> {
+        storage::store_account(host, account).map(|_| ())
+    }
+
+    fn read_public_key<R: Runtime>(
+        host: &mut R,
+        public_key_hash: &PublicKeyHash,
+    ) -> Result<Option<PublicKey>> {
+        storage::read_public_key(host, public_key_hash)
+    }
+
+    fn store_public_key<R: Runtime>(
+        host: &mut R,
+        public_key_hash: &PublicKeyHash,
+        public_key: &PublicKey,
+    ) -> Result<()> {
+        storage::store_public_key(host, public_key_hash, public_key)
+    }
+
+    /// The receipts are deleted by the garbage collection of the start of level
+    fn store_receipt<R: Runtime>(host: &mut R, receipt: &Receipt) -> Result<()> {
+        storage::store_receipt(host, receipt).map(|_| ())
+    }
+
+    fn add_level_metrics<R: Runtime>(
+        host: &mut R,
+        level: &u32,
+        kind: &str,
+        metrics: &Metrics,
+    ) -> Result<()> {
+        storage::add_level_metrics(host, level, kind, metrics)
+    }
+
+    /// Interprets the content of the message and emits its event
+    fn apply<R: Runtime>(
+        host: &mut R,
+        level: u32,
+        hash: &Blake2b,
+        account: &Account,
+        content: Content,
+    ) -> Result<()> {
+        let event = match content {
+            Content::PostTweet(post_tweet) => {
+                let author = post_tweet.author.clone();
+                let tweet_id = create_tweet(host, level, account, post_tweet)?;
+                EventKind::TweetPosted { tweet_id, author }
+            }
+            Content::LikeTweet(tweet_id) => {
+                like_tweet(host, account, &tweet_id)?;
+                EventKind::TweetLiked {
+                    tweet_id,
+                    account: account.public_key_hash.clone(),
+                }
+            }
+            Content::Transfer(transfer) => {
+                transfer_tweet(host, account, &transfer)?;
+                let Transfer {
+                    tweet_id,
+                    destination,
+                } = transfer;
+                EventKind::TweetTransferred {
+                    tweet_id,
+                    from: account.public_key_hash.clone(),
+                    to: destination,
+                }
+            }
+            Content::Collect(tweet_id) => {
+                withdraw_tweet(host, level, account, &tweet_id)?;
+                EventKind::TweetCollected {
+                    tweet_id,
+                    owner: account.public_key_hash.clone(),
+                }
+            }
+            Content::List(list) => {
+                list_tweet(host, account, &list)?;
+                let List { tweet_id, price } = list;
+                EventKind::TweetListed {
+                    tweet_id,
+                    seller: account.public_key_hash.clone(),
+                    price,
+                }
+            }
+            Content::Unlist(tweet_id) => {
+                unlist_tweet(host, account, &tweet_id)?;
+                EventKind::TweetUnlisted {
+                    tweet_id,
+                    seller: account.public_key_hash.clone(),
+                }
+            }
+            Content::Buy(buy) => {
+                let listing = buy_tweet(host, account, &buy)?;
+                let Buy { tweet_id, .. } = buy;
+                EventKind::TweetSold {
+                    tweet_id,
+                    seller: listing.seller,
+                    buyer: account.public_key_hash.clone(),
+                    price: listing.price,
+                }
+            }
+            Content::MakeOffer(offer) => {
+                make_offer(host, account, &offer)?;
+                let MakeOffer { tweet_id, price } = offer;
+                EventKind::OfferMade {
+                    tweet_id,
+                    buyer: account.public_key_hash.clone(),
+                    price,
+                }
+            }
+            Content::AcceptOffer(offer) => {
+                let price = accept_offer(host, account, &offer)?;
+                let AcceptOffer { tweet_id, buyer } = offer;
+                EventKind::TweetSold {
+                    tweet_id,
+                    seller: account.public_key_hash.clone(),
+                    buyer,
+                    price,
+                }
+            }
+            Content::ReportTweet(report) => {
+                let hidden = report_tweet(host, account, &report)?;
+                let ReportTweet { tweet_id, .. } = report;
+                let reported = EventKind::TweetReported {
+                    tweet_id,
+                    account: account.public_key_hash.clone(),
+                };
+                if !hidden {
+                    reported
+                } else {
+                    // The report has reached the threshold, the tweet is hidden after being reported
+                    store_event(host, &level, &Event::new(hash, reported))?;
+                    EventKind::TweetHidden { tweet_id }
+                }
+            }
+            Content::Hide(tweet_id) => {
+                hide_tweet(host, account, &tweet_id)?;
+                EventKind::TweetHidden { tweet_id }
+            }
+            Content::SendDirectMessage(direct_message) => {
+                let to = direct_message.to.clone();
+                let index = send_direct_message(host, level, account, direct_message)?;
+                EventKind::DirectMessageSent {
+                    from: account.public_key_hash.clone(),
+                    to,
+                    index,
+                }
+            }
+            Content::DeleteDirectMessage(index) => {
+                remove_direct_message(host, account, &index)?;
+                EventKind::DirectMessageDeleted {
+                    account: account.public_key_hash.clone(),
+                    index,
+                }
+            }
+            Content::Block(blocked) => {
+                block_account(host, account, &blocked)?;
+                EventKind::AccountBlocked {
+                    account: account.public_key_hash.clone(),
+                    blocked,
+                }
+            }
+            Content::Unblock(unblocked) => {
+                unblock_account(host, account, &unblocked)?;
+                EventKind::AccountUnblocked {
+                    account: account.public_key_hash.clone(),
+                    unblocked,
+                }
+            }
+            Content::WithdrawBalance(amount) => {
+                withdraw_balance(host, level, account, &amount)?;
+                EventKind::BalanceWithdrawn {
+                    account: account.public_key_hash.clone(),
+                    amount,
+                }
+            }
+        };
 
-    Ok(())
+        let event = Event::new(hash, event);
+        let _ = store_event(host, &level, &event)?;
+        Ok(())
+    }
+}
+
+/// Housekeeping done at the beginning of each level
+///
+/// - expire the withdrawals that can't be executed anymore
+/// - prune the old events and metrics
+/// - delete the old receipts
+fn start_of_level<R: Runtime>(host: &mut R, level: u32) -> Result<()> {
+    let config = read_config(host)?;
+    expire_withdrawals(host, level)?;
+    prune_events(host, level, &config)?;
+    prune_metrics(host, level, &config)?;
+    collect_receipts(host, level, &config)
 }
 
 /// Process all the inbox
@@ -55,23 +249,36 @@ fn step<R: Runtime>(host: &mut R, message: Message, level: u32) -> Result<()> {
 /// Then all the errors, will be stored in a receipt
 /// Continue until the inbox is emptied
 ///
+/// The start of level is used to clean the state
+/// The mint confirmations are used to keep track of the withdrawals
+/// The deposits credit the balances used by the marketplace
+///
 /// This function stop its execution when a RuntimeError happens
 ///
 /// TODO: it can count ticks and reboot the kernel between two inbox message
 fn execute<R: Runtime>(host: &mut R) -> Result<()> {
-    let message = read_input(host);
-    match message {
+    let input = stages::read_input(host);
+    match input {
         Err(ReadInputError::EndOfInbox) => Ok(()),
         Err(ReadInputError::Runtime(err)) => Err(Error::Runtime(err)),
         Err(_) => execute(host),
-        Ok((message, level)) => {
-            // If the message is processed we can extract the hash of the message
-            let hash = message.hash();
-            let result = step(host, message, level);
-
-            let receipt = Receipt::new(hash, &result);
-            let _ = store_receipt(host, &receipt)?;
-
+        Ok((Input::StartOfLevel, level)) => match start_of_level(host, level) {
+            Err(Error::Runtime(err)) => Err(Error::Runtime(err)),
+            Err(_) => execute(host),
+            Ok(()) => execute(host),
+        },
+        Ok((Input::MintConfirmation(tweet_id), _)) => match confirm_withdrawal(host, &tweet_id) {
+            Err(Error::Runtime(err)) => Err(Error::Runtime(err)),
+            Err(_) => execute(host),
+            Ok(()) => execute(host),
+        },
+        Ok((Input::Deposit { account, amount }, _)) => match deposit(host, &account, &amount) {
+            Err(Error::Runtime(err)) => Err(Error::Runtime(err)),
+            Err(_) => execute(host),
+            Ok(()) => execute(host),
+        },
+        Ok((Input::Message(message), level)) => {
+            let result = process::<Tzwitter, R>(host, message, level);
             match result {
                 Err(Error::Runtime(err)) => Err(Error::Runtime(err)),
                 Err(_) => execute(host),
@@ -91,18 +298,54 @@ pub fn entry<R: Runtime>(host: &mut R) {
 
 kernel_entry!(entry);
 
+/// Reads the inputs of the inbox, only used by the fuzz targets
+#[cfg(feature = "fuzzing")]
+pub use stages::read_input;
+
 #[cfg(test)]
 mod tests {
 
     use tezos_data_encoding::enc::BinWriter;
-    use tezos_smart_rollup::{prelude::*, storage::path::RefPath, testing::prelude::MockHost};
+    use tezos_smart_rollup::{
+        prelude::*,
+        storage::path::{OwnedPath, RefPath},
+        testing::prelude::MockHost,
+    };
 
     use crate::{
-        constants::MAGIC_BYTE,
-        core::message::Message,
-        stages::read_input,
-        step,
-        storage::{exists, read_u64},
+        constants::{
+            DEFAULT_EVENTS_RETENTION, DEFAULT_RECEIPTS_RETENTION, DIRECT_MESSAGES_PER_LEVEL,
+            EVENTS_GC_BUDGET, INBOX_QUOTA, MAGIC_BYTE, MAX_CIPHERTEXT_SIZE, MAX_REPORT_REASON_SIZE,
+            METRICS_GC_BUDGET, OUTBOX_EXPIRATION_WINDOW, RECEIPTS_GC_BUDGET,
+        },
+        core::{
+            account::Account,
+            error::Error,
+            event::{Event, EventKind},
+            hash::Blake2b,
+            input::Input,
+            message::{
+                AcceptOffer, Buy, Content, List, MakeOffer, Message, PostTweet, ReportTweet,
+                SendDirectMessage, Transfer,
+            },
+            metrics::Metrics,
+            nonce::Nonce,
+            public_key_hash::PublicKeyHash,
+            receipt::Receipt,
+        },
+        pipeline::{step, Application},
+        stages::{
+            accept_offer, block_account, buy_tweet, confirm_withdrawal, create_tweet, deposit,
+            expire_withdrawals, hide_tweet, like_tweet, list_tweet, make_offer, read_input,
+            remove_direct_message, report_tweet, send_direct_message, transfer_tweet,
+            unblock_account, withdraw_balance, withdraw_tweet,
+        },
+        start_of_level,
+        storage::{
+            add_level_metrics, exists, inbox_size, is_owner, read_balance, read_listing,
+            read_outbox_size, read_tweet_owner, read_u64, store_event, store_receipt,
+        },
+        Tzwitter,
     };
 
     /// Assert a path exists in the storage
@@ -170,7 +413,53 @@ mod tests {
     }
 
     fn next_input<R: Runtime>(host: &mut R) -> Message {
-        read_input(host).unwrap().0
+        match read_input(host).unwrap().0 {
+            Input::Message(message) => message,
+            _ => panic!("The input should be a tzwitter message"),
+        }
+    }
+
+    /// Account of the author of the input_1 tweet
+    fn account() -> Account {
+        Account {
+            public_key_hash: PublicKeyHash::from_b58("tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv")
+                .unwrap(),
+            nonce: Nonce(1),
+        }
+    }
+
+    /// Account of a given address
+    fn account_of(public_key_hash: &str) -> Account {
+        Account {
+            public_key_hash: PublicKeyHash::from_b58(public_key_hash).unwrap(),
+            nonce: Nonce(1),
+        }
+    }
+
+    /// Post a tweet, and gives it to the seller
+    fn seller_tweet<R: Runtime>(host: &mut R, seller: &Account) {
+        let author = account();
+        let post_tweet = PostTweet {
+            author: author.public_key_hash.clone(),
+            content: "Hello world".to_string(),
+        };
+        create_tweet(host, 0, &author, post_tweet).unwrap();
+        let transfer = Transfer {
+            tweet_id: 0,
+            destination: seller.public_key_hash.clone(),
+        };
+        transfer_tweet(host, &author, &transfer).unwrap();
+    }
+
+    /// Post a tweet and collect it at the given level
+    fn collect_tweet<R: Runtime>(host: &mut R, level: u32) {
+        let account = account();
+        let post_tweet = PostTweet {
+            author: account.public_key_hash.clone(),
+            content: "Hello world".to_string(),
+        };
+        create_tweet(host, level, &account, post_tweet).unwrap();
+        withdraw_tweet(host, level, &account, &0).unwrap();
     }
 
     #[test]
@@ -183,12 +472,14 @@ mod tests {
         // host.as_mut().add_next_inputs(0, inputs);
 
         let message = next_input(&mut host);
-        let res = step(&mut host, message, 0);
+        let res = step::<Tzwitter, _>(&mut host, message, 0);
 
         assert!(res.is_ok());
 
         assert_exist(&mut host, "/tweets/0");
         assert_u64(&mut host, "/tweets/0/likes", Some(0));
+        assert_exist(&mut host, "/tweets/0/written_level");
+        assert_exist(&mut host, "/events/0/0");
         assert_exist(
             &mut host,
             "/accounts/tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv/tweets/owned/0",
@@ -207,9 +498,9 @@ mod tests {
         host.add_external(input);
 
         let message = next_input(&mut host);
-        let res1 = step(&mut host, message, 0);
+        let res1 = step::<Tzwitter, _>(&mut host, message, 0);
         let message = next_input(&mut host);
-        let res2 = step(&mut host, message, 0);
+        let res2 = step::<Tzwitter, _>(&mut host, message, 0);
 
         assert!(res1.is_ok());
         assert!(res2.is_err());
@@ -224,9 +515,9 @@ mod tests {
         host.add_external(input_2);
 
         let message = next_input(&mut host);
-        let res_1 = step(&mut host, message, 0);
+        let res_1 = step::<Tzwitter, _>(&mut host, message, 0);
         let message = next_input(&mut host);
-        let res_2 = step(&mut host, message, 0);
+        let res_2 = step::<Tzwitter, _>(&mut host, message, 0);
 
         assert!(res_1.is_ok());
         assert!(res_2.is_ok());
@@ -246,9 +537,9 @@ mod tests {
         host.add_external(input_2);
 
         let message = next_input(&mut host);
-        let res_1 = step(&mut host, message, 0);
+        let res_1 = step::<Tzwitter, _>(&mut host, message, 0);
         let message = next_input(&mut host);
-        let res_2 = step(&mut host, message, 0);
+        let res_2 = step::<Tzwitter, _>(&mut host, message, 0);
 
         assert!(res_1.is_ok());
         assert!(res_2.is_ok());
@@ -269,11 +560,11 @@ mod tests {
         host.add_external(input_3);
 
         let message = next_input(&mut host);
-        let res_1 = step(&mut host, message, 0);
+        let res_1 = step::<Tzwitter, _>(&mut host, message, 0);
         let message = next_input(&mut host);
-        let res_2 = step(&mut host, message, 0);
+        let res_2 = step::<Tzwitter, _>(&mut host, message, 0);
         let message = next_input(&mut host);
-        let res_3 = step(&mut host, message, 0);
+        let res_3 = step::<Tzwitter, _>(&mut host, message, 0);
 
         assert!(res_1.is_ok());
         assert!(res_2.is_ok());
@@ -293,9 +584,9 @@ mod tests {
         host.add_external(input_2);
 
         let message = next_input(&mut host);
-        let res_1 = step(&mut host, message, 0);
+        let res_1 = step::<Tzwitter, _>(&mut host, message, 0);
         let message = next_input(&mut host);
-        let res_2 = step(&mut host, message, 0);
+        let res_2 = step::<Tzwitter, _>(&mut host, message, 0);
 
         assert!(res_1.is_ok());
         assert!(res_2.is_ok());
@@ -316,5 +607,771 @@ mod tests {
             &mut host,
             "/accounts/tz1TGu6TN5GSez2ndXXeDX6LgUDvLzPLqgYV/tweets/writte/0",
         );
+
+        let owner = read_tweet_owner(&mut host, &0).unwrap().unwrap();
+        assert_eq!(owner.to_string(), "tz1TGu6TN5GSez2ndXXeDX6LgUDvLzPLqgYV");
+    }
+
+    #[test]
+    fn test_self_transfer() {
+        let mut host = MockHost::default();
+        let account = account();
+        let post_tweet = PostTweet {
+            author: account.public_key_hash.clone(),
+            content: "Hello world".to_string(),
+        };
+        create_tweet(&mut host, 0, &account, post_tweet).unwrap();
+
+        let transfer = Transfer {
+            tweet_id: 0,
+            destination: account.public_key_hash.clone(),
+        };
+        let res = transfer_tweet(&mut host, &account, &transfer);
+
+        assert!(matches!(res, Err(Error::SelfTransfer)));
+        assert_exist(
+            &mut host,
+            "/accounts/tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv/tweets/owned/0",
+        );
+    }
+
+    #[test]
+    fn test_transfer_of_collected_tweet() {
+        let mut host = MockHost::default();
+        collect_tweet(&mut host, 10);
+
+        let transfer = Transfer {
+            tweet_id: 0,
+            destination: PublicKeyHash::from_b58("tz1TGu6TN5GSez2ndXXeDX6LgUDvLzPLqgYV").unwrap(),
+        };
+        let res = transfer_tweet(&mut host, &account(), &transfer);
+
+        assert!(matches!(res, Err(Error::TweetBeingCollected)));
+        assert_not_exists(
+            &mut host,
+            "/accounts/tz1TGu6TN5GSez2ndXXeDX6LgUDvLzPLqgYV/tweets/owned/0",
+        );
+    }
+
+    #[test]
+    fn test_owner_of_legacy_tweet() {
+        let mut host = MockHost::default();
+        let path = RefPath::assert_from(
+            "/accounts/tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv/tweets/owned/0".as_bytes(),
+        );
+        host.store_write(&path, &[0x01], 0).unwrap();
+
+        let other = PublicKeyHash::from_b58("tz1TGu6TN5GSez2ndXXeDX6LgUDvLzPLqgYV").unwrap();
+        assert!(is_owner(&mut host, &account().public_key_hash, &0).is_ok());
+        assert!(is_owner(&mut host, &other, &0).is_err());
+    }
+
+    #[test]
+    fn test_withdrawal_is_registered() {
+        let mut host = MockHost::default();
+        collect_tweet(&mut host, 10);
+
+        assert_u64(&mut host, "/outbox/10/0/tweet_id", Some(0));
+        assert_exist(&mut host, "/outbox/10/0/owner");
+        assert_exist(&mut host, "/tweets/0/withdrawal/level");
+        assert_exist(&mut host, "/tweets/0/withdrawal/index");
+        assert_exist(
+            &mut host,
+            "/accounts/tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv/collecting/0",
+        );
+    }
+
+    #[test]
+    fn test_withdrawal_confirmation() {
+        let mut host = MockHost::default();
+        collect_tweet(&mut host, 10);
+
+        let res = confirm_withdrawal(&mut host, &0);
+
+        assert!(res.is_ok());
+        assert_exist(&mut host, "/tweets/0/collected_level");
+        assert_not_exists(&mut host, "/tweets/0/withdrawal");
+        assert_not_exists(&mut host, "/outbox/10/0");
+        assert_not_exists(
+            &mut host,
+            "/accounts/tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv/collecting/0",
+        );
+    }
+
+    #[test]
+    fn test_confirmation_of_unknown_withdrawal() {
+        let mut host = MockHost::default();
+        let res = confirm_withdrawal(&mut host, &0);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_withdrawal_expiration() {
+        let mut host = MockHost::default();
+        collect_tweet(&mut host, 10);
+
+        expire_withdrawals(&mut host, 10 + OUTBOX_EXPIRATION_WINDOW).unwrap();
+        assert_exist(&mut host, "/tweets/0/collected_level");
+
+        expire_withdrawals(&mut host, 10 + OUTBOX_EXPIRATION_WINDOW + 1).unwrap();
+        assert_not_exists(&mut host, "/tweets/0/collected_level");
+        assert_not_exists(&mut host, "/tweets/0/withdrawal");
+        assert_not_exists(&mut host, "/outbox/10");
+        assert_not_exists(
+            &mut host,
+            "/accounts/tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv/collecting/0",
+        );
+
+        // The tweet can be collected again
+        let res = withdraw_tweet(&mut host, 20_200, &account(), &0);
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn test_events() {
+        let mut host = MockHost::default();
+        host.add_external(input_1());
+        host.add_external(input_like());
+
+        let message = next_input(&mut host);
+        step::<Tzwitter, _>(&mut host, message, 5).unwrap();
+        let message = next_input(&mut host);
+        step::<Tzwitter, _>(&mut host, message, 5).unwrap();
+
+        assert_exist(&mut host, "/events/5/0");
+        assert_exist(&mut host, "/events/5/1");
+        assert_not_exists(&mut host, "/events/5/2");
+
+        start_of_level(&mut host, 5 + DEFAULT_EVENTS_RETENTION - 1).unwrap();
+        assert_exist(&mut host, "/events/5");
+
+        start_of_level(&mut host, 5 + DEFAULT_EVENTS_RETENTION).unwrap();
+        assert_not_exists(&mut host, "/events/5");
+    }
+
+    #[test]
+    fn test_events_retention_reduced() {
+        let mut host = MockHost::default();
+        let hash = Blake2b::from("event".as_bytes());
+        for level in 1..=30 {
+            let event = Event::new(&hash, EventKind::TweetHidden { tweet_id: 0 });
+            store_event(&mut host, &level, &event).unwrap();
+        }
+        let retention = RefPath::assert_from(b"/config/events_retention");
+        host.store_write(&retention, &5_u32.to_be_bytes(), 0)
+            .unwrap();
+
+        // The events of at most EVENTS_GC_BUDGET levels are pruned at each level
+        start_of_level(&mut host, 31).unwrap();
+        assert_not_exists(&mut host, &format!("/events/{}", EVENTS_GC_BUDGET));
+        assert_exist(&mut host, &format!("/events/{}", EVENTS_GC_BUDGET + 1));
+
+        start_of_level(&mut host, 32).unwrap();
+        start_of_level(&mut host, 33).unwrap();
+        assert_not_exists(&mut host, "/events/28");
+        assert_exist(&mut host, "/events/29");
+    }
+
+    #[test]
+    fn test_metrics_retention_reduced() {
+        let mut host = MockHost::default();
+        for level in 1..=30 {
+            add_level_metrics(&mut host, &level, "like_tweet", &Metrics::default()).unwrap();
+        }
+        let retention = RefPath::assert_from(b"/config/receipts_retention");
+        host.store_write(&retention, &5_u32.to_be_bytes(), 0)
+            .unwrap();
+
+        // The metrics of at most METRICS_GC_BUDGET levels are pruned at each level
+        start_of_level(&mut host, 31).unwrap();
+        assert_not_exists(&mut host, &format!("/metrics/{}", METRICS_GC_BUDGET));
+        assert_exist(&mut host, &format!("/metrics/{}", METRICS_GC_BUDGET + 1));
+
+        start_of_level(&mut host, 32).unwrap();
+        start_of_level(&mut host, 33).unwrap();
+        assert_not_exists(&mut host, "/metrics/28");
+        assert_exist(&mut host, "/metrics/29");
+    }
+
+    /// Stores a successful receipt at the given level, and returns its hash
+    fn receipt_at<R: Runtime>(host: &mut R, level: u32, n: u32) -> String {
+        let hash = Blake2b::from(format!("{}-{}", level, n).as_bytes());
+        let receipt = Receipt::new(hash, level, &Ok::<(), Error>(()));
+        store_receipt(host, &receipt).unwrap();
+        receipt.hash().to_string()
+    }
+
+    #[test]
+    fn test_receipts_gc() {
+        let mut host = MockHost::default();
+        let old = receipt_at(&mut host, 1, 0);
+        let recent = receipt_at(&mut host, 2, 0);
+
+        assert_exist(&mut host, &format!("/receipts/{}/success", old));
+        assert_exist(&mut host, "/receipts_by_level/1/0");
+
+        start_of_level(&mut host, 1 + DEFAULT_RECEIPTS_RETENTION).unwrap();
+
+        assert_not_exists(&mut host, &format!("/receipts/{}", old));
+        assert_not_exists(&mut host, "/receipts_by_level/1");
+        assert_exist(&mut host, &format!("/receipts/{}/success", recent));
+        assert_exist(&mut host, "/receipts_by_level/2/0");
+    }
+
+    #[test]
+    fn test_receipts_gc_keeps_fresh_receipt() {
+        let mut host = MockHost::default();
+        let hash = receipt_at(&mut host, 1, 0);
+
+        // The same message is received again at a later level
+        let receipt = Receipt::new(Blake2b::from("1-0".as_bytes()), 2, &Ok::<(), Error>(()));
+        store_receipt(&mut host, &receipt).unwrap();
+
+        start_of_level(&mut host, 1 + DEFAULT_RECEIPTS_RETENTION).unwrap();
+        assert_not_exists(&mut host, "/receipts_by_level/1");
+        assert_exist(&mut host, &format!("/receipts/{}/success", hash));
+
+        start_of_level(&mut host, 2 + DEFAULT_RECEIPTS_RETENTION).unwrap();
+        assert_not_exists(&mut host, &format!("/receipts/{}", hash));
+    }
+
+    #[test]
+    fn test_receipts_gc_budget() {
+        let mut host = MockHost::default();
+        let hashes: Vec<String> = (0..RECEIPTS_GC_BUDGET + 1)
+            .map(|n| receipt_at(&mut host, 1, n))
+            .collect();
+
+        start_of_level(&mut host, 1 + DEFAULT_RECEIPTS_RETENTION).unwrap();
+
+        // The last receipt is deleted at the next level
+        let last = hashes.last().unwrap();
+        assert_not_exists(&mut host, &format!("/receipts/{}", hashes[0]));
+        assert_exist(&mut host, &format!("/receipts/{}/success", last));
+
+        start_of_level(&mut host, 2 + DEFAULT_RECEIPTS_RETENTION).unwrap();
+        assert_not_exists(&mut host, &format!("/receipts/{}", last));
+    }
+
+    #[test]
+    fn test_buy_listed_tweet() {
+        let mut host = MockHost::default();
+        let seller = account_of("tz1TGu6TN5GSez2ndXXeDX6LgUDvLzPLqgYV");
+        let buyer = account_of("tz1SodoUsWVe1Yey9eMFbqRUtNpBWfir5NRr");
+        seller_tweet(&mut host, &seller);
+        deposit(&mut host, &buyer.public_key_hash, &1_500).unwrap();
+
+        let list = List {
+            tweet_id: 0,
+            price: 1_000,
+        };
+        let buy = Buy {
+            tweet_id: 0,
+            max_price: 1_000,
+        };
+        list_tweet(&mut host, &seller, &list).unwrap();
+        let res = buy_tweet(&mut host, &buyer, &buy);
+
+        assert!(res.is_ok());
+        let owner = read_tweet_owner(&mut host, &0).unwrap().unwrap();
+        assert_eq!(owner, buyer.public_key_hash);
+        assert!(read_listing(&mut host, &0).unwrap().is_none());
+        assert_eq!(
+            read_balance(&mut host, &buyer.public_key_hash).unwrap(),
+            500
+        );
+        assert_eq!(
+            read_balance(&mut host, &seller.public_key_hash).unwrap(),
+            950
+        );
+        assert_eq!(
+            read_balance(&mut host, &account().public_key_hash).unwrap(),
+            50
+        );
+    }
+
+    #[test]
+    fn test_buy_with_insufficient_balance() {
+        let mut host = MockHost::default();
+        let seller = account_of("tz1TGu6TN5GSez2ndXXeDX6LgUDvLzPLqgYV");
+        let buyer = account_of("tz1SodoUsWVe1Yey9eMFbqRUtNpBWfir5NRr");
+        seller_tweet(&mut host, &seller);
+        deposit(&mut host, &buyer.public_key_hash, &999).unwrap();
+
+        let list = List {
+            tweet_id: 0,
+            price: 1_000,
+        };
+        let buy = Buy {
+            tweet_id: 0,
+            max_price: 1_000,
+        };
+        list_tweet(&mut host, &seller, &list).unwrap();
+        let res = buy_tweet(&mut host, &buyer, &buy);
+
+        assert!(matches!(res, Err(Error::InsufficientBalance)));
+        let owner = read_tweet_owner(&mut host, &0).unwrap().unwrap();
+        assert_eq!(owner, seller.public_key_hash);
+        assert!(read_listing(&mut host, &0).unwrap().is_some());
+        assert_eq!(
+            read_balance(&mut host, &buyer.public_key_hash).unwrap(),
+            999
+        );
+        assert_eq!(read_balance(&mut host, &seller.public_key_hash).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_buy_above_max_price() {
+        let mut host = MockHost::default();
+        let seller = account_of("tz1TGu6TN5GSez2ndXXeDX6LgUDvLzPLqgYV");
+        let buyer = account_of("tz1SodoUsWVe1Yey9eMFbqRUtNpBWfir5NRr");
+        seller_tweet(&mut host, &seller);
+        deposit(&mut host, &buyer.public_key_hash, &2_000).unwrap();
+
+        // The price is raised after the buy has been signed
+        let buy = Buy {
+            tweet_id: 0,
+            max_price: 1_000,
+        };
+        let list = List {
+            tweet_id: 0,
+            price: 1_500,
+        };
+        list_tweet(&mut host, &seller, &list).unwrap();
+        let res = buy_tweet(&mut host, &buyer, &buy);
+
+        assert!(matches!(res, Err(Error::PriceTooHigh)));
+        let owner = read_tweet_owner(&mut host, &0).unwrap().unwrap();
+        assert_eq!(owner, seller.public_key_hash);
+        assert_eq!(
+            read_balance(&mut host, &buyer.public_key_hash).unwrap(),
+            2_000
+        );
+    }
+
+    #[test]
+    fn test_buy_with_seller_balance_overflow() {
+        let mut host = MockHost::default();
+        let seller = account_of("tz1TGu6TN5GSez2ndXXeDX6LgUDvLzPLqgYV");
+        let buyer = account_of("tz1SodoUsWVe1Yey9eMFbqRUtNpBWfir5NRr");
+        seller_tweet(&mut host, &seller);
+        deposit(&mut host, &buyer.public_key_hash, &1_000).unwrap();
+        deposit(&mut host, &seller.public_key_hash, &u64::MAX).unwrap();
+
+        let list = List {
+            tweet_id: 0,
+            price: 1_000,
+        };
+        let buy = Buy {
+            tweet_id: 0,
+            max_price: 1_000,
+        };
+        list_tweet(&mut host, &seller, &list).unwrap();
+        let res = buy_tweet(&mut host, &buyer, &buy);
+
+        // Nothing is debited nor credited when the credit of the seller overflows
+        assert!(matches!(res, Err(Error::BalanceOverflow)));
+        let owner = read_tweet_owner(&mut host, &0).unwrap().unwrap();
+        assert_eq!(owner, seller.public_key_hash);
+        assert_eq!(
+            read_balance(&mut host, &buyer.public_key_hash).unwrap(),
+            1_000
+        );
+        assert_eq!(
+            read_balance(&mut host, &account().public_key_hash).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_withdraw_balance() {
+        let mut host = MockHost::default();
+        let account = account();
+        deposit(&mut host, &account.public_key_hash, &1_000).unwrap();
+
+        let res = withdraw_balance(&mut host, 10, &account, &1_001);
+        assert!(matches!(res, Err(Error::InsufficientBalance)));
+
+        withdraw_balance(&mut host, 10, &account, &400).unwrap();
+        assert_eq!(
+            read_balance(&mut host, &account.public_key_hash).unwrap(),
+            600
+        );
+        assert_eq!(read_outbox_size(&mut host, &10).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_listing_is_invalidated() {
+        let mut host = MockHost::default();
+        let seller = account_of("tz1TGu6TN5GSez2ndXXeDX6LgUDvLzPLqgYV");
+        let buyer = account_of("tz1SodoUsWVe1Yey9eMFbqRUtNpBWfir5NRr");
+        seller_tweet(&mut host, &seller);
+        deposit(&mut host, &buyer.public_key_hash, &1_000).unwrap();
+
+        let list = List {
+            tweet_id: 0,
+            price: 1_000,
+        };
+        let buy = Buy {
+            tweet_id: 0,
+            max_price: 1_000,
+        };
+        list_tweet(&mut host, &seller, &list).unwrap();
+        let transfer = Transfer {
+            tweet_id: 0,
+            destination: account().public_key_hash,
+        };
+        transfer_tweet(&mut host, &seller, &transfer).unwrap();
+        let res = buy_tweet(&mut host, &buyer, &buy);
+        assert!(matches!(res, Err(Error::NotListed)));
+
+        list_tweet(&mut host, &account(), &list).unwrap();
+        withdraw_tweet(&mut host, 10, &account(), &0).unwrap();
+        let res = buy_tweet(&mut host, &buyer, &buy);
+        assert!(matches!(res, Err(Error::NotListed)));
+        assert_eq!(
+            read_balance(&mut host, &buyer.public_key_hash).unwrap(),
+            1_000
+        );
+    }
+
+    #[test]
+    fn test_accept_offer() {
+        let mut host = MockHost::default();
+        let seller = account_of("tz1TGu6TN5GSez2ndXXeDX6LgUDvLzPLqgYV");
+        let buyer = account_of("tz1SodoUsWVe1Yey9eMFbqRUtNpBWfir5NRr");
+        seller_tweet(&mut host, &seller);
+
+        let offer = MakeOffer {
+            tweet_id: 0,
+            price: 2_000,
+        };
+        let res = make_offer(&mut host, &buyer, &offer);
+        assert!(matches!(res, Err(Error::InsufficientBalance)));
+
+        deposit(&mut host, &buyer.public_key_hash, &2_000).unwrap();
+        make_offer(&mut host, &buyer, &offer).unwrap();
+        assert_exist(
+            &mut host,
+            "/tweets/0/offers/tz1SodoUsWVe1Yey9eMFbqRUtNpBWfir5NRr",
+        );
+
+        let accept = AcceptOffer {
+            tweet_id: 0,
+            buyer: buyer.public_key_hash.clone(),
+        };
+        let res = accept_offer(&mut host, &seller, &accept);
+
+        assert!(matches!(res, Ok(2_000)));
+        assert_not_exists(
+            &mut host,
+            "/tweets/0/offers/tz1SodoUsWVe1Yey9eMFbqRUtNpBWfir5NRr",
+        );
+        let owner = read_tweet_owner(&mut host, &0).unwrap().unwrap();
+        assert_eq!(owner, buyer.public_key_hash);
+        assert_eq!(read_balance(&mut host, &buyer.public_key_hash).unwrap(), 0);
+        assert_eq!(
+            read_balance(&mut host, &seller.public_key_hash).unwrap(),
+            1_900
+        );
+        assert_eq!(
+            read_balance(&mut host, &account().public_key_hash).unwrap(),
+            100
+        );
+
+        // The offer can't be accepted twice
+        let res = accept_offer(&mut host, &buyer, &accept);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_reports_hide_tweet() {
+        let mut host = MockHost::default();
+        let owner = account_of("tz1TGu6TN5GSez2ndXXeDX6LgUDvLzPLqgYV");
+        let reporter = account_of("tz1SodoUsWVe1Yey9eMFbqRUtNpBWfir5NRr");
+        seller_tweet(&mut host, &owner);
+        let threshold = RefPath::assert_from(b"/config/reports_threshold");
+        host.store_write(&threshold, &2_u32.to_be_bytes(), 0)
+            .unwrap();
+
+        let report = ReportTweet {
+            tweet_id: 0,
+            reason: "spam".to_string(),
+        };
+        let hidden = report_tweet(&mut host, &reporter, &report).unwrap();
+        assert!(!hidden);
+        let res = report_tweet(&mut host, &reporter, &report);
+        assert!(matches!(res, Err(Error::AlreadyReported)));
+        assert_u64(&mut host, "/tweets/0/report_count", Some(1));
+        assert_not_exists(&mut host, "/tweets/0/hidden");
+
+        let long_report = ReportTweet {
+            tweet_id: 0,
+            reason: "a".repeat(MAX_REPORT_REASON_SIZE + 1),
+        };
+        let res = report_tweet(&mut host, &owner, &long_report);
+        assert!(matches!(res, Err(Error::ReportReasonTooLarge)));
+        assert_u64(&mut host, "/tweets/0/report_count", Some(1));
+
+        let hidden = report_tweet(&mut host, &account(), &report).unwrap();
+        assert!(hidden);
+        assert_u64(&mut host, "/tweets/0/report_count", Some(2));
+        assert_exist(&mut host, "/tweets/0/hidden");
+
+        // The tweet is only hidden once
+        let hidden = report_tweet(&mut host, &owner, &report).unwrap();
+        assert!(!hidden);
+
+        // A hidden tweet can't be liked, transferred, listed nor collected
+        let res = like_tweet(&mut host, &reporter, &0);
+        assert!(matches!(res, Err(Error::TweetHidden)));
+        let transfer = Transfer {
+            tweet_id: 0,
+            destination: reporter.public_key_hash.clone(),
+        };
+        let res = transfer_tweet(&mut host, &owner, &transfer);
+        assert!(matches!(res, Err(Error::TweetHidden)));
+        let list = List {
+            tweet_id: 0,
+            price: 1_000,
+        };
+        let res = list_tweet(&mut host, &owner, &list);
+        assert!(matches!(res, Err(Error::TweetHidden)));
+        let res = withdraw_tweet(&mut host, 10, &owner, &0);
+        assert!(matches!(res, Err(Error::TweetHidden)));
+    }
+
+    #[test]
+    fn test_report_events() {
+        let mut host = MockHost::default();
+        seller_tweet(
+            &mut host,
+            &account_of("tz1TGu6TN5GSez2ndXXeDX6LgUDvLzPLqgYV"),
+        );
+        let threshold = RefPath::assert_from(b"/config/reports_threshold");
+        host.store_write(&threshold, &1_u32.to_be_bytes(), 0)
+            .unwrap();
+
+        let report = Content::ReportTweet(ReportTweet {
+            tweet_id: 0,
+            reason: "spam".to_string(),
+        });
+        let hash = Blake2b::from("report".as_bytes());
+        Tzwitter::apply(&mut host, 5, &hash, &account(), report).unwrap();
+
+        // The tweet is hidden by the report, after it has been reported
+        let event = |host: &mut MockHost, n: u32| {
+            let path = OwnedPath::try_from(format!("/events/5/{}", n)).unwrap();
+            let size = host.store_value_size(&path).unwrap();
+            String::from_utf8(host.store_read(&path, 0, size).unwrap()).unwrap()
+        };
+        assert!(event(&mut host, 0).contains("TweetReported"));
+        assert!(event(&mut host, 1).contains("TweetHidden"));
+        assert_not_exists(&mut host, "/events/5/2");
+    }
+
+    #[test]
+    fn test_admin_hides_tweet() {
+        let mut host = MockHost::default();
+        let admin = account_of("tz1SodoUsWVe1Yey9eMFbqRUtNpBWfir5NRr");
+        seller_tweet(
+            &mut host,
+            &account_of("tz1TGu6TN5GSez2ndXXeDX6LgUDvLzPLqgYV"),
+        );
+
+        let res = hide_tweet(&mut host, &admin, &0);
+        assert!(matches!(res, Err(Error::NotAdmin)));
+
+        let path = RefPath::assert_from(b"/config/admin");
+        host.store_write(&path, admin.public_key_hash.to_string().as_bytes(), 0)
+            .unwrap();
+        let res = hide_tweet(&mut host, &account(), &0);
+        assert!(matches!(res, Err(Error::NotAdmin)));
+        hide_tweet(&mut host, &admin, &0).unwrap();
+        assert_exist(&mut host, "/tweets/0/hidden");
+
+        let res = hide_tweet(&mut host, &admin, &1);
+        assert!(matches!(res, Err(Error::TweetNotFound)));
+    }
+
+    /// Direct message from the author of the input_1 tweet
+    fn direct_message(to: &Account, ciphertext: &str) -> SendDirectMessage {
+        SendDirectMessage {
+            to: to.public_key_hash.clone(),
+            ciphertext: ciphertext.to_string(),
+            ephemeral_pk: "edpkuDMUm7Y53wp4gxeLBXuiAhXZrLn8XB1R83ksvvesH8Lp8bmCfK".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_direct_message() {
+        let mut host = MockHost::default();
+        let recipient = account_of("tz1TGu6TN5GSez2ndXXeDX6LgUDvLzPLqgYV");
+        let inbox = "/accounts/tz1TGu6TN5GSez2ndXXeDX6LgUDvLzPLqgYV/inbox";
+
+        let index =
+            send_direct_message(&mut host, 1, &account(), direct_message(&recipient, "00ff"))
+                .unwrap();
+        assert_eq!(index, 0);
+        assert_exist(&mut host, &format!("{}/0/ciphertext", inbox));
+        assert_exist(&mut host, &format!("{}/0/ephemeral_pk", inbox));
+
+        let res = remove_direct_message(&mut host, &account(), &0);
+        assert!(matches!(res, Err(Error::DirectMessageNotFound)));
+        remove_direct_message(&mut host, &recipient, &0).unwrap();
+        assert_not_exists(&mut host, &format!("{}/0/from", inbox));
+
+        // The indices are not reused
+        let index =
+            send_direct_message(&mut host, 1, &account(), direct_message(&recipient, "00ff"))
+                .unwrap();
+        assert_eq!(index, 1);
+    }
+
+    #[test]
+    fn test_blocked_sender() {
+        let mut host = MockHost::default();
+        let recipient = account_of("tz1TGu6TN5GSez2ndXXeDX6LgUDvLzPLqgYV");
+
+        block_account(&mut host, &recipient, &account().public_key_hash).unwrap();
+        let res = send_direct_message(&mut host, 1, &account(), direct_message(&recipient, "00"));
+        assert!(matches!(res, Err(Error::SenderBlocked)));
+
+        unblock_account(&mut host, &recipient, &account().public_key_hash).unwrap();
+        let res = send_direct_message(&mut host, 1, &account(), direct_message(&recipient, "00"));
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn test_direct_message_limits() {
+        let mut host = MockHost::default();
+        let recipient = account_of("tz1TGu6TN5GSez2ndXXeDX6LgUDvLzPLqgYV");
+
+        let ciphertext = "0".repeat(MAX_CIPHERTEXT_SIZE + 1);
+        let res = send_direct_message(
+            &mut host,
+            1,
+            &account(),
+            direct_message(&recipient, &ciphertext),
+        );
+        assert!(matches!(res, Err(Error::DirectMessageTooLarge)));
+
+        // The number of messages sent at each level is limited
+        for _ in 0..DIRECT_MESSAGES_PER_LEVEL {
+            send_direct_message(&mut host, 1, &account(), direct_message(&recipient, "00"))
+                .unwrap();
+        }
+        let res = send_direct_message(&mut host, 1, &account(), direct_message(&recipient, "00"));
+        assert!(matches!(res, Err(Error::RateLimited)));
+
+        // The inbox of the recipient is limited
+        let mut level = 1;
+        while inbox_size(&mut host, &recipient.public_key_hash).unwrap() < INBOX_QUOTA {
+            level += 1;
+            for _ in 0..DIRECT_MESSAGES_PER_LEVEL {
+                let message = direct_message(&recipient, "00");
+                send_direct_message(&mut host, level, &account(), message).unwrap();
+            }
+        }
+        let message = direct_message(&recipient, "00");
+        let res = send_direct_message(&mut host, level + 1, &account(), message);
+        assert!(matches!(res, Err(Error::InboxFull)));
+    }
+
+    mod ownership {
+        use proptest::prelude::*;
+        use tezos_smart_rollup::{storage::path::OwnedPath, testing::prelude::MockHost};
+
+        use crate::{
+            core::{
+                account::Account,
+                message::{PostTweet, Transfer},
+                nonce::Nonce,
+                public_key_hash::PublicKeyHash,
+            },
+            stages::{create_tweet, transfer_tweet, withdraw_tweet},
+            storage::{exists, read_tweet_owner},
+        };
+
+        const ACCOUNTS: [&str; 3] = [
+            "tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv",
+            "tz1TGu6TN5GSez2ndXXeDX6LgUDvLzPLqgYV",
+            "tz1SodoUsWVe1Yey9eMFbqRUtNpBWfir5NRr",
+        ];
+
+        #[derive(Debug, Clone)]
+        enum Operation {
+            Post(usize),
+            Transfer(usize, u64, usize),
+            Collect(usize, u64),
+        }
+
+        fn operation() -> impl Strategy<Value = Operation> {
+            prop_oneof![
+                (0..ACCOUNTS.len()).prop_map(Operation::Post),
+                (0..ACCOUNTS.len(), 0..8u64, 0..ACCOUNTS.len())
+                    .prop_map(|(from, id, to)| Operation::Transfer(from, id, to)),
+                (0..ACCOUNTS.len(), 0..8u64).prop_map(|(from, id)| Operation::Collect(from, id)),
+            ]
+        }
+
+        fn account(index: usize) -> Account {
+            Account {
+                public_key_hash: PublicKeyHash::from_b58(ACCOUNTS[index]).unwrap(),
+                nonce: Nonce(0),
+            }
+        }
+
+        fn owned_path(account: &str, tweet_id: u64) -> OwnedPath {
+            let path = format!("/accounts/{}/tweets/owned/{}", account, tweet_id);
+            OwnedPath::try_from(path).unwrap()
+        }
+
+        proptest! {
+            /// Whatever the sequence of operations, each tweet has exactly one owner
+            /// and only this owner has the tweet in its "owned" index
+            #[test]
+            fn test_single_owner(operations in prop::collection::vec(operation(), 1..40)) {
+                let mut host = MockHost::default();
+                let mut tweets = 0;
+
+                for (level, operation) in operations.into_iter().enumerate() {
+                    let level = level as u32;
+                    match operation {
+                        Operation::Post(author) => {
+                            let account = account(author);
+                            let post_tweet = PostTweet {
+                                author: account.public_key_hash.clone(),
+                                content: "Hello world".to_string(),
+                            };
+                            create_tweet(&mut host, level, &account, post_tweet).unwrap();
+                            tweets += 1;
+                        }
+                        Operation::Transfer(from, tweet_id, to) => {
+                            let transfer = Transfer {
+                                tweet_id,
+                                destination: account(to).public_key_hash,
+                            };
+                            let _ = transfer_tweet(&mut host, &account(from), &transfer);
+                        }
+                        Operation::Collect(from, tweet_id) => {
+                            let _ = withdraw_tweet(&mut host, level, &account(from), &tweet_id);
+                        }
+                    }
+
+                    for tweet_id in 0..tweets {
+                        let owner = read_tweet_owner(&mut host, &tweet_id).unwrap().unwrap();
+                        let indexed_by: Vec<String> = ACCOUNTS
+                            .iter()
+                            .filter(|account| {
+                                exists(&mut host, &owned_path(account, tweet_id)).unwrap()
+                            })
+                            .map(|account| account.to_string())
+                            .collect();
+                        prop_assert_eq!(indexed_by, vec![owner.to_string()]);
+                    }
+                }
+            }
+        }
     }
 }
@@ -1,46 +1,75 @@
 use crate::{
-    constants::{L1_TOKEN_CONTRACT_ADDRESS, L1_TOKEN_CONTRACT_ENTRYPOINT, MAGIC_BYTE},
+    constants::{
+        DIRECT_MESSAGES_PER_LEVEL, EVENTS_GC_BUDGET, INBOX_QUOTA, L1_TOKEN_CONTRACT_ADDRESS,
+        L1_TOKEN_CONTRACT_ENTRYPOINT, L1_WITHDRAW_ENTRYPOINT, MAGIC_BYTE, MAX_CIPHERTEXT_SIZE,
+        MAX_EPHEMERAL_PK_SIZE, MAX_REPORT_REASON_SIZE, METRICS_GC_BUDGET, OUTBOX_EXPIRATION_WINDOW,
+        RECEIPTS_GC_BUDGET, ROYALTIES_BPS,
+    },
     core::{
         account::Account,
-        message::{Content, Inner, PostTweet, Transfer},
-        nonce::Nonce,
+        config::Config,
+        direct_message::DirectMessage,
+        input::Input,
+        listing::Listing,
+        message::{
+            AcceptOffer, Buy, List, MakeOffer, Message, PostTweet, ReportTweet, SendDirectMessage,
+            Transfer,
+        },
+        public_key_hash::PublicKeyHash,
         tweet::Tweet,
+        withdrawal::Withdrawal,
     },
     storage::{
-        self, add_collecting_tweet_to_account, add_owned_tweet_to_account,
-        add_written_tweet_to_account, increment_tweet_counter, is_liked, is_not_collected,
-        is_owner, read_tweet, set_collected_block, set_like_flag, store_tweet,
+        self, add_collecting_tweet_to_account, add_report, add_written_tweet_to_account,
+        delete_direct_message, delete_events, delete_listing, delete_metrics, delete_offer,
+        delete_outbox_level, delete_receipt, delete_receipts_level, delete_withdrawal, inbox_size,
+        increment_outbox_size, increment_tweet_counter, is_blocked, is_collected, is_hidden,
+        is_in_inbox, is_liked, is_not_collected, is_not_hidden, is_owner, is_reported,
+        read_balance, read_config, read_direct_messages_sent, read_events_gc_level, read_listing,
+        read_metrics_gc_level, read_offer, read_outbox_size, read_receipt_hash,
+        read_receipts_gc_cursor, read_tweet, read_tweet_withdrawal, read_withdrawal,
+        read_written_level, remove_collecting_tweet_from_account, set_blocked, set_collected_block,
+        set_hidden, set_like_flag, set_tweet_owner, set_written_level, store_balance,
+        store_direct_message, store_direct_messages_sent, store_events_gc_level, store_listing,
+        store_metrics_gc_level, store_offer, store_receipts_gc_cursor, store_tweet,
+        store_withdrawal, unset_blocked, unset_collected_block,
     },
 };
 
 use num_bigint::ToBigInt;
+use serde::de::DeserializeOwned;
 use tezos_data_encoding::{enc::BinWriter, types::Zarith};
 use tezos_smart_rollup::{
-    michelson::{MichelsonContract, MichelsonInt, MichelsonPair, MichelsonString},
+    inbox::{InboxMessage, InternalInboxMessage},
+    michelson::{
+        Michelson, MichelsonContract, MichelsonInt, MichelsonOr, MichelsonPair, MichelsonString,
+    },
     outbox::{OutboxMessage, OutboxMessageTransaction, OutboxMessageTransactionBatch},
     prelude::*,
     types::{Contract, Entrypoint},
 };
 
 use crate::core::error::*;
-use crate::core::message::Message;
 
 /// Read a message from the inbox
 ///
-/// It will only read messages External Messages with the MAGIC_BYTE
+/// It will only read messages External Messages with the MAGIC_BYTE,
+/// the start of level messages and the mint confirmations of the L1 contract
 /// Benchmark: 2_000_000 ticks (processing an inbox with only one message)
-pub fn read_input<R: Runtime>(host: &mut R) -> std::result::Result<(Message, u32), ReadInputError> {
+pub fn read_input<R: Runtime>(host: &mut R) -> std::result::Result<(Input, u32), ReadInputError> {
     let input = host.read_input().map_err(ReadInputError::Runtime)?;
     match input {
         None => Err(ReadInputError::EndOfInbox),
         Some(message) => {
             let data = message.as_ref();
             match data {
+                [0x00, ..] => {
+                    let input = read_internal_message(data)?;
+                    Ok((input, message.level))
+                }
                 [0x01, MAGIC_BYTE, ..] => {
-                    let bytes = data.iter().skip(2).copied().collect();
-                    let str = String::from_utf8(bytes).map_err(ReadInputError::FromUtf8Error)?;
-                    let msg = serde_json_wasm::from_str(&str).map_err(ReadInputError::SerdeJson)?;
-                    Ok((msg, message.level))
+                    let msg = decode_message(&data[2..])?;
+                    Ok((Input::Message(msg), message.level))
                 }
                 _ => Err(ReadInputError::NotATzwitterMessage),
             }
@@ -48,48 +77,72 @@ pub fn read_input<R: Runtime>(host: &mut R) -> std::result::Result<(Message, u32
     }
 }
 
-/// Verify the signature of a message
-///
-/// Returns the inner message
-pub fn verify_signature(message: Message) -> Result<Inner> {
-    let signature = message.signature();
-    let pkey = message.public_key();
-    let inner = message.inner();
-    let hash = inner.hash();
-
-    signature.verify(pkey, hash.as_ref())?;
-    let Message { inner, .. } = message;
-    Ok(inner)
+/// Deserializes a message from the json bytes of an external message
+fn decode_message<C: DeserializeOwned>(
+    bytes: &[u8],
+) -> std::result::Result<Message<C>, ReadInputError> {
+    let str = String::from_utf8(bytes.to_vec()).map_err(ReadInputError::FromUtf8Error)?;
+    serde_json_wasm::from_str(&str).map_err(ReadInputError::SerdeJson)
 }
 
-/// Verify the nonce of the inner message
+/// Parameter type of the rollup
+///
+/// (or (int %confirm) (pair %deposit address nat))
+type RollupParameter = MichelsonOr<MichelsonInt, MichelsonPair<MichelsonContract, MichelsonInt>>;
+
+/// Read an internal message
 ///
-/// If the nonce is correct the content of the inner is returned
-pub fn verify_nonce(inner: Inner, nonce: &Nonce) -> Result<Content> {
-    let next_nonce = nonce.next();
-    let inner_nonce = inner.nonce();
-    if &next_nonce == inner_nonce {
-        let Inner { content, .. } = inner;
-        Ok(content)
-    } else {
-        Err(Error::InvalidNonce)
+/// Only the start of level and the transfers from the L1 contract are processed
+/// The L1 contract sends the id of the minted tweet, or the deposits of the accounts
+fn read_internal_message(data: &[u8]) -> std::result::Result<Input, ReadInputError> {
+    let (_, message) = InboxMessage::<RollupParameter>::parse(data)
+        .map_err(|_| ReadInputError::NotATzwitterMessage)?;
+    match message {
+        InboxMessage::Internal(InternalInboxMessage::StartOfLevel) => Ok(Input::StartOfLevel),
+        InboxMessage::Internal(InternalInboxMessage::Transfer(transfer)) => {
+            if transfer.sender.to_base58_check() != L1_TOKEN_CONTRACT_ADDRESS {
+                return Err(ReadInputError::NotATzwitterMessage);
+            }
+            match transfer.payload {
+                MichelsonOr::Left(MichelsonInt(Zarith(tweet_id))) => {
+                    let tweet_id = u64::try_from(&tweet_id)
+                        .map_err(|_| ReadInputError::NotATzwitterMessage)?;
+                    Ok(Input::MintConfirmation(tweet_id))
+                }
+                MichelsonOr::Right(MichelsonPair(
+                    MichelsonContract(account),
+                    MichelsonInt(Zarith(amount)),
+                )) => {
+                    let account = PublicKeyHash::from_b58(&account.to_b58check())
+                        .map_err(|_| ReadInputError::NotATzwitterMessage)?;
+                    let amount =
+                        u64::try_from(&amount).map_err(|_| ReadInputError::NotATzwitterMessage)?;
+                    Ok(Input::Deposit { account, amount })
+                }
+            }
+        }
+        _ => Err(ReadInputError::NotATzwitterMessage),
     }
 }
 
 /// Create a new tweet from the PostTweet request
 /// Save the tweet to the durable state
 /// And add a tweet entry to the user account
+///
+/// Returns the id of the created tweet
 pub fn create_tweet<R: Runtime>(
     host: &mut R,
+    level: u32,
     account: &Account,
     post_tweet: PostTweet,
-) -> Result<()> {
+) -> Result<u64> {
     let id = increment_tweet_counter(host)?;
     let tweet = Tweet::from(post_tweet);
     let _ = store_tweet(host, &id, &tweet)?;
-    add_owned_tweet_to_account(host, &account.public_key_hash, &id)?;
+    set_written_level(host, &id, &level)?;
+    set_tweet_owner(host, &id, &account.public_key_hash)?;
     add_written_tweet_to_account(host, &account.public_key_hash, &id)?;
-    Ok(())
+    Ok(id)
 }
 
 pub fn like_tweet<R: Runtime>(host: &mut R, account: &Account, tweet_id: &u64) -> Result<()> {
@@ -101,6 +154,7 @@ pub fn like_tweet<R: Runtime>(host: &mut R, account: &Account, tweet_id: &u64) -
             match tweet {
                 None => Err(Error::TweetNotFound),
                 Some(tweet) => {
+                    is_not_hidden(host, tweet_id)?;
                     let tweet = tweet.like();
                     store_tweet(host, tweet_id, &tweet)?;
                     set_like_flag(host, &account.public_key_hash, tweet_id)?;
@@ -114,6 +168,7 @@ pub fn like_tweet<R: Runtime>(host: &mut R, account: &Account, tweet_id: &u64) -
 /// Transfer a tweet from an account to another one
 ///
 /// Checks if the account parameter is owner of the tweet
+/// The tweet cannot be transferred to its owner, nor when it is being collected or hidden
 pub fn transfer_tweet<R: Runtime>(
     host: &mut R,
     account: &Account,
@@ -124,64 +179,352 @@ pub fn transfer_tweet<R: Runtime>(
         destination,
     } = transfer;
     is_owner(host, &account.public_key_hash, tweet_id)?;
+    if &account.public_key_hash == destination {
+        return Err(Error::SelfTransfer);
+    }
+    if is_collected(host, tweet_id)? {
+        return Err(Error::TweetBeingCollected);
+    }
+    is_not_hidden(host, tweet_id)?;
     storage::transfer(host, &account.public_key_hash, tweet_id, destination)?;
     Ok(())
 }
 
-/// Withdraw the tweet to layer 1
-pub fn withdraw_tweet<R: Runtime>(
+/// Credit the balance of an account
+pub fn deposit<R: Runtime>(host: &mut R, account: &PublicKeyHash, amount: &u64) -> Result<()> {
+    let balance = read_balance(host, account)?;
+    let balance = balance.checked_add(*amount).ok_or(Error::BalanceOverflow)?;
+    store_balance(host, account, &balance)
+}
+
+/// Computes the royalties of the author on a sale
+fn royalties(price: u64) -> u64 {
+    let royalties = price as u128 * ROYALTIES_BPS as u128 / 10_000;
+    royalties as u64
+}
+
+/// Sells a tweet from the seller to the buyer
+///
+/// The buyer pays the price, the author of the tweet receives the royalties and the seller the rest
+/// Everything is checked before the state is updated, so the sale happens entirely or not at all
+/// The author can be the buyer or the seller, so each balance is computed from the previous ones
+fn sell<R: Runtime>(
     host: &mut R,
-    level: u32,
-    account: &Account,
     tweet_id: &u64,
+    seller: &PublicKeyHash,
+    buyer: &PublicKeyHash,
+    price: u64,
 ) -> Result<()> {
+    if seller == buyer {
+        return Err(Error::SelfTransfer);
+    }
+    is_owner(host, seller, tweet_id)?;
+    if is_collected(host, tweet_id)? {
+        return Err(Error::TweetBeingCollected);
+    }
+    is_not_hidden(host, tweet_id)?;
+    let tweet = read_tweet(host, tweet_id)?.ok_or(Error::TweetNotFound)?;
+    let author = &tweet.author;
+    let royalties = royalties(price);
+
+    let buyer_balance = read_balance(host, buyer)?
+        .checked_sub(price)
+        .ok_or(Error::InsufficientBalance)?;
+    let author_balance = match author == buyer {
+        true => buyer_balance,
+        false => read_balance(host, author)?,
+    }
+    .checked_add(royalties)
+    .ok_or(Error::BalanceOverflow)?;
+    let seller_balance = match seller == author {
+        true => author_balance,
+        false => read_balance(host, seller)?,
+    }
+    .checked_add(price - royalties)
+    .ok_or(Error::BalanceOverflow)?;
+
+    store_balance(host, buyer, &buyer_balance)?;
+    store_balance(host, author, &author_balance)?;
+    store_balance(host, seller, &seller_balance)?;
+    storage::transfer(host, seller, tweet_id, buyer)
+}
+
+/// Put a tweet on sale
+///
+/// Checks if the account parameter is owner of the tweet
+pub fn list_tweet<R: Runtime>(host: &mut R, account: &Account, list: &List) -> Result<()> {
+    let List { tweet_id, price } = list;
     is_owner(host, &account.public_key_hash, tweet_id)?;
     is_not_collected(host, tweet_id)?;
+    is_not_hidden(host, tweet_id)?;
+    let listing = Listing {
+        seller: account.public_key_hash.clone(),
+        price: *price,
+    };
+    store_listing(host, tweet_id, &listing)
+}
 
-    let tweet = read_tweet(host, tweet_id)
-        .map_err(Error::from)?
-        .ok_or(Error::TweetNotFound)?;
+/// Report an abusive tweet
+///
+/// An account can report a tweet only once
+/// The tweet is hidden when its number of reports reaches the threshold of the configuration
+///
+/// Returns true if the report has hidden the tweet
+pub fn report_tweet<R: Runtime>(
+    host: &mut R,
+    account: &Account,
+    report: &ReportTweet,
+) -> Result<bool> {
+    let ReportTweet { tweet_id, reason } = report;
+    if reason.len() > MAX_REPORT_REASON_SIZE {
+        return Err(Error::ReportReasonTooLarge);
+    }
+    read_tweet(host, tweet_id)?.ok_or(Error::TweetNotFound)?;
+    if is_reported(host, tweet_id, &account.public_key_hash)? {
+        return Err(Error::AlreadyReported);
+    }
+    let report_count = add_report(host, tweet_id, &account.public_key_hash, reason)?;
+    let config = read_config(host)?;
+    if report_count < config.reports_threshold as u64 || is_hidden(host, tweet_id)? {
+        return Ok(false);
+    }
+    set_hidden(host, tweet_id)?;
+    Ok(true)
+}
 
-    let owner = {
-        let contract = Contract::from_b58check(&account.public_key_hash.to_string())
-            .map_err(|_| Error::FromBase58CheckError)?;
-        MichelsonContract(contract)
-    };
-    let author = {
-        let contract = Contract::from_b58check(&tweet.author.to_string())
-            .map_err(|_| Error::FromBase58CheckError)?;
-        MichelsonContract(contract)
-    };
-    let id = {
-        let id = tweet_id.to_bigint().ok_or(Error::BigIntError)?;
-        let id = Zarith(id);
-        MichelsonInt(id)
-    };
-    // What to do with that?
-    let likes = {
-        let likes = tweet.likes.to_bigint().ok_or(Error::BigIntError)?;
-        let likes = Zarith(likes);
-        MichelsonInt(likes)
+/// Hide a tweet
+///
+/// Only the admin of the configuration can hide a tweet
+pub fn hide_tweet<R: Runtime>(host: &mut R, account: &Account, tweet_id: &u64) -> Result<()> {
+    let config = read_config(host)?;
+    if config.admin.as_ref() != Some(&account.public_key_hash) {
+        return Err(Error::NotAdmin);
+    }
+    read_tweet(host, tweet_id)?.ok_or(Error::TweetNotFound)?;
+    set_hidden(host, tweet_id)
+}
+
+/// Send a direct message to an account
+///
+/// The size of the message, the inbox of the recipient and the number of messages
+/// sent by the account at each level are limited
+/// The recipient should not have blocked the account
+///
+/// Returns the index of the message in the inbox of the recipient
+pub fn send_direct_message<R: Runtime>(
+    host: &mut R,
+    level: u32,
+    account: &Account,
+    send_direct_message: SendDirectMessage,
+) -> Result<u64> {
+    let SendDirectMessage {
+        to,
+        ciphertext,
+        ephemeral_pk,
+    } = send_direct_message;
+    let from = &account.public_key_hash;
+    if ciphertext.len() > MAX_CIPHERTEXT_SIZE || ephemeral_pk.len() > MAX_EPHEMERAL_PK_SIZE {
+        return Err(Error::DirectMessageTooLarge);
+    }
+    if is_blocked(host, &to, from)? {
+        return Err(Error::SenderBlocked);
+    }
+    if inbox_size(host, &to)? >= INBOX_QUOTA {
+        return Err(Error::InboxFull);
+    }
+    let sent = read_direct_messages_sent(host, from, &level)?;
+    if sent >= DIRECT_MESSAGES_PER_LEVEL {
+        return Err(Error::RateLimited);
+    }
+
+    store_direct_messages_sent(host, from, &level, &(sent + 1))?;
+    let direct_message = DirectMessage {
+        from: from.clone(),
+        ciphertext,
+        ephemeral_pk,
+        level,
     };
-    let content = MichelsonString(tweet.content);
+    store_direct_message(host, &to, &direct_message)
+}
 
-    let destination = Contract::from_b58check(L1_TOKEN_CONTRACT_ADDRESS)
+/// Delete a direct message from the inbox of the account
+pub fn remove_direct_message<R: Runtime>(
+    host: &mut R,
+    account: &Account,
+    index: &u64,
+) -> Result<()> {
+    if !is_in_inbox(host, &account.public_key_hash, index)? {
+        return Err(Error::DirectMessageNotFound);
+    }
+    delete_direct_message(host, &account.public_key_hash, index)
+}
+
+/// Block the direct messages of an account
+pub fn block_account<R: Runtime>(
+    host: &mut R,
+    account: &Account,
+    blocked: &PublicKeyHash,
+) -> Result<()> {
+    set_blocked(host, &account.public_key_hash, blocked)
+}
+
+/// Unblock the direct messages of an account
+pub fn unblock_account<R: Runtime>(
+    host: &mut R,
+    account: &Account,
+    blocked: &PublicKeyHash,
+) -> Result<()> {
+    unset_blocked(host, &account.public_key_hash, blocked)
+}
+
+/// Remove a tweet from sale
+///
+/// Checks if the account parameter is owner of the tweet
+pub fn unlist_tweet<R: Runtime>(host: &mut R, account: &Account, tweet_id: &u64) -> Result<()> {
+    is_owner(host, &account.public_key_hash, tweet_id)?;
+    read_listing(host, tweet_id)?.ok_or(Error::NotListed)?;
+    delete_listing(host, tweet_id)
+}
+
+/// Buy a listed tweet at its price
+///
+/// The price may have been changed since the buy was signed,
+/// so the tweet is only bought if its price is at most the maximum price of the buyer
+///
+/// Returns the listing of the bought tweet
+pub fn buy_tweet<R: Runtime>(host: &mut R, account: &Account, buy: &Buy) -> Result<Listing> {
+    let Buy {
+        tweet_id,
+        max_price,
+    } = buy;
+    let listing = read_listing(host, tweet_id)?.ok_or(Error::NotListed)?;
+    if &listing.price > max_price {
+        return Err(Error::PriceTooHigh);
+    }
+    sell(
+        host,
+        tweet_id,
+        &listing.seller,
+        &account.public_key_hash,
+        listing.price,
+    )?;
+    Ok(listing)
+}
+
+/// Offer to buy a tweet
+///
+/// The balance of the account should cover the offer when it is made
+/// The balance is checked again when the offer is accepted
+pub fn make_offer<R: Runtime>(host: &mut R, account: &Account, offer: &MakeOffer) -> Result<()> {
+    let MakeOffer { tweet_id, price } = offer;
+    read_tweet(host, tweet_id)?.ok_or(Error::TweetNotFound)?;
+    match is_owner(host, &account.public_key_hash, tweet_id) {
+        Ok(()) => return Err(Error::SelfTransfer),
+        Err(Error::NotOwner) => {}
+        Err(err) => return Err(err),
+    }
+    let balance = read_balance(host, &account.public_key_hash)?;
+    if &balance < price {
+        return Err(Error::InsufficientBalance);
+    }
+    store_offer(host, tweet_id, &account.public_key_hash, price)
+}
+
+/// Sell a tweet to the buyer of an offer
+///
+/// Returns the price of the offer
+pub fn accept_offer<R: Runtime>(
+    host: &mut R,
+    account: &Account,
+    accept_offer: &AcceptOffer,
+) -> Result<u64> {
+    let AcceptOffer { tweet_id, buyer } = accept_offer;
+    let price = read_offer(host, tweet_id, buyer)?.ok_or(Error::OfferNotFound)?;
+    sell(host, tweet_id, &account.public_key_hash, buyer, price)?;
+    delete_offer(host, tweet_id, buyer)?;
+    Ok(price)
+}
+
+/// Converts a public key hash to a michelson address
+fn michelson_address(public_key_hash: &PublicKeyHash) -> Result<MichelsonContract> {
+    let contract = Contract::from_b58check(&public_key_hash.to_string())
         .map_err(|_| Error::FromBase58CheckError)?;
+    Ok(MichelsonContract(contract))
+}
 
-    // (pair %mint
-    //     (pair (nat %id) (address %owner))
-    //     (pair %token (pair (address %author) (string %content)) (nat %likes)))
+/// Converts an u64 to a michelson nat
+fn michelson_nat(nat: &u64) -> Result<MichelsonInt> {
+    let nat = nat.to_bigint().ok_or(Error::BigIntError)?;
+    Ok(MichelsonInt(Zarith(nat)))
+}
 
-    let michelson = MichelsonPair(
+/// Parameters of the mint entrypoint of smart_contract/dummy-fa2.jsligo
+///
+/// (pair %mint
+///     (pair (nat %id) (address %owner))
+///     (pair %token (pair (address %author) (string %content)) (nat %likes)))
+#[cfg(feature = "legacy-mint")]
+fn mint_parameters(
+    tweet_id: &u64,
+    tweet: Tweet,
+    owner: &PublicKeyHash,
+    _written_level: Option<u32>,
+    _collected_level: u32,
+) -> Result<impl Michelson> {
+    let owner = michelson_address(owner)?;
+    let author = michelson_address(&tweet.author)?;
+    let id = michelson_nat(tweet_id)?;
+    let likes = michelson_nat(&tweet.likes)?;
+    let content = MichelsonString(tweet.content);
+
+    Ok(MichelsonPair(
         MichelsonPair(id, owner),
         MichelsonPair(MichelsonPair(author, content), likes),
-    );
+    ))
+}
+
+/// Parameters of the mint entrypoint of smart_contract/fa2.jsligo
+///
+/// The token info contains the TZIP-21 metadata of the tweet
+///
+/// (pair %mint
+///     (pair (address %owner) (nat %token_id))
+///     (map %token_info string bytes))
+#[cfg(not(feature = "legacy-mint"))]
+fn mint_parameters(
+    tweet_id: &u64,
+    tweet: Tweet,
+    owner: &PublicKeyHash,
+    written_level: Option<u32>,
+    collected_level: u32,
+) -> Result<impl Michelson> {
+    use crate::core::{metadata::TokenMetadata, michelson::MichelsonMap};
+    use tezos_smart_rollup::michelson::MichelsonBytes;
+
+    let owner = michelson_address(owner)?;
+    let id = michelson_nat(tweet_id)?;
+    let metadata = TokenMetadata::new(tweet_id, &tweet, written_level, collected_level);
+    let token_info = metadata
+        .token_info()?
+        .into_iter()
+        .map(|(key, value)| (MichelsonString(key), MichelsonBytes(value)))
+        .collect();
+
+    Ok(MichelsonPair(
+        MichelsonPair(owner, id),
+        MichelsonMap(token_info),
+    ))
+}
+
+/// Encodes a transaction to an entrypoint of the L1 contract as an outbox message
+fn l1_transaction<M: Michelson>(entrypoint: &str, parameters: M) -> Result<Vec<u8>> {
+    let destination = Contract::from_b58check(L1_TOKEN_CONTRACT_ADDRESS)
+        .map_err(|_| Error::FromBase58CheckError)?;
 
     let transaction = OutboxMessageTransaction {
-        parameters: michelson,
+        parameters,
         destination,
-        entrypoint: Entrypoint::try_from(L1_TOKEN_CONTRACT_ENTRYPOINT.to_string())
-            .map_err(Error::from)?,
+        entrypoint: Entrypoint::try_from(entrypoint.to_string()).map_err(Error::from)?,
     };
 
     let batch = OutboxMessageTransactionBatch::from(vec![transaction]);
@@ -189,12 +532,197 @@ pub fn withdraw_tweet<R: Runtime>(
 
     let mut output = Vec::default();
     message.bin_write(&mut output).unwrap();
+    Ok(output)
+}
+
+/// Writes a message in the outbox
+///
+/// It is written once the state is updated, so a failed update does not leave a message in the outbox
+/// Returns the index of the message in the outbox of the level
+fn write_outbox_message<R: Runtime>(host: &mut R, level: u32, output: &[u8]) -> Result<u32> {
+    host.write_output(output).map_err(Error::from)?;
+
+    // Keep track of the outbox messages, to know the index of the next ones
+    increment_outbox_size(host, &level)
+}
+
+/// Withdraw the tweet to layer 1
+pub fn withdraw_tweet<R: Runtime>(
+    host: &mut R,
+    level: u32,
+    account: &Account,
+    tweet_id: &u64,
+) -> Result<()> {
+    is_owner(host, &account.public_key_hash, tweet_id)?;
+    is_not_collected(host, tweet_id)?;
+    is_not_hidden(host, tweet_id)?;
+
+    let tweet = read_tweet(host, tweet_id)
+        .map_err(Error::from)?
+        .ok_or(Error::TweetNotFound)?;
 
-    host.write_output(&output).unwrap();
+    let written_level = read_written_level(host, tweet_id)?;
+    let parameters = mint_parameters(
+        tweet_id,
+        tweet,
+        &account.public_key_hash,
+        written_level,
+        level,
+    )?;
 
-    // Freeze the tweets
+    let output = l1_transaction(L1_TOKEN_CONTRACT_ENTRYPOINT, parameters)?;
+
+    // Keep track of the outbox message, to know when it is executed
+    let index = read_outbox_size(host, &level)?;
+    let withdrawal = Withdrawal {
+        level,
+        index,
+        tweet_id: *tweet_id,
+        owner: account.public_key_hash.clone(),
+    };
+    store_withdrawal(host, &withdrawal)?;
+
+    // Freeze the tweets, it can't be sold anymore
     set_collected_block(host, tweet_id, &level)?;
+    delete_listing(host, tweet_id)?;
     // Indicates that the user is collecting the tweet
     add_collecting_tweet_to_account(host, &account.public_key_hash, tweet_id)?;
+
+    let _ = write_outbox_message(host, level, &output)?;
+    Ok(())
+}
+
+/// Withdraw mutez from the balance of an account to layer 1
+///
+/// The balance is debited at once, the L1 contract sends the tez to the account
+/// when the outbox message is executed
+pub fn withdraw_balance<R: Runtime>(
+    host: &mut R,
+    level: u32,
+    account: &Account,
+    amount: &u64,
+) -> Result<()> {
+    let balance = read_balance(host, &account.public_key_hash)?
+        .checked_sub(*amount)
+        .ok_or(Error::InsufficientBalance)?;
+
+    // (pair %withdraw address nat)
+    let parameters = MichelsonPair(
+        michelson_address(&account.public_key_hash)?,
+        michelson_nat(amount)?,
+    );
+    let output = l1_transaction(L1_WITHDRAW_ENTRYPOINT, parameters)?;
+    store_balance(host, &account.public_key_hash, &balance)?;
+    let _ = write_outbox_message(host, level, &output)?;
     Ok(())
 }
+
+/// Confirm the withdrawal of a tweet
+///
+/// The L1 contract has minted the tweet, so the tweet is not being collected anymore
+/// The tweet stays frozen on the layer 2
+pub fn confirm_withdrawal<R: Runtime>(host: &mut R, tweet_id: &u64) -> Result<()> {
+    let withdrawal = read_tweet_withdrawal(host, tweet_id)?.ok_or(Error::WithdrawalNotFound)?;
+    remove_collecting_tweet_from_account(host, &withdrawal.owner, tweet_id)?;
+    delete_withdrawal(host, &withdrawal)
+}
+
+/// Expire the withdrawals that can't be executed on the layer 1 anymore
+///
+/// The tweets of these withdrawals are unfrozen so their owner can collect them again
+pub fn expire_withdrawals<R: Runtime>(host: &mut R, level: u32) -> Result<()> {
+    let expired_level = match level.checked_sub(OUTBOX_EXPIRATION_WINDOW + 1) {
+        None => return Ok(()),
+        Some(expired_level) => expired_level,
+    };
+
+    let size = read_outbox_size(host, &expired_level)?;
+    for index in 0..size {
+        let withdrawal = read_withdrawal(host, &expired_level, &index)?;
+        if let Some(withdrawal) = withdrawal {
+            unset_collected_block(host, &withdrawal.tweet_id)?;
+            remove_collecting_tweet_from_account(host, &withdrawal.owner, &withdrawal.tweet_id)?;
+            delete_withdrawal(host, &withdrawal)?;
+        }
+    }
+    delete_outbox_level(host, &expired_level)
+}
+
+/// Deletes the events that are older than the retention period
+///
+/// At most EVENTS_GC_BUDGET levels are pruned, the remaining ones will be pruned at the next levels
+/// The events of the current level are never deleted
+pub fn prune_events<R: Runtime>(host: &mut R, level: u32, config: &Config) -> Result<()> {
+    let mut gc_level = match read_events_gc_level(host)? {
+        None => return Ok(()),
+        Some(gc_level) => gc_level,
+    };
+
+    let mut budget = EVENTS_GC_BUDGET;
+    while budget > 0
+        && gc_level < level
+        && gc_level.saturating_add(config.events_retention) <= level
+    {
+        delete_events(host, &gc_level)?;
+        gc_level += 1;
+        budget -= 1;
+    }
+
+    store_events_gc_level(host, &gc_level)
+}
+
+/// Deletes the metrics that are older than the retention period
+///
+/// The metrics are kept as long as the receipts
+/// At most METRICS_GC_BUDGET levels are pruned, the remaining ones will be pruned at the next levels
+pub fn prune_metrics<R: Runtime>(host: &mut R, level: u32, config: &Config) -> Result<()> {
+    let mut gc_level = match read_metrics_gc_level(host)? {
+        None => return Ok(()),
+        Some(gc_level) => gc_level,
+    };
+
+    let mut budget = METRICS_GC_BUDGET;
+    while budget > 0
+        && gc_level < level
+        && gc_level.saturating_add(config.receipts_retention) <= level
+    {
+        delete_metrics(host, &gc_level)?;
+        gc_level += 1;
+        budget -= 1;
+    }
+
+    store_metrics_gc_level(host, &gc_level)
+}
+
+/// Deletes the receipts that are older than the retention period
+///
+/// At most RECEIPTS_GC_BUDGET receipts are deleted, the remaining ones will be deleted at the next levels
+/// The receipts of the current level are never deleted
+pub fn collect_receipts<R: Runtime>(host: &mut R, level: u32, config: &Config) -> Result<()> {
+    let (mut gc_level, mut index) = match read_receipts_gc_cursor(host)? {
+        None => return Ok(()),
+        Some(cursor) => cursor,
+    };
+
+    let mut budget = RECEIPTS_GC_BUDGET;
+    while budget > 0
+        && gc_level < level
+        && gc_level.saturating_add(config.receipts_retention) <= level
+    {
+        match read_receipt_hash(host, &gc_level, &index)? {
+            Some(hash) => {
+                delete_receipt(host, &hash, &gc_level)?;
+                index += 1;
+            }
+            None => {
+                // All the receipts of this level have been deleted
+                delete_receipts_level(host, &gc_level)?;
+                gc_level += 1;
+                index = 0;
+            }
+        }
+        budget -= 1;
+    }
+
+    store_receipts_gc_cursor(host, &gc_level, &index)
+}
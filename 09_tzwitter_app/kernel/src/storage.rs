@@ -1,14 +1,36 @@
 use tezos_smart_rollup::{prelude::*, storage::path::*};
 
+use crate::constants::{
+    DEFAULT_EVENTS_RETENTION, DEFAULT_RECEIPTS_RETENTION, DEFAULT_REPORTS_THRESHOLD,
+};
+use crate::core::config::Config;
+use crate::core::direct_message::DirectMessage;
+use crate::core::event::Event;
+use crate::core::listing::Listing;
+use crate::core::metrics::Metrics;
+use crate::core::public_key::PublicKey;
 use crate::core::public_key_hash::PublicKeyHash;
 use crate::core::receipt::Receipt;
 use crate::core::tweet::Tweet;
+use crate::core::withdrawal::Withdrawal;
 use crate::core::{account::Account, error::*, nonce::Nonce};
 
 const ACCOUNTS: RefPath = RefPath::assert_from(b"/accounts");
 pub const TWEETS: RefPath = RefPath::assert_from(b"/tweets");
 const TWEET_COUNTER: RefPath = RefPath::assert_from(b"/constants/tweet-counter"); // The name constants is not appropriate
 const RECEIPTS: RefPath = RefPath::assert_from(b"/receipts");
+const OUTBOX: RefPath = RefPath::assert_from(b"/outbox");
+const EVENTS: RefPath = RefPath::assert_from(b"/events");
+const METRICS: RefPath = RefPath::assert_from(b"/metrics");
+const RECEIPTS_BY_LEVEL: RefPath = RefPath::assert_from(b"/receipts_by_level");
+const RECEIPTS_GC_LEVEL: RefPath = RefPath::assert_from(b"/receipts_gc/level");
+const RECEIPTS_GC_INDEX: RefPath = RefPath::assert_from(b"/receipts_gc/index");
+const EVENTS_GC_LEVEL: RefPath = RefPath::assert_from(b"/events_gc/level");
+const METRICS_GC_LEVEL: RefPath = RefPath::assert_from(b"/metrics_gc/level");
+const CONFIG_EVENTS_RETENTION: RefPath = RefPath::assert_from(b"/config/events_retention");
+const CONFIG_RECEIPTS_RETENTION: RefPath = RefPath::assert_from(b"/config/receipts_retention");
+const CONFIG_REPORTS_THRESHOLD: RefPath = RefPath::assert_from(b"/config/reports_threshold");
+const CONFIG_ADMIN: RefPath = RefPath::assert_from(b"/config/admin");
 
 /// Compute the paths for the different fields of a tweet
 ///
@@ -35,6 +57,58 @@ fn tweet_content_path(tweet_id: &u64) -> Result<OwnedPath> {
     tweet_field_path(tweet_id, "/content")
 }
 
+/// Compute the path of the tweet owner
+/// /tweets/{id}/owner
+///
+/// The owner is the source of truth of the ownership of a tweet
+fn tweet_owner_path(tweet_id: &u64) -> Result<OwnedPath> {
+    tweet_field_path(tweet_id, "/owner")
+}
+
+/// Compute the path of the listing of a tweet
+/// /tweets/{id}/listing
+fn tweet_listing_path(tweet_id: &u64) -> Result<OwnedPath> {
+    tweet_field_path(tweet_id, "/listing")
+}
+
+/// Compute the path of the price of a listing
+/// /tweets/{id}/listing/price
+fn tweet_listing_price_path(tweet_id: &u64) -> Result<OwnedPath> {
+    tweet_field_path(tweet_id, "/listing/price")
+}
+
+/// Compute the path of the seller of a listing
+/// /tweets/{id}/listing/seller
+fn tweet_listing_seller_path(tweet_id: &u64) -> Result<OwnedPath> {
+    tweet_field_path(tweet_id, "/listing/seller")
+}
+
+/// Compute the path of the offer of a buyer
+/// /tweets/{id}/offers/{tz1...}
+fn tweet_offer_path(tweet_id: &u64, buyer: &PublicKeyHash) -> Result<OwnedPath> {
+    tweet_field_path(tweet_id, &format!("/offers/{}", buyer.to_string()))
+}
+
+/// Compute the path of the report of an account
+/// /tweets/{id}/reports/{tz1...}
+///
+/// The value is the reason of the report
+fn tweet_report_path(tweet_id: &u64, account: &PublicKeyHash) -> Result<OwnedPath> {
+    tweet_field_path(tweet_id, &format!("/reports/{}", account.to_string()))
+}
+
+/// Compute the path of the number of reports of a tweet
+/// /tweets/{id}/report_count
+fn tweet_report_count_path(tweet_id: &u64) -> Result<OwnedPath> {
+    tweet_field_path(tweet_id, "/report_count")
+}
+
+/// Compute the path of the hidden flag of a tweet
+/// /tweets/{id}/hidden
+fn tweet_hidden_path(tweet_id: &u64) -> Result<OwnedPath> {
+    tweet_field_path(tweet_id, "/hidden")
+}
+
 /// Compute the path of the tweet content
 /// /tweets/{hash}/content
 fn tweet_likes_path(tweet_id: &u64) -> Result<OwnedPath> {
@@ -48,6 +122,109 @@ fn tweet_collected_block_path(tweet_id: &u64) -> Result<OwnedPath> {
     tweet_field_path(tweet_id, "/collected_level")
 }
 
+/// Path of the level when the tweet has been written
+/// /tweets/{id}/written_level
+fn tweet_written_level_path(tweet_id: &u64) -> Result<OwnedPath> {
+    tweet_field_path(tweet_id, "/written_level")
+}
+
+/// Path of the withdrawal of a tweet
+/// /tweets/{id}/withdrawal
+fn tweet_withdrawal_path(tweet_id: &u64) -> Result<OwnedPath> {
+    tweet_field_path(tweet_id, "/withdrawal")
+}
+
+/// Path of the outbox level of the withdrawal of a tweet
+/// /tweets/{id}/withdrawal/level
+fn tweet_withdrawal_level_path(tweet_id: &u64) -> Result<OwnedPath> {
+    tweet_field_path(tweet_id, "/withdrawal/level")
+}
+
+/// Path of the outbox index of the withdrawal of a tweet
+/// /tweets/{id}/withdrawal/index
+fn tweet_withdrawal_index_path(tweet_id: &u64) -> Result<OwnedPath> {
+    tweet_field_path(tweet_id, "/withdrawal/index")
+}
+
+/// Compute the paths for the different fields of an outbox level
+///
+/// The field_path should start with slash
+fn outbox_field_path(level: &u32, field_path: &str) -> Result<OwnedPath> {
+    let level_path: Vec<u8> = format!("/{}", level).into();
+    let level_path = OwnedPath::try_from(level_path).map_err(Error::from)?;
+    let level_path = concat(&OUTBOX, &level_path).map_err(Error::from)?;
+
+    let field_path: Vec<u8> = field_path.into();
+    let field_path = OwnedPath::try_from(field_path).map_err(Error::from)?;
+    concat(&level_path, &field_path).map_err(Error::from)
+}
+
+/// Path of the outbox messages written at a given level
+/// /outbox/{level}
+fn outbox_level_path(level: &u32) -> Result<OwnedPath> {
+    let level_path: Vec<u8> = format!("/{}", level).into();
+    let level_path = OwnedPath::try_from(level_path).map_err(Error::from)?;
+    concat(&OUTBOX, &level_path).map_err(Error::from)
+}
+
+/// Path of the number of outbox messages written at a given level
+/// /outbox/{level}/size
+fn outbox_size_path(level: &u32) -> Result<OwnedPath> {
+    outbox_field_path(level, "/size")
+}
+
+/// Path of an outbox message
+/// /outbox/{level}/{index}
+fn outbox_message_path(level: &u32, index: &u32) -> Result<OwnedPath> {
+    outbox_field_path(level, &format!("/{}", index))
+}
+
+/// Path of the tweet withdrawn by an outbox message
+/// /outbox/{level}/{index}/tweet_id
+fn outbox_message_tweet_path(level: &u32, index: &u32) -> Result<OwnedPath> {
+    outbox_field_path(level, &format!("/{}/tweet_id", index))
+}
+
+/// Path of the owner of the tweet withdrawn by an outbox message
+/// /outbox/{level}/{index}/owner
+fn outbox_message_owner_path(level: &u32, index: &u32) -> Result<OwnedPath> {
+    outbox_field_path(level, &format!("/{}/owner", index))
+}
+
+/// Path of the events emitted at a given level
+/// /events/{level}
+fn events_level_path(level: &u32) -> Result<OwnedPath> {
+    let level_path: Vec<u8> = format!("/{}", level).into();
+    let level_path = OwnedPath::try_from(level_path).map_err(Error::from)?;
+    concat(&EVENTS, &level_path).map_err(Error::from)
+}
+
+/// Path of an event
+/// /events/{level}/{n}
+fn event_path(level: &u32, n: &u64) -> Result<OwnedPath> {
+    let events_level_path = events_level_path(level)?;
+    let event_path: Vec<u8> = format!("/{}", n).into();
+    let event_path = OwnedPath::try_from(event_path).map_err(Error::from)?;
+    concat(&events_level_path, &event_path).map_err(Error::from)
+}
+
+/// Path of the metrics of a given level
+/// /metrics/{level}
+fn metrics_level_path(level: &u32) -> Result<OwnedPath> {
+    let level_path: Vec<u8> = format!("/{}", level).into();
+    let level_path = OwnedPath::try_from(level_path).map_err(Error::from)?;
+    concat(&METRICS, &level_path).map_err(Error::from)
+}
+
+/// Path of a field of the metrics of a content kind at a given level
+/// /metrics/{level}/{kind}/{field}
+fn metrics_field_path(level: &u32, kind: &str, field: &str) -> Result<OwnedPath> {
+    let metrics_level_path = metrics_level_path(level)?;
+    let field_path: Vec<u8> = format!("/{}/{}", kind, field).into();
+    let field_path = OwnedPath::try_from(field_path).map_err(Error::from)?;
+    concat(&metrics_level_path, &field_path).map_err(Error::from)
+}
+
 /// Compute the paths for the different fields of an account
 ///
 /// The field_path should start with slash
@@ -66,6 +243,16 @@ fn nonce_path(public_key_hash: &PublicKeyHash) -> Result<OwnedPath> {
     account_field_path(public_key_hash, "/nonce")
 }
 
+/// Compute the path /accounts/{tz1...}/public_key
+fn public_key_path(public_key_hash: &PublicKeyHash) -> Result<OwnedPath> {
+    account_field_path(public_key_hash, "/public_key")
+}
+
+/// Compute the path /accounts/{tz1...}/balance
+fn balance_path(public_key_hash: &PublicKeyHash) -> Result<OwnedPath> {
+    account_field_path(public_key_hash, "/balance")
+}
+
 /// Compute the path to the liked tweet
 fn account_likes_path(public_key_hash: &PublicKeyHash, tweet_id: &u64) -> Result<OwnedPath> {
     account_field_path(public_key_hash, &format!("/likes/{}", tweet_id))
@@ -78,10 +265,10 @@ fn account_collecting_path(public_key_hash: &PublicKeyHash, tweet_id: &u64) -> R
 
 /// Path to keep track of owned tweets
 ///
-/// /account/{tz1...}/tweets/{tweet_id}
-/// If the id is present in the subkey /tweets then the account owns the tweets
+/// /account/{tz1...}/tweets/owned/{tweet_id}
+/// If the id is present in the subkey /tweets/owned then the account owns the tweets
 ///
-/// TODO: this structure is not the best one, it does not ensure that a tweet is owned by only one user.
+/// This is an index derived from /tweets/{id}/owner, it is updated with the owner of the tweet
 fn account_owned_tweet_path(public_key_hash: &PublicKeyHash, tweet_id: &u64) -> Result<OwnedPath> {
     account_field_path(public_key_hash, &format!("/tweets/owned/{}", tweet_id))
 }
@@ -94,11 +281,68 @@ fn account_written_tweet_path(
     account_field_path(public_key_hash, &format!("/tweets/written/{}", tweet_id))
 }
 
+/// Compute the path of the inbox of an account
+/// /accounts/{tz1...}/inbox
+fn account_inbox_path(public_key_hash: &PublicKeyHash) -> Result<OwnedPath> {
+    account_field_path(public_key_hash, "/inbox")
+}
+
+/// Compute the path of a field of a direct message
+/// /accounts/{tz1...}/inbox/{n}/{field}
+fn account_inbox_field_path(
+    public_key_hash: &PublicKeyHash,
+    index: &u64,
+    field: &str,
+) -> Result<OwnedPath> {
+    account_field_path(public_key_hash, &format!("/inbox/{}{}", index, field))
+}
+
+/// Compute the path of the counter of the direct messages received by an account
+/// /accounts/{tz1...}/inbox_counter
+fn account_inbox_counter_path(public_key_hash: &PublicKeyHash) -> Result<OwnedPath> {
+    account_field_path(public_key_hash, "/inbox_counter")
+}
+
+/// Compute the path of an account blocked by another one
+/// /accounts/{tz1...}/blocked/{tz1...}
+fn account_blocked_path(
+    public_key_hash: &PublicKeyHash,
+    blocked: &PublicKeyHash,
+) -> Result<OwnedPath> {
+    account_field_path(
+        public_key_hash,
+        &format!("/blocked/{}", blocked.to_string()),
+    )
+}
+
+/// Compute the path of the level of the last direct message sent by an account
+/// /accounts/{tz1...}/direct_messages_rate/level
+fn account_rate_level_path(public_key_hash: &PublicKeyHash) -> Result<OwnedPath> {
+    account_field_path(public_key_hash, "/direct_messages_rate/level")
+}
+
+/// Compute the path of the number of direct messages sent by an account at its last level
+/// /accounts/{tz1...}/direct_messages_rate/count
+fn account_rate_count_path(public_key_hash: &PublicKeyHash) -> Result<OwnedPath> {
+    account_field_path(public_key_hash, "/direct_messages_rate/count")
+}
+
+/// Compute the path of a receipt from the hash of the message
+/// /receipts/{hash}
+fn receipt_path(hash: &str) -> Result<OwnedPath> {
+    let receipt_path = format!("/{}", hash);
+    let receipt_path = OwnedPath::try_from(receipt_path).map_err(Error::from)?;
+    concat(&RECEIPTS, &receipt_path).map_err(Error::from)
+}
+
 /// Compute the path of the different field of a receipt
 fn receipt_field_path(receipt: &Receipt, field_path: &str) -> Result<OwnedPath> {
-    let receipt_path = format!("/{}", receipt.hash().to_string());
-    let receipt_path = OwnedPath::try_from(receipt_path).map_err(Error::from)?;
-    let receipt_path = concat(&RECEIPTS, &receipt_path)?;
+    receipt_hash_field_path(&receipt.hash().to_string(), field_path)
+}
+
+/// Compute the path of the different field of a receipt from the hash of the message
+fn receipt_hash_field_path(hash: &str, field_path: &str) -> Result<OwnedPath> {
+    let receipt_path = receipt_path(hash)?;
 
     let field_path: Vec<u8> = field_path.into();
     let field_path = OwnedPath::try_from(field_path).map_err(Error::from)?;
@@ -110,6 +354,38 @@ fn receipt_success_path(receipt: &Receipt) -> Result<OwnedPath> {
     receipt_field_path(receipt, "/success")
 }
 
+/// Compute the path of the level field of a receipt
+fn receipt_level_path(receipt: &Receipt) -> Result<OwnedPath> {
+    receipt_field_path(receipt, "/level")
+}
+
+/// Compute the path of the bytes read by a receipt
+fn receipt_bytes_read_path(receipt: &Receipt) -> Result<OwnedPath> {
+    receipt_field_path(receipt, "/bytes_read")
+}
+
+/// Compute the path of the bytes written by a receipt
+fn receipt_bytes_written_path(receipt: &Receipt) -> Result<OwnedPath> {
+    receipt_field_path(receipt, "/bytes_written")
+}
+
+/// Path of the receipts of a given level
+/// /receipts_by_level/{level}
+fn receipts_level_path(level: &u32) -> Result<OwnedPath> {
+    let level_path: Vec<u8> = format!("/{}", level).into();
+    let level_path = OwnedPath::try_from(level_path).map_err(Error::from)?;
+    concat(&RECEIPTS_BY_LEVEL, &level_path).map_err(Error::from)
+}
+
+/// Path of the hash of the n-th receipt of a given level
+/// /receipts_by_level/{level}/{n}
+fn receipts_level_entry_path(level: &u32, n: &u64) -> Result<OwnedPath> {
+    let receipts_level_path = receipts_level_path(level)?;
+    let entry_path: Vec<u8> = format!("/{}", n).into();
+    let entry_path = OwnedPath::try_from(entry_path).map_err(Error::from)?;
+    concat(&receipts_level_path, &entry_path).map_err(Error::from)
+}
+
 ///  Check if a path exists
 pub fn exists<R: Runtime>(host: &mut R, path: &impl Path) -> Result<bool> {
     let exists = Runtime::store_has(host, path)?
@@ -133,8 +409,33 @@ pub fn read_u64<R: Runtime>(host: &mut R, path: &impl Path) -> Result<Option<u64
     }
 }
 
+/// Read an u32 from a given path
+pub fn read_u32<R: Runtime>(host: &mut R, path: &impl Path) -> Result<Option<u32>> {
+    let is_exists = exists(host, path)?;
+    if !is_exists {
+        return Ok(None);
+    }
+
+    let mut buffer = [0_u8; 4];
+    match host.store_read_slice(path, 0, &mut buffer) {
+        Ok(4) => Ok(Some(u32::from_be_bytes(buffer))),
+        _ => Err(Error::StateDeserializarion),
+    }
+}
+
+/// Deletes the data at a given path, and all its subkeys
+///
+/// Does nothing if the path does not exist
+fn delete<R: Runtime>(host: &mut R, path: &impl Path) -> Result<()> {
+    let is_exists = exists(host, path)?;
+    if !is_exists {
+        return Ok(());
+    }
+    host.store_delete(path).map_err(Error::from)
+}
+
 /// Store an u64 at a given path
-fn store_u64<'a, R: Runtime>(host: &mut R, path: &impl Path, u64: &'a u64) -> Result<&'a u64> {
+pub fn store_u64<'a, R: Runtime>(host: &mut R, path: &impl Path, u64: &'a u64) -> Result<&'a u64> {
     let data = u64.to_be_bytes();
     let data = data.as_slice();
 
@@ -165,7 +466,7 @@ where
         .map(|_| data)
 }
 
-fn read_string<R: Runtime>(host: &mut R, path: &OwnedPath) -> Result<Option<String>> {
+fn read_string<R: Runtime>(host: &mut R, path: &impl Path) -> Result<Option<String>> {
     let is_exists = exists(host, path)?;
     if !is_exists {
         return Ok(None);
@@ -306,32 +607,59 @@ pub fn add_written_tweet_to_account<R: Runtime>(
     store_flag(host, &path)
 }
 
-/// Add a tweet in the "owned" path of an account
-pub fn add_owned_tweet_to_account<R: Runtime>(
+/// Set the owner of a tweet
+///
+/// The tweet is also added in the "owned" path of the account
+pub fn set_tweet_owner<R: Runtime>(
     host: &mut R,
-    public_key_hash: &PublicKeyHash,
     tweet_id: &u64,
+    public_key_hash: &PublicKeyHash,
 ) -> Result<()> {
+    let owner_path = tweet_owner_path(tweet_id)?;
     let path = account_owned_tweet_path(public_key_hash, tweet_id)?;
+    let _ = store_string(host, &owner_path, public_key_hash)?;
     store_flag(host, &path)
 }
 
+/// Read the owner of a tweet
+///
+/// The tweets created before the owner was recorded don't have one
+pub fn read_tweet_owner<R: Runtime>(host: &mut R, tweet_id: &u64) -> Result<Option<PublicKeyHash>> {
+    let owner_path = tweet_owner_path(tweet_id)?;
+    let owner = read_string(host, &owner_path)?;
+    match owner {
+        None => Ok(None),
+        Some(str) => Ok(Some(PublicKeyHash::from_b58(&str)?)),
+    }
+}
+
 /// Checks if the user is owner of the tweet
+///
+/// If the tweet has no recorded owner, the "owned" path of the account is used
 pub fn is_owner<R: Runtime>(
     host: &mut R,
     public_key_hash: &PublicKeyHash,
     tweet_id: &u64,
 ) -> Result<()> {
-    let path = account_owned_tweet_path(public_key_hash, tweet_id)?;
-    let is_present = exists(host, &path)?;
+    let owner = read_tweet_owner(host, tweet_id)?;
+    let is_owner = match owner {
+        Some(owner) => &owner == public_key_hash,
+        None => {
+            let path = account_owned_tweet_path(public_key_hash, tweet_id)?;
+            exists(host, &path)?
+        }
+    };
 
-    match is_present {
+    match is_owner {
         true => Ok(()),
         false => Err(Error::NotOwner),
     }
 }
 
 /// Transfer a tweet from a user to another one
+///
+/// The owner of the tweet is updated, and the "owned" paths of both accounts accordingly
+/// The listing of the tweet is not valid anymore, so it is deleted
 /// Does not check if the user owns the tweet
 pub fn transfer<R: Runtime>(
     host: &mut R,
@@ -340,30 +668,254 @@ pub fn transfer<R: Runtime>(
     destination: &PublicKeyHash,
 ) -> Result<()> {
     let from = account_owned_tweet_path(public_key_hash, tweet_id)?;
-    let to = account_owned_tweet_path(destination, tweet_id)?;
-    host.store_move(&from, &to).map_err(Error::from)
+    delete(host, &from)?;
+    delete_listing(host, tweet_id)?;
+    set_tweet_owner(host, tweet_id, destination)
+}
+
+/// Read the public key revealed by an account
+pub fn read_public_key<R: Runtime>(
+    host: &mut R,
+    public_key_hash: &PublicKeyHash,
+) -> Result<Option<PublicKey>> {
+    let path = public_key_path(public_key_hash)?;
+    read_string(host, &path)?
+        .map(|public_key| PublicKey::from_b58(&public_key))
+        .transpose()
+        .map_err(Error::from)
+}
+
+/// Stores the public key revealed by an account, encoded in b58
+pub fn store_public_key<R: Runtime>(
+    host: &mut R,
+    public_key_hash: &PublicKeyHash,
+    public_key: &PublicKey,
+) -> Result<()> {
+    let path = public_key_path(public_key_hash)?;
+    let _ = store_string(host, &path, &public_key.to_b58())?;
+    Ok(())
+}
+
+/// Read the balance of an account, in mutez
+///
+/// The balance of an unknown account is 0
+pub fn read_balance<R: Runtime>(host: &mut R, public_key_hash: &PublicKeyHash) -> Result<u64> {
+    let path = balance_path(public_key_hash)?;
+    let balance = read_u64(host, &path)?;
+    Ok(balance.unwrap_or_default())
+}
+
+/// Store the balance of an account, in mutez
+pub fn store_balance<R: Runtime>(
+    host: &mut R,
+    public_key_hash: &PublicKeyHash,
+    balance: &u64,
+) -> Result<()> {
+    let path = balance_path(public_key_hash)?;
+    let _ = store_u64(host, &path, balance)?;
+    Ok(())
+}
+
+/// Store the listing of a tweet
+///
+/// A previous listing of the tweet is replaced
+pub fn store_listing<R: Runtime>(host: &mut R, tweet_id: &u64, listing: &Listing) -> Result<()> {
+    let price_path = tweet_listing_price_path(tweet_id)?;
+    let seller_path = tweet_listing_seller_path(tweet_id)?;
+    let _ = store_u64(host, &price_path, &listing.price)?;
+    let _ = store_string(host, &seller_path, &listing.seller)?;
+    Ok(())
+}
+
+/// Read the listing of a tweet
+pub fn read_listing<R: Runtime>(host: &mut R, tweet_id: &u64) -> Result<Option<Listing>> {
+    let price_path = tweet_listing_price_path(tweet_id)?;
+    let seller_path = tweet_listing_seller_path(tweet_id)?;
+    let price = read_u64(host, &price_path)?;
+    let seller = read_string(host, &seller_path)?;
+    match (price, seller) {
+        (Some(price), Some(seller)) => {
+            let seller = PublicKeyHash::from_b58(&seller)?;
+            Ok(Some(Listing { seller, price }))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Deletes the listing of a tweet
+pub fn delete_listing<R: Runtime>(host: &mut R, tweet_id: &u64) -> Result<()> {
+    let path = tweet_listing_path(tweet_id)?;
+    delete(host, &path)
+}
+
+/// Store the offer of a buyer for a tweet, in mutez
+///
+/// A previous offer of the buyer is replaced
+pub fn store_offer<R: Runtime>(
+    host: &mut R,
+    tweet_id: &u64,
+    buyer: &PublicKeyHash,
+    price: &u64,
+) -> Result<()> {
+    let path = tweet_offer_path(tweet_id, buyer)?;
+    let _ = store_u64(host, &path, price)?;
+    Ok(())
+}
+
+/// Read the offer of a buyer for a tweet
+pub fn read_offer<R: Runtime>(
+    host: &mut R,
+    tweet_id: &u64,
+    buyer: &PublicKeyHash,
+) -> Result<Option<u64>> {
+    let path = tweet_offer_path(tweet_id, buyer)?;
+    read_u64(host, &path)
+}
+
+/// Deletes the offer of a buyer for a tweet
+pub fn delete_offer<R: Runtime>(host: &mut R, tweet_id: &u64, buyer: &PublicKeyHash) -> Result<()> {
+    let path = tweet_offer_path(tweet_id, buyer)?;
+    delete(host, &path)
 }
 
 // Stores a receipt under /receipt/{hash}
+//
+// The receipt is also indexed by level under /receipts_by_level/{level}/{n}
 pub fn store_receipt<'a, R: Runtime>(host: &mut R, receipt: &'a Receipt) -> Result<&'a Receipt> {
     let success_path = receipt_success_path(receipt)?;
+    let level_path = receipt_level_path(receipt)?;
+
+    let bytes_read_path = receipt_bytes_read_path(receipt)?;
+    let bytes_written_path = receipt_bytes_written_path(receipt)?;
 
     store_bool(host, &success_path, receipt.success())?;
+    let _ = store_u32(host, &level_path, &receipt.level())?;
+    let _ = store_u64(host, &bytes_read_path, &receipt.metrics().bytes_read)?;
+    let _ = store_u64(host, &bytes_written_path, &receipt.metrics().bytes_written)?;
+
+    let level = receipt.level();
+    let receipts_level_path = receipts_level_path(&level)?;
+    let n = match exists(host, &receipts_level_path)? {
+        false => 0,
+        true => host.store_count_subkeys(&receipts_level_path)?,
+    };
+    let entry_path = receipts_level_entry_path(&level, &n)?;
+    let _ = store_string(host, &entry_path, receipt.hash())?;
+
+    // The garbage collection starts from the first stored receipt
+    if read_receipts_gc_cursor(host)?.is_none() {
+        store_receipts_gc_cursor(host, &level, &0)?;
+    }
 
     Ok(receipt)
 }
 
-/// Returns Ok if the tweet is not collected
-pub fn is_not_collected<R: Runtime>(host: &mut R, tweet_id: &u64) -> Result<()> {
+/// Read the hash of the n-th receipt of a given level
+pub fn read_receipt_hash<R: Runtime>(host: &mut R, level: &u32, n: &u64) -> Result<Option<String>> {
+    let entry_path = receipts_level_entry_path(level, n)?;
+    read_string(host, &entry_path)
+}
+
+/// Deletes a receipt from its hash, if it has been stored at the given level
+///
+/// The same hash can be stored again at a later level, by a message sent twice,
+/// the receipt is then kept until its own level is collected
+pub fn delete_receipt<R: Runtime>(host: &mut R, hash: &str, level: &u32) -> Result<()> {
+    let level_path = receipt_hash_field_path(hash, "/level")?;
+    if read_u32(host, &level_path)?.as_ref() != Some(level) {
+        return Ok(());
+    }
+    let receipt_path = receipt_path(hash)?;
+    delete(host, &receipt_path)
+}
+
+/// Deletes the receipts index of a given level
+pub fn delete_receipts_level<R: Runtime>(host: &mut R, level: &u32) -> Result<()> {
+    let receipts_level_path = receipts_level_path(level)?;
+    delete(host, &receipts_level_path)
+}
+
+/// Read the position of the garbage collection of the receipts
+///
+/// The position is the level and the index of the next receipt to delete
+pub fn read_receipts_gc_cursor<R: Runtime>(host: &mut R) -> Result<Option<(u32, u64)>> {
+    let level = read_u32(host, &RECEIPTS_GC_LEVEL)?;
+    let index = read_u64(host, &RECEIPTS_GC_INDEX)?;
+    match (level, index) {
+        (Some(level), Some(index)) => Ok(Some((level, index))),
+        _ => Ok(None),
+    }
+}
+
+/// Store the position of the garbage collection of the receipts
+pub fn store_receipts_gc_cursor<R: Runtime>(host: &mut R, level: &u32, index: &u64) -> Result<()> {
+    let _ = store_u32(host, &RECEIPTS_GC_LEVEL, level)?;
+    let _ = store_u64(host, &RECEIPTS_GC_INDEX, index)?;
+    Ok(())
+}
+
+/// Checks if the tweet is collected (being withdrawn or minted on the layer 1)
+pub fn is_collected<R: Runtime>(host: &mut R, tweet_id: &u64) -> Result<bool> {
     let tweet_collected_block_path = tweet_collected_block_path(tweet_id)?;
-    let is_present = exists(host, &tweet_collected_block_path)?;
+    exists(host, &tweet_collected_block_path)
+}
 
-    match is_present {
+/// Returns Ok if the tweet is not collected
+pub fn is_not_collected<R: Runtime>(host: &mut R, tweet_id: &u64) -> Result<()> {
+    match is_collected(host, tweet_id)? {
         true => Err(Error::TweetAlreadyCollected),
         false => Ok(()),
     }
 }
 
+/// Checks if an account has reported a tweet
+pub fn is_reported<R: Runtime>(
+    host: &mut R,
+    tweet_id: &u64,
+    account: &PublicKeyHash,
+) -> Result<bool> {
+    let path = tweet_report_path(tweet_id, account)?;
+    exists(host, &path)
+}
+
+/// Stores the report of an account and increments the report counter of the tweet
+///
+/// Returns the number of reports of the tweet
+pub fn add_report<R: Runtime>(
+    host: &mut R,
+    tweet_id: &u64,
+    account: &PublicKeyHash,
+    reason: &str,
+) -> Result<u64> {
+    let report_path = tweet_report_path(tweet_id, account)?;
+    let report_count_path = tweet_report_count_path(tweet_id)?;
+
+    let report_count = read_u64(host, &report_count_path)?.unwrap_or_default() + 1;
+    let _ = store_string(host, &report_path, &reason)?;
+    let _ = store_u64(host, &report_count_path, &report_count)?;
+    Ok(report_count)
+}
+
+/// Hides a tweet
+pub fn set_hidden<R: Runtime>(host: &mut R, tweet_id: &u64) -> Result<()> {
+    let path = tweet_hidden_path(tweet_id)?;
+    store_flag(host, &path)
+}
+
+/// Checks if a tweet is hidden
+pub fn is_hidden<R: Runtime>(host: &mut R, tweet_id: &u64) -> Result<bool> {
+    let path = tweet_hidden_path(tweet_id)?;
+    exists(host, &path)
+}
+
+/// Returns Ok if the tweet is not hidden
+pub fn is_not_hidden<R: Runtime>(host: &mut R, tweet_id: &u64) -> Result<()> {
+    match is_hidden(host, tweet_id)? {
+        true => Err(Error::TweetHidden),
+        false => Ok(()),
+    }
+}
+
 /// Set the block when the tweet has been collected
 pub fn set_collected_block<R: Runtime>(
     host: &mut R,
@@ -384,3 +936,373 @@ pub fn add_collecting_tweet_to_account<R: Runtime>(
     let account_collecting_path = account_collecting_path(public_key_hash, tweet_id)?;
     store_flag(host, &account_collecting_path)
 }
+
+/// Set the level when the tweet has been written
+pub fn set_written_level<R: Runtime>(host: &mut R, tweet_id: &u64, level: &u32) -> Result<()> {
+    let tweet_written_level_path = tweet_written_level_path(tweet_id)?;
+    let _ = store_u32(host, &tweet_written_level_path, level)?;
+    Ok(())
+}
+
+/// Read the level when the tweet has been written
+///
+/// The tweets written before this level was recorded don't have one
+pub fn read_written_level<R: Runtime>(host: &mut R, tweet_id: &u64) -> Result<Option<u32>> {
+    let tweet_written_level_path = tweet_written_level_path(tweet_id)?;
+    read_u32(host, &tweet_written_level_path)
+}
+
+/// Indicates that a tweet is not collected anymore
+pub fn unset_collected_block<R: Runtime>(host: &mut R, tweet_id: &u64) -> Result<()> {
+    let tweet_collected_block_path = tweet_collected_block_path(tweet_id)?;
+    delete(host, &tweet_collected_block_path)
+}
+
+/// Indicates that a tweet is not being collected by the given user anymore
+pub fn remove_collecting_tweet_from_account<R: Runtime>(
+    host: &mut R,
+    public_key_hash: &PublicKeyHash,
+    tweet_id: &u64,
+) -> Result<()> {
+    let account_collecting_path = account_collecting_path(public_key_hash, tweet_id)?;
+    delete(host, &account_collecting_path)
+}
+
+/// Increment the number of outbox messages of a level and return the previous one
+///
+/// The returned value is the index of the next outbox message
+pub fn increment_outbox_size<R: Runtime>(host: &mut R, level: &u32) -> Result<u32> {
+    let outbox_size_path = outbox_size_path(level)?;
+    let previous_size = read_u32(host, &outbox_size_path)?.unwrap_or_default();
+    let next_size = previous_size + 1;
+    let _ = store_u32(host, &outbox_size_path, &next_size)?;
+    Ok(previous_size)
+}
+
+/// Read the number of outbox messages written at a given level
+pub fn read_outbox_size<R: Runtime>(host: &mut R, level: &u32) -> Result<u32> {
+    let outbox_size_path = outbox_size_path(level)?;
+    let size = read_u32(host, &outbox_size_path)?.unwrap_or_default();
+    Ok(size)
+}
+
+/// Deletes all the outbox messages of a given level
+pub fn delete_outbox_level<R: Runtime>(host: &mut R, level: &u32) -> Result<()> {
+    let outbox_level_path = outbox_level_path(level)?;
+    delete(host, &outbox_level_path)
+}
+
+/// Stores a withdrawal under /outbox/{level}/{index}
+///
+/// A reference to the outbox message is also kept under /tweets/{id}/withdrawal
+pub fn store_withdrawal<'a, R: Runtime>(
+    host: &mut R,
+    withdrawal: &'a Withdrawal,
+) -> Result<&'a Withdrawal> {
+    let Withdrawal {
+        level,
+        index,
+        tweet_id,
+        owner,
+    } = withdrawal;
+
+    let tweet_path = outbox_message_tweet_path(level, index)?;
+    let owner_path = outbox_message_owner_path(level, index)?;
+    let level_path = tweet_withdrawal_level_path(tweet_id)?;
+    let index_path = tweet_withdrawal_index_path(tweet_id)?;
+
+    let _ = store_u64(host, &tweet_path, tweet_id)?;
+    let _ = store_string(host, &owner_path, owner)?;
+    let _ = store_u32(host, &level_path, level)?;
+    let _ = store_u32(host, &index_path, index)?;
+
+    Ok(withdrawal)
+}
+
+/// Read the withdrawal of a given outbox message
+pub fn read_withdrawal<R: Runtime>(
+    host: &mut R,
+    level: &u32,
+    index: &u32,
+) -> Result<Option<Withdrawal>> {
+    let tweet_path = outbox_message_tweet_path(level, index)?;
+    let owner_path = outbox_message_owner_path(level, index)?;
+
+    let tweet_id = read_u64(host, &tweet_path)?;
+    let owner = read_string(host, &owner_path)?;
+    let owner = match owner {
+        None => None,
+        Some(str) => Some(PublicKeyHash::from_b58(&str)?),
+    };
+
+    match (tweet_id, owner) {
+        (Some(tweet_id), Some(owner)) => Ok(Some(Withdrawal {
+            level: *level,
+            index: *index,
+            tweet_id,
+            owner,
+        })),
+        _ => Ok(None),
+    }
+}
+
+/// Read the withdrawal of a tweet
+///
+/// Returns None if the tweet is not being withdrawn
+pub fn read_tweet_withdrawal<R: Runtime>(
+    host: &mut R,
+    tweet_id: &u64,
+) -> Result<Option<Withdrawal>> {
+    let level_path = tweet_withdrawal_level_path(tweet_id)?;
+    let index_path = tweet_withdrawal_index_path(tweet_id)?;
+
+    let level = read_u32(host, &level_path)?;
+    let index = read_u32(host, &index_path)?;
+
+    match (level, index) {
+        (Some(level), Some(index)) => read_withdrawal(host, &level, &index),
+        _ => Ok(None),
+    }
+}
+
+/// Deletes a withdrawal and its reference in the tweet
+pub fn delete_withdrawal<R: Runtime>(host: &mut R, withdrawal: &Withdrawal) -> Result<()> {
+    let Withdrawal {
+        level,
+        index,
+        tweet_id,
+        ..
+    } = withdrawal;
+    let outbox_message_path = outbox_message_path(level, index)?;
+    let tweet_withdrawal_path = tweet_withdrawal_path(tweet_id)?;
+
+    delete(host, &outbox_message_path)?;
+    delete(host, &tweet_withdrawal_path)
+}
+
+/// Read the configuration of the kernel
+///
+/// The missing values are replaced by their default
+pub fn read_config<R: Runtime>(host: &mut R) -> Result<Config> {
+    let events_retention =
+        read_u32(host, &CONFIG_EVENTS_RETENTION)?.unwrap_or(DEFAULT_EVENTS_RETENTION);
+    let receipts_retention =
+        read_u32(host, &CONFIG_RECEIPTS_RETENTION)?.unwrap_or(DEFAULT_RECEIPTS_RETENTION);
+    let reports_threshold =
+        read_u32(host, &CONFIG_REPORTS_THRESHOLD)?.unwrap_or(DEFAULT_REPORTS_THRESHOLD);
+    let admin = read_string(host, &CONFIG_ADMIN)?
+        .map(|admin| PublicKeyHash::from_b58(&admin))
+        .transpose()?;
+    Ok(Config {
+        events_retention,
+        receipts_retention,
+        reports_threshold,
+        admin,
+    })
+}
+
+/// Appends an event to the events of the given level
+///
+/// Returns the index of the event
+pub fn store_event<R: Runtime>(host: &mut R, level: &u32, event: &Event) -> Result<u64> {
+    let events_level_path = events_level_path(level)?;
+    let n = match exists(host, &events_level_path)? {
+        false => 0,
+        true => host.store_count_subkeys(&events_level_path)?,
+    };
+
+    // The pruning starts from the first stored event
+    if read_events_gc_level(host)?.is_none() {
+        store_events_gc_level(host, level)?;
+    }
+
+    let event_path = event_path(level, &n)?;
+    let json = serde_json_wasm::to_string(event)?;
+    host.store_write(&event_path, json.as_bytes(), 0)
+        .map_err(Error::from)
+        .map(|_| n)
+}
+
+/// Deletes all the events of a given level
+pub fn delete_events<R: Runtime>(host: &mut R, level: &u32) -> Result<()> {
+    let events_level_path = events_level_path(level)?;
+    delete(host, &events_level_path)
+}
+
+/// Read the next level whose events will be pruned
+pub fn read_events_gc_level<R: Runtime>(host: &mut R) -> Result<Option<u32>> {
+    read_u32(host, &EVENTS_GC_LEVEL)
+}
+
+/// Store the next level whose events will be pruned
+pub fn store_events_gc_level<R: Runtime>(host: &mut R, level: &u32) -> Result<()> {
+    let _ = store_u32(host, &EVENTS_GC_LEVEL, level)?;
+    Ok(())
+}
+
+/// Adds the metrics of a message to the aggregates of its content kind at a given level
+///
+/// The aggregates are the number of messages, the bytes read and the bytes written
+pub fn add_level_metrics<R: Runtime>(
+    host: &mut R,
+    level: &u32,
+    kind: &str,
+    metrics: &Metrics,
+) -> Result<()> {
+    let messages_path = metrics_field_path(level, kind, "messages")?;
+    let bytes_read_path = metrics_field_path(level, kind, "bytes_read")?;
+    let bytes_written_path = metrics_field_path(level, kind, "bytes_written")?;
+
+    // The pruning starts from the first aggregated metrics
+    if read_metrics_gc_level(host)?.is_none() {
+        store_metrics_gc_level(host, level)?;
+    }
+
+    let messages = read_u64(host, &messages_path)?.unwrap_or_default();
+    let aggregate = Metrics {
+        bytes_read: read_u64(host, &bytes_read_path)?.unwrap_or_default(),
+        bytes_written: read_u64(host, &bytes_written_path)?.unwrap_or_default(),
+    }
+    .add(metrics);
+
+    let _ = store_u64(host, &messages_path, &messages.saturating_add(1))?;
+    let _ = store_u64(host, &bytes_read_path, &aggregate.bytes_read)?;
+    let _ = store_u64(host, &bytes_written_path, &aggregate.bytes_written)?;
+    Ok(())
+}
+
+/// Deletes the metrics of a given level
+pub fn delete_metrics<R: Runtime>(host: &mut R, level: &u32) -> Result<()> {
+    let metrics_level_path = metrics_level_path(level)?;
+    delete(host, &metrics_level_path)
+}
+
+/// Read the next level whose metrics will be pruned
+pub fn read_metrics_gc_level<R: Runtime>(host: &mut R) -> Result<Option<u32>> {
+    read_u32(host, &METRICS_GC_LEVEL)
+}
+
+/// Store the next level whose metrics will be pruned
+pub fn store_metrics_gc_level<R: Runtime>(host: &mut R, level: &u32) -> Result<()> {
+    let _ = store_u32(host, &METRICS_GC_LEVEL, level)?;
+    Ok(())
+}
+
+/// Appends a direct message to the inbox of the recipient
+///
+/// Returns the index of the direct message
+pub fn store_direct_message<R: Runtime>(
+    host: &mut R,
+    to: &PublicKeyHash,
+    direct_message: &DirectMessage,
+) -> Result<u64> {
+    let DirectMessage {
+        from,
+        ciphertext,
+        ephemeral_pk,
+        level,
+    } = direct_message;
+    let counter_path = account_inbox_counter_path(to)?;
+    let index = read_u64(host, &counter_path)?.unwrap_or_default();
+
+    let from_path = account_inbox_field_path(to, &index, "/from")?;
+    let ciphertext_path = account_inbox_field_path(to, &index, "/ciphertext")?;
+    let ephemeral_pk_path = account_inbox_field_path(to, &index, "/ephemeral_pk")?;
+    let level_path = account_inbox_field_path(to, &index, "/level")?;
+
+    let _ = store_string(host, &from_path, from)?;
+    let _ = store_string(host, &ciphertext_path, ciphertext)?;
+    let _ = store_string(host, &ephemeral_pk_path, ephemeral_pk)?;
+    let _ = store_u32(host, &level_path, level)?;
+    let _ = store_u64(host, &counter_path, &(index + 1))?;
+    Ok(index)
+}
+
+/// Checks if a direct message is in the inbox of an account
+pub fn is_in_inbox<R: Runtime>(
+    host: &mut R,
+    public_key_hash: &PublicKeyHash,
+    index: &u64,
+) -> Result<bool> {
+    let from_path = account_inbox_field_path(public_key_hash, index, "/from")?;
+    exists(host, &from_path)
+}
+
+/// Deletes a direct message from the inbox of an account
+pub fn delete_direct_message<R: Runtime>(
+    host: &mut R,
+    public_key_hash: &PublicKeyHash,
+    index: &u64,
+) -> Result<()> {
+    let path = account_inbox_field_path(public_key_hash, index, "")?;
+    delete(host, &path)
+}
+
+/// Returns the number of direct messages in the inbox of an account
+pub fn inbox_size<R: Runtime>(host: &mut R, public_key_hash: &PublicKeyHash) -> Result<u64> {
+    let inbox_path = account_inbox_path(public_key_hash)?;
+    match exists(host, &inbox_path)? {
+        false => Ok(0),
+        true => host.store_count_subkeys(&inbox_path).map_err(Error::from),
+    }
+}
+
+/// Blocks the direct messages of an account
+pub fn set_blocked<R: Runtime>(
+    host: &mut R,
+    public_key_hash: &PublicKeyHash,
+    blocked: &PublicKeyHash,
+) -> Result<()> {
+    let path = account_blocked_path(public_key_hash, blocked)?;
+    store_flag(host, &path)
+}
+
+/// Unblocks the direct messages of an account
+pub fn unset_blocked<R: Runtime>(
+    host: &mut R,
+    public_key_hash: &PublicKeyHash,
+    blocked: &PublicKeyHash,
+) -> Result<()> {
+    let path = account_blocked_path(public_key_hash, blocked)?;
+    delete(host, &path)
+}
+
+/// Checks if an account has blocked another one
+pub fn is_blocked<R: Runtime>(
+    host: &mut R,
+    public_key_hash: &PublicKeyHash,
+    blocked: &PublicKeyHash,
+) -> Result<bool> {
+    let path = account_blocked_path(public_key_hash, blocked)?;
+    exists(host, &path)
+}
+
+/// Read the number of direct messages sent by an account at a given level
+pub fn read_direct_messages_sent<R: Runtime>(
+    host: &mut R,
+    public_key_hash: &PublicKeyHash,
+    level: &u32,
+) -> Result<u32> {
+    let level_path = account_rate_level_path(public_key_hash)?;
+    let count_path = account_rate_count_path(public_key_hash)?;
+    match read_u32(host, &level_path)? {
+        Some(last_level) if &last_level == level => {
+            Ok(read_u32(host, &count_path)?.unwrap_or_default())
+        }
+        _ => Ok(0),
+    }
+}
+
+/// Stores the number of direct messages sent by an account at a given level
+pub fn store_direct_messages_sent<R: Runtime>(
+    host: &mut R,
+    public_key_hash: &PublicKeyHash,
+    level: &u32,
+    count: &u32,
+) -> Result<()> {
+    let level_path = account_rate_level_path(public_key_hash)?;
+    let count_path = account_rate_count_path(public_key_hash)?;
+    let _ = store_u32(host, &level_path, level)?;
+    let _ = store_u32(host, &count_path, count)?;
+    Ok(())
+}
@@ -0,0 +1,296 @@
+//! Test support for the tzwitter state machine
+//!
+//! The messages are signed by deterministic keypairs, so the tests don't rely on hand-signed inputs.
+//! Random sequences of operations are applied to the kernel and to a reference model,
+//! the state of the kernel is then checked against the model after each operation.
+
+use std::collections::HashSet;
+
+use proptest::prelude::*;
+use serde::Serialize;
+use tezos_data_encoding::enc::BinWriter;
+use tezos_smart_rollup::{
+    prelude::*,
+    storage::path::{OwnedPath, RefPath},
+    testing::prelude::MockHost,
+};
+
+use crate::{
+    constants::MAGIC_BYTE,
+    core::{
+        message::{Content, Message, PostTweet, Transfer},
+        nonce::Nonce,
+        public_key_hash::PublicKeyHash,
+    },
+    entry,
+    storage::{exists, read_account, read_public_key, read_tweet, read_tweet_owner},
+};
+
+pub use tzwitter_types::signer::Signer;
+
+/// External message of the inbox, prefixed by the MAGIC_BYTE
+pub struct ExternalMessage(Vec<u8>);
+
+impl<C: Serialize> From<&Message<C>> for ExternalMessage {
+    fn from(message: &Message<C>) -> Self {
+        let json = serde_json_wasm::to_string(message).unwrap();
+        ExternalMessage(json.into_bytes())
+    }
+}
+
+impl BinWriter for ExternalMessage {
+    fn bin_write(&self, output: &mut Vec<u8>) -> tezos_data_encoding::enc::BinResult {
+        output.push(MAGIC_BYTE);
+        output.extend_from_slice(&self.0);
+        Ok(())
+    }
+}
+
+/// Number of accounts used by the generated operations
+const ACCOUNTS: usize = 3;
+
+/// Maximum tweet id used by the generated operations
+const TWEETS: u64 = 8;
+
+/// Operations of a test, the accounts are the indices of the signers
+#[derive(Debug, Clone)]
+pub enum Operation {
+    Post(usize),
+    Like(usize, u64),
+    Transfer(usize, u64, usize),
+    Collect(usize, u64),
+    /// Sends a message with an already used nonce
+    Replay(usize, u64),
+}
+
+/// Generates a random operation
+pub fn operation() -> impl Strategy<Value = Operation> {
+    prop_oneof![
+        (0..ACCOUNTS).prop_map(Operation::Post),
+        (0..ACCOUNTS, 0..TWEETS).prop_map(|(account, id)| Operation::Like(account, id)),
+        (0..ACCOUNTS, 0..TWEETS, 0..ACCOUNTS)
+            .prop_map(|(from, id, to)| Operation::Transfer(from, id, to)),
+        (0..ACCOUNTS, 0..TWEETS).prop_map(|(account, id)| Operation::Collect(account, id)),
+        (0..ACCOUNTS, 0..TWEETS).prop_map(|(account, id)| Operation::Replay(account, id)),
+    ]
+}
+
+/// A tweet of the reference model
+struct ModelTweet {
+    owner: usize,
+    likes: HashSet<usize>,
+    collected: bool,
+}
+
+/// Reference model of the kernel
+///
+/// It only keeps what is needed to predict the result of an operation
+#[derive(Default)]
+pub struct Model {
+    tweets: Vec<ModelTweet>,
+    nonces: [u64; ACCOUNTS],
+}
+
+impl Model {
+    /// Applies an operation to the model
+    ///
+    /// Returns true if the kernel should process it successfully
+    fn apply(&mut self, operation: &Operation) -> bool {
+        let account = match operation {
+            Operation::Replay(_, _) => return false,
+            Operation::Post(account)
+            | Operation::Like(account, _)
+            | Operation::Transfer(account, _, _)
+            | Operation::Collect(account, _) => *account,
+        };
+        // A correctly signed message always increments the nonce
+        self.nonces[account] += 1;
+
+        match operation {
+            Operation::Post(author) => {
+                self.tweets.push(ModelTweet {
+                    owner: *author,
+                    likes: HashSet::default(),
+                    collected: false,
+                });
+                true
+            }
+            Operation::Like(account, id) => match self.tweets.get_mut(*id as usize) {
+                None => false,
+                Some(tweet) => tweet.likes.insert(*account),
+            },
+            Operation::Transfer(from, id, to) => match self.tweets.get_mut(*id as usize) {
+                Some(tweet) if tweet.owner == *from && from != to && !tweet.collected => {
+                    tweet.owner = *to;
+                    true
+                }
+                _ => false,
+            },
+            Operation::Collect(account, id) => match self.tweets.get_mut(*id as usize) {
+                Some(tweet) if tweet.owner == *account && !tweet.collected => {
+                    tweet.collected = true;
+                    true
+                }
+                _ => false,
+            },
+            Operation::Replay(_, _) => false,
+        }
+    }
+}
+
+/// Runs the operations against the kernel and the model
+pub struct Harness {
+    host: MockHost,
+    signers: Vec<Signer>,
+    model: Model,
+}
+
+impl Default for Harness {
+    fn default() -> Self {
+        Harness {
+            host: MockHost::default(),
+            signers: (1..=ACCOUNTS as u8).map(Signer::from_seed).collect(),
+            model: Model::default(),
+        }
+    }
+}
+
+impl Harness {
+    fn public_key_hash(&self, account: usize) -> PublicKeyHash {
+        self.signers[account].public_key_hash()
+    }
+
+    /// Builds the signed message of an operation
+    fn message(&self, operation: &Operation) -> Message {
+        let nonce = |account: usize| Nonce(self.model.nonces[account] + 1);
+        match operation {
+            Operation::Post(author) => {
+                let content = Content::PostTweet(PostTweet {
+                    author: self.public_key_hash(*author),
+                    content: "Hello world".to_string(),
+                });
+                self.signers[*author].sign(nonce(*author), content)
+            }
+            Operation::Like(account, id) => {
+                self.signers[*account].sign(nonce(*account), Content::LikeTweet(*id))
+            }
+            Operation::Transfer(from, id, to) => {
+                let content = Content::Transfer(Transfer {
+                    tweet_id: *id,
+                    destination: self.public_key_hash(*to),
+                });
+                self.signers[*from].sign(nonce(*from), content)
+            }
+            Operation::Collect(account, id) => {
+                self.signers[*account].sign(nonce(*account), Content::Collect(*id))
+            }
+            Operation::Replay(account, id) => {
+                let nonce = Nonce(self.model.nonces[*account]);
+                self.signers[*account].sign(nonce, Content::LikeTweet(*id))
+            }
+        }
+    }
+
+    /// Applies the operation to the kernel and to the model
+    ///
+    /// Returns the result of the kernel, and the one expected by the model
+    pub fn apply(&mut self, operation: &Operation) -> (bool, bool) {
+        let message = self.message(operation);
+        let hash = message.hash().to_string();
+        self.host.add_external(ExternalMessage::from(&message));
+        entry(&mut self.host);
+
+        let expected = self.model.apply(operation);
+        let success = self.receipt_success(&hash);
+        (success, expected)
+    }
+
+    /// Reads the success of a receipt, a missing receipt is a failure
+    fn receipt_success(&mut self, hash: &str) -> bool {
+        let path = OwnedPath::try_from(format!("/receipts/{}/success", hash)).unwrap();
+        match exists(&mut self.host, &path).unwrap() {
+            false => false,
+            true => self.host.store_read(&path, 0, 1).unwrap() == vec![0x01],
+        }
+    }
+
+    fn exists(&mut self, path: &str) -> bool {
+        let path = RefPath::assert_from(path.as_bytes());
+        exists(&mut self.host, &path).unwrap()
+    }
+
+    /// Checks the invariants of the kernel state against the model
+    pub fn check_invariants(&mut self) -> std::result::Result<(), TestCaseError> {
+        // Nonces are only incremented by correctly signed messages
+        // The public key is revealed by the first of them
+        for account in 0..ACCOUNTS {
+            let public_key_hash = self.public_key_hash(account);
+            let public_key = read_public_key(&mut self.host, &public_key_hash).unwrap();
+            let account_state = read_account(&mut self.host, public_key_hash).unwrap();
+            prop_assert_eq!(account_state.nonce.0, self.model.nonces[account]);
+            match self.model.nonces[account] {
+                0 => prop_assert!(public_key.is_none()),
+                _ => prop_assert_eq!(public_key, Some(self.signers[account].public_key())),
+            }
+        }
+
+        let next_id = self.model.tweets.len() as u64;
+        prop_assert!(read_tweet(&mut self.host, &next_id).unwrap().is_none());
+
+        for id in 0..self.model.tweets.len() {
+            let tweet_id = id as u64;
+            let (owner, likes, collected) = {
+                let tweet = &self.model.tweets[id];
+                (tweet.owner, tweet.likes.clone(), tweet.collected)
+            };
+
+            // Single ownership
+            let kernel_owner = read_tweet_owner(&mut self.host, &tweet_id)
+                .unwrap()
+                .unwrap();
+            prop_assert_eq!(&kernel_owner, &self.public_key_hash(owner));
+            for account in 0..ACCOUNTS {
+                let pkh = self.public_key_hash(account).to_string();
+                let owned = self.exists(&format!("/accounts/{}/tweets/owned/{}", pkh, id));
+                prop_assert_eq!(owned, account == owner);
+            }
+
+            // The likes count is equal to the like flags
+            let tweet = read_tweet(&mut self.host, &tweet_id).unwrap().unwrap();
+            let mut flags = 0;
+            for account in 0..ACCOUNTS {
+                let pkh = self.public_key_hash(account).to_string();
+                let liked = self.exists(&format!("/accounts/{}/likes/{}", pkh, id));
+                prop_assert_eq!(liked, likes.contains(&account));
+                flags += liked as u64;
+            }
+            prop_assert_eq!(tweet.likes, flags);
+
+            // Collected tweets stay frozen
+            let frozen = self.exists(&format!("/tweets/{}/collected_level", id));
+            prop_assert_eq!(frozen, collected);
+        }
+        Ok(())
+    }
+}
+
+proptest! {
+    #[test]
+    fn test_state_machine(operations in prop::collection::vec(operation(), 1..50)) {
+        let mut harness = Harness::default();
+        for operation in operations {
+            let (success, expected) = harness.apply(&operation);
+            prop_assert_eq!(success, expected, "{:?}", operation);
+            harness.check_invariants()?;
+        }
+    }
+}
+
+#[test]
+fn test_signed_message_is_accepted() {
+    let mut harness = Harness::default();
+    let (success, expected) = harness.apply(&Operation::Post(0));
+    assert!(expected);
+    assert!(success);
+    harness.check_invariants().unwrap();
+}
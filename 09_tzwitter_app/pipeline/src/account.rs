@@ -1,6 +1,4 @@
-use crate::core::nonce::Nonce;
-
-use super::public_key_hash::PublicKeyHash;
+use tzwitter_types::{nonce::Nonce, public_key_hash::PublicKeyHash};
 
 pub struct Account {
     pub public_key_hash: PublicKeyHash,
@@ -0,0 +1,30 @@
+/// Represents the errors of the pipeline
+///
+/// The application converts them to its own errors
+#[derive(Debug)]
+pub enum Error {
+    /// The signature of the message can't be verified
+    Signature(tzwitter_types::error::Error),
+    InvalidNonce,
+    PublicKeyMismatch,
+}
+
+impl ToString for Error {
+    fn to_string(&self) -> String {
+        let err = match self {
+            Error::Signature(_) => "Invalid signature",
+            Error::InvalidNonce => "Invalid nonce",
+            Error::PublicKeyMismatch => "The public key is not the one revealed by the account",
+        };
+        err.to_string()
+    }
+}
+
+impl From<tzwitter_types::error::Error> for Error {
+    fn from(error: tzwitter_types::error::Error) -> Self {
+        Error::Signature(error)
+    }
+}
+
+/// The functions of the application return their own error
+pub type Result<A, E = Error> = std::result::Result<A, E>;
@@ -0,0 +1,432 @@
+//! Pipeline of the signed messages
+//!
+//! The messages of an application are signed by accounts, identified by their public key hash.
+//! Every message goes through the same stages:
+//! - verify the signature of the message
+//! - verify the public key of the message against the one revealed by the account
+//! - verify the nonce of the message, and increment the one of the account
+//! - apply the content of the message with the state transition of the application
+//! - store the receipt of the message, with the storage costs of the previous stages
+//!
+//! An application defines its content, its state transition and the layout of its durable storage:
+//! the pipeline reads and writes the accounts, the receipts and the metrics through the application,
+//! the replay protection and the metering are handled by the pipeline.
+
+pub mod account;
+pub mod error;
+pub mod metering;
+pub mod receipt;
+
+use serde::de::DeserializeOwned;
+use tezos_smart_rollup::prelude::*;
+use tzwitter_types::{
+    hash::Blake2b,
+    message::{Inner, Message, Payload},
+    metrics::Metrics,
+    nonce::Nonce,
+    public_key::PublicKey,
+    public_key_hash::PublicKeyHash,
+};
+
+use account::Account;
+use error::{Error, Result};
+use metering::MeteredHost;
+use receipt::Receipt;
+
+/// An application plugged in the pipeline
+///
+/// The application owns the layout of the durable storage,
+/// the pipeline only accesses it through the functions of this trait
+pub trait Application {
+    /// Content of the messages of the application
+    type Content: Payload + DeserializeOwned;
+
+    /// Error of the application, the errors of the pipeline are converted to it
+    type Error: From<Error>;
+
+    /// Reads the account of a public key hash, with its last nonce
+    fn read_account<R: Runtime>(
+        host: &mut R,
+        public_key_hash: PublicKeyHash,
+    ) -> Result<Account, Self::Error>;
+
+    /// Stores the account, with its incremented nonce
+    fn store_account<R: Runtime>(host: &mut R, account: &Account) -> Result<(), Self::Error>;
+
+    /// Reads the public key revealed by an account
+    fn read_public_key<R: Runtime>(
+        host: &mut R,
+        public_key_hash: &PublicKeyHash,
+    ) -> Result<Option<PublicKey>, Self::Error>;
+
+    /// Stores the public key revealed by the first valid message of an account
+    fn store_public_key<R: Runtime>(
+        host: &mut R,
+        public_key_hash: &PublicKeyHash,
+        public_key: &PublicKey,
+    ) -> Result<(), Self::Error>;
+
+    /// Stores the receipt of a processed message
+    ///
+    /// The application is in charge of deleting its old receipts
+    fn store_receipt<R: Runtime>(host: &mut R, receipt: &Receipt) -> Result<(), Self::Error>;
+
+    /// Adds the metrics of a message to the aggregates of its content kind at a given level
+    fn add_level_metrics<R: Runtime>(
+        host: &mut R,
+        level: &u32,
+        kind: &str,
+        metrics: &Metrics,
+    ) -> Result<(), Self::Error>;
+
+    /// Applies the content of a message to the state
+    ///
+    /// The signature and the nonce have been verified,
+    /// the given account already has its incremented nonce
+    fn apply<R: Runtime>(
+        host: &mut R,
+        level: u32,
+        hash: &Blake2b,
+        account: &Account,
+        content: Self::Content,
+    ) -> Result<(), Self::Error>;
+}
+
+/// Verify the signature of a message
+///
+/// Returns the inner message
+pub fn verify_signature<C: Payload>(message: Message<C>) -> Result<Inner<C>> {
+    let signature = message.signature();
+    let pkey = message.public_key();
+    let inner = message.inner();
+    let hash = inner.hash();
+
+    signature.verify(pkey, hash.as_ref())?;
+    let Message { inner, .. } = message;
+    Ok(inner)
+}
+
+/// Verify the public key of a message
+///
+/// An account reveals its public key with its first valid message,
+/// the next messages should be signed with the same key
+pub fn verify_public_key(public_key: &PublicKey, revealed: Option<&PublicKey>) -> Result<()> {
+    match revealed {
+        Some(revealed) if revealed != public_key => Err(Error::PublicKeyMismatch),
+        _ => Ok(()),
+    }
+}
+
+/// Verify the nonce of the inner message
+///
+/// If the nonce is correct the content of the inner is returned
+pub fn verify_nonce<C>(inner: Inner<C>, nonce: &Nonce) -> Result<C> {
+    let next_nonce = nonce.next();
+    let inner_nonce = inner.nonce();
+    if &next_nonce == inner_nonce {
+        let Inner { content, .. } = inner;
+        Ok(content)
+    } else {
+        Err(Error::InvalidNonce)
+    }
+}
+
+/// A step is processing only one message from the inbox
+///
+/// It will execute several sub steps:
+/// - verify the signature of the message
+/// - verify the public key of the message, and reveal it if it's the first one of the account
+/// - verify the nonce of the message
+/// - apply the message
+pub fn step<A: Application, R: Runtime>(
+    host: &mut R,
+    message: Message<A::Content>,
+    level: u32,
+) -> Result<(), A::Error> {
+    let hash = message.hash();
+    let public_key = message.public_key().clone();
+    let public_key_hash = PublicKeyHash::from(&public_key);
+    debug_msg!(host, "Message is deserialized\n");
+
+    let inner = verify_signature(message)?;
+    debug_msg!(host, "Signature is correct\n");
+
+    // Verify the public key
+    let revealed = A::read_public_key(host, &public_key_hash)?;
+    verify_public_key(&public_key, revealed.as_ref())?;
+
+    // Verify the nonce
+    let account = A::read_account(host, public_key_hash)?;
+    let content = verify_nonce(inner, account.nonce())?;
+    let account = account.increment_nonce();
+    A::store_account(host, &account)?;
+    if revealed.is_none() {
+        A::store_public_key(host, &account.public_key_hash, &public_key)?;
+    }
+
+    A::apply(host, level, &hash, &account, content)
+}
+
+/// Processes a message and stores its receipt
+///
+/// The bytes read and written by the step are recorded in the receipt,
+/// and added to the metrics of the level and of the kind of the content
+///
+/// Returns the result of the step, or the error of the storage of the receipt
+pub fn process<A: Application, R: Runtime>(
+    host: &mut R,
+    message: Message<A::Content>,
+    level: u32,
+) -> Result<(), A::Error> {
+    // If the message is processed we can extract the hash of the message
+    let hash = message.hash();
+    let kind = message.inner().content.kind();
+
+    let mut metered_host = MeteredHost::new(host);
+    let result = step::<A, _>(&mut metered_host, message, level);
+    let metrics = metered_host.metrics();
+
+    let receipt = Receipt::new(hash, level, &result).with_metrics(metrics);
+    A::store_receipt(host, &receipt)?;
+    A::add_level_metrics(host, &level, kind, &metrics)?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use tezos_smart_rollup::{
+        prelude::*,
+        storage::path::{OwnedPath, RefPath},
+        testing::prelude::MockHost,
+    };
+    use tezos_smart_rollup_host::{path::Path, runtime::RuntimeError};
+    use tzwitter_types::{
+        hash::Blake2b, message::Payload, metrics::Metrics, nonce::Nonce, public_key::PublicKey,
+        public_key_hash::PublicKeyHash, signer::Signer,
+    };
+
+    use super::{process, verify_public_key, Account, Application, Error, Receipt};
+
+    const COUNTER: RefPath = RefPath::assert_from(b"/counter/value");
+
+    /// Errors of the counter application
+    #[derive(Debug)]
+    enum TestError {
+        Pipeline(Error),
+        Runtime,
+    }
+
+    impl From<Error> for TestError {
+        fn from(error: Error) -> Self {
+            TestError::Pipeline(error)
+        }
+    }
+
+    impl From<RuntimeError> for TestError {
+        fn from(_: RuntimeError) -> Self {
+            TestError::Runtime
+        }
+    }
+
+    fn path(path: String) -> OwnedPath {
+        OwnedPath::try_from(path).unwrap()
+    }
+
+    fn read_u64<R: Runtime>(host: &mut R, path: &impl Path) -> Result<Option<u64>, TestError> {
+        match host.store_has(path)? {
+            None => Ok(None),
+            Some(_) => {
+                let bytes = host.store_read(path, 0, 8)?;
+                let bytes: [u8; 8] = bytes.try_into().unwrap();
+                Ok(Some(u64::from_be_bytes(bytes)))
+            }
+        }
+    }
+
+    fn store_u64<R: Runtime>(host: &mut R, path: &impl Path, value: u64) -> Result<(), TestError> {
+        host.store_write(path, &value.to_be_bytes(), 0)?;
+        Ok(())
+    }
+
+    /// Content of a counter application
+    #[derive(Deserialize, Serialize)]
+    enum Counter {
+        Increment,
+    }
+
+    impl Payload for Counter {
+        fn hash(&self, nonce: &Nonce) -> Blake2b {
+            let string = format!("{}increment", nonce.to_string());
+            Blake2b::from(string.as_bytes())
+        }
+
+        fn kind(&self) -> &'static str {
+            "increment"
+        }
+    }
+
+    /// The counter application stores everything under /counter
+    impl Application for Counter {
+        type Content = Counter;
+        type Error = TestError;
+
+        fn read_account<R: Runtime>(
+            host: &mut R,
+            public_key_hash: PublicKeyHash,
+        ) -> Result<Account, TestError> {
+            let nonce_path = path(format!(
+                "/counter/accounts/{}/nonce",
+                public_key_hash.to_string()
+            ));
+            let nonce = read_u64(host, &nonce_path)?.unwrap_or_default();
+            Ok(Account {
+                public_key_hash,
+                nonce: Nonce(nonce),
+            })
+        }
+
+        fn store_account<R: Runtime>(host: &mut R, account: &Account) -> Result<(), TestError> {
+            let nonce_path = format!(
+                "/counter/accounts/{}/nonce",
+                account.public_key_hash.to_string()
+            );
+            store_u64(host, &path(nonce_path), account.nonce.0)
+        }
+
+        fn read_public_key<R: Runtime>(
+            host: &mut R,
+            public_key_hash: &PublicKeyHash,
+        ) -> Result<Option<PublicKey>, TestError> {
+            let public_key_path = path(format!(
+                "/counter/accounts/{}/public_key",
+                public_key_hash.to_string()
+            ));
+            if host.store_has(&public_key_path)?.is_none() {
+                return Ok(None);
+            }
+            let bytes = host.store_read(&public_key_path, 0, 4096)?;
+            let public_key = String::from_utf8(bytes).unwrap();
+            Ok(Some(PublicKey::from_b58(&public_key).unwrap()))
+        }
+
+        fn store_public_key<R: Runtime>(
+            host: &mut R,
+            public_key_hash: &PublicKeyHash,
+            public_key: &PublicKey,
+        ) -> Result<(), TestError> {
+            let public_key_path = path(format!(
+                "/counter/accounts/{}/public_key",
+                public_key_hash.to_string()
+            ));
+            host.store_write(&public_key_path, public_key.to_b58().as_bytes(), 0)?;
+            Ok(())
+        }
+
+        fn store_receipt<R: Runtime>(host: &mut R, receipt: &Receipt) -> Result<(), TestError> {
+            let hash = receipt.hash().to_string();
+            let bytes_written_path = path(format!("/counter/receipts/{}/bytes_written", hash));
+            store_u64(host, &bytes_written_path, receipt.metrics().bytes_written)
+        }
+
+        fn add_level_metrics<R: Runtime>(
+            host: &mut R,
+            level: &u32,
+            kind: &str,
+            metrics: &Metrics,
+        ) -> Result<(), TestError> {
+            let messages_path = path(format!("/counter/metrics/{}/{}/messages", level, kind));
+            let bytes_written_path =
+                path(format!("/counter/metrics/{}/{}/bytes_written", level, kind));
+            let messages = read_u64(host, &messages_path)?.unwrap_or_default();
+            let bytes_written = read_u64(host, &bytes_written_path)?.unwrap_or_default();
+            store_u64(host, &messages_path, messages + 1)?;
+            store_u64(
+                host,
+                &bytes_written_path,
+                bytes_written + metrics.bytes_written,
+            )
+        }
+
+        fn apply<R: Runtime>(
+            host: &mut R,
+            _: u32,
+            _: &Blake2b,
+            _: &Account,
+            content: Counter,
+        ) -> Result<(), TestError> {
+            match content {
+                Counter::Increment => {
+                    let counter = read_u64(host, &COUNTER)?.unwrap_or_default();
+                    store_u64(host, &COUNTER, counter + 1)
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_counter_application() {
+        let mut host = MockHost::default();
+        let signer = Signer::from_seed(1);
+
+        let message = signer.sign(Nonce(1), Counter::Increment);
+        assert!(process::<Counter, _>(&mut host, message, 0).is_ok());
+
+        // The same nonce can't be replayed
+        let message = signer.sign(Nonce(1), Counter::Increment);
+        let result = process::<Counter, _>(&mut host, message, 0);
+        assert!(matches!(
+            result,
+            Err(TestError::Pipeline(Error::InvalidNonce))
+        ));
+
+        let message = signer.sign(Nonce(2), Counter::Increment);
+        assert!(process::<Counter, _>(&mut host, message, 0).is_ok());
+
+        assert_eq!(read_u64(&mut host, &COUNTER).unwrap(), Some(2));
+    }
+
+    #[test]
+    fn test_public_key_reveal() {
+        let public_key = Signer::from_seed(1).public_key();
+        let other = Signer::from_seed(2).public_key();
+
+        assert!(verify_public_key(&public_key, None).is_ok());
+        assert!(verify_public_key(&public_key, Some(&public_key)).is_ok());
+        let res = verify_public_key(&public_key, Some(&other));
+        assert!(matches!(res, Err(Error::PublicKeyMismatch)));
+
+        let mut host = MockHost::default();
+        let signer = Signer::from_seed(1);
+        let message = signer.sign(Nonce(1), Counter::Increment);
+        process::<Counter, _>(&mut host, message, 0).unwrap();
+        let revealed = Counter::read_public_key(&mut host, &signer.public_key_hash()).unwrap();
+        assert_eq!(revealed, Some(public_key));
+    }
+
+    #[test]
+    fn test_metrics() {
+        let mut host = MockHost::default();
+        let signer = Signer::from_seed(1);
+
+        let message = signer.sign(Nonce(1), Counter::Increment);
+        process::<Counter, _>(&mut host, message, 3).unwrap();
+        let message = signer.sign(Nonce(2), Counter::Increment);
+        let hash = message.hash().to_string();
+        process::<Counter, _>(&mut host, message, 3).unwrap();
+
+        // The nonce and the counter are written, the public key only by the first message
+        let receipt_path = path(format!("/counter/receipts/{}/bytes_written", hash));
+        assert_eq!(read_u64(&mut host, &receipt_path).unwrap(), Some(16));
+
+        let messages_path = RefPath::assert_from(b"/counter/metrics/3/increment/messages");
+        assert_eq!(read_u64(&mut host, &messages_path).unwrap(), Some(2));
+        let bytes_written_path =
+            RefPath::assert_from(b"/counter/metrics/3/increment/bytes_written");
+        let public_key_size = signer.public_key().to_b58().len() as u64;
+        assert_eq!(
+            read_u64(&mut host, &bytes_written_path).unwrap(),
+            Some(32 + public_key_size)
+        );
+    }
+}
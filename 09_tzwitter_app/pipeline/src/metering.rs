@@ -0,0 +1,191 @@
+//! Metering of the storage accesses
+//!
+//! The MeteredHost wraps the runtime of the kernel and counts the bytes read from
+//! and written to the durable storage, the calls are forwarded to the wrapped runtime.
+
+use std::cell::Cell;
+
+use tezos_smart_rollup_core::PREIMAGE_HASH_SIZE;
+use tezos_smart_rollup_host::{
+    input::Message,
+    metadata::RollupMetadata,
+    path::Path,
+    runtime::{Runtime, RuntimeError, ValueType},
+};
+
+use tzwitter_types::metrics::Metrics;
+
+/// Runtime counting the bytes exchanged with the durable storage
+///
+/// The moves and the copies are not counted, their bytes don't go through the kernel
+pub struct MeteredHost<'a, R: Runtime> {
+    host: &'a mut R,
+    // The reads only borrow the runtime
+    bytes_read: Cell<u64>,
+    bytes_written: u64,
+}
+
+impl<'a, R: Runtime> MeteredHost<'a, R> {
+    pub fn new(host: &'a mut R) -> Self {
+        MeteredHost {
+            host,
+            bytes_read: Cell::new(0),
+            bytes_written: 0,
+        }
+    }
+
+    /// Returns the metrics since the creation of the metered host
+    pub fn metrics(&self) -> Metrics {
+        Metrics {
+            bytes_read: self.bytes_read.get(),
+            bytes_written: self.bytes_written,
+        }
+    }
+
+    fn count_read(&self, size: usize) {
+        let bytes_read = self.bytes_read.get().saturating_add(size as u64);
+        self.bytes_read.set(bytes_read);
+    }
+}
+
+impl<'a, R: Runtime> Runtime for MeteredHost<'a, R> {
+    fn write_output(&mut self, from: &[u8]) -> Result<(), RuntimeError> {
+        self.host.write_output(from)
+    }
+
+    fn write_debug(&self, msg: &str) {
+        self.host.write_debug(msg)
+    }
+
+    fn read_input(&mut self) -> Result<Option<Message>, RuntimeError> {
+        self.host.read_input()
+    }
+
+    fn store_has<T: Path>(&self, path: &T) -> Result<Option<ValueType>, RuntimeError> {
+        self.host.store_has(path)
+    }
+
+    fn store_read<T: Path>(
+        &self,
+        path: &T,
+        from_offset: usize,
+        max_bytes: usize,
+    ) -> Result<Vec<u8>, RuntimeError> {
+        let bytes = self.host.store_read(path, from_offset, max_bytes)?;
+        self.count_read(bytes.len());
+        Ok(bytes)
+    }
+
+    fn store_read_slice<T: Path>(
+        &self,
+        path: &T,
+        from_offset: usize,
+        buffer: &mut [u8],
+    ) -> Result<usize, RuntimeError> {
+        let size = self.host.store_read_slice(path, from_offset, buffer)?;
+        self.count_read(size);
+        Ok(size)
+    }
+
+    fn store_write<T: Path>(
+        &mut self,
+        path: &T,
+        src: &[u8],
+        at_offset: usize,
+    ) -> Result<(), RuntimeError> {
+        self.host.store_write(path, src, at_offset)?;
+        self.bytes_written = self.bytes_written.saturating_add(src.len() as u64);
+        Ok(())
+    }
+
+    fn store_delete<T: Path>(&mut self, path: &T) -> Result<(), RuntimeError> {
+        self.host.store_delete(path)
+    }
+
+    fn store_count_subkeys<T: Path>(&self, prefix: &T) -> Result<u64, RuntimeError> {
+        self.host.store_count_subkeys(prefix)
+    }
+
+    fn store_move(
+        &mut self,
+        from_path: &impl Path,
+        to_path: &impl Path,
+    ) -> Result<(), RuntimeError> {
+        self.host.store_move(from_path, to_path)
+    }
+
+    fn store_copy(
+        &mut self,
+        from_path: &impl Path,
+        to_path: &impl Path,
+    ) -> Result<(), RuntimeError> {
+        self.host.store_copy(from_path, to_path)
+    }
+
+    fn reveal_preimage(
+        &self,
+        hash: &[u8; PREIMAGE_HASH_SIZE],
+        destination: &mut [u8],
+    ) -> Result<usize, RuntimeError> {
+        self.host.reveal_preimage(hash, destination)
+    }
+
+    fn store_value_size(&self, path: &impl Path) -> Result<usize, RuntimeError> {
+        self.host.store_value_size(path)
+    }
+
+    fn mark_for_reboot(&mut self) -> Result<(), RuntimeError> {
+        self.host.mark_for_reboot()
+    }
+
+    fn reveal_metadata(&self) -> Result<RollupMetadata, RuntimeError> {
+        self.host.reveal_metadata()
+    }
+
+    fn last_run_aborted(&self) -> Result<bool, RuntimeError> {
+        self.host.last_run_aborted()
+    }
+
+    fn upgrade_failed(&self) -> Result<bool, RuntimeError> {
+        self.host.upgrade_failed()
+    }
+
+    fn restart_forced(&self) -> Result<bool, RuntimeError> {
+        self.host.restart_forced()
+    }
+
+    fn reboot_left(&self) -> Result<u32, RuntimeError> {
+        self.host.reboot_left()
+    }
+
+    fn runtime_version(&self) -> Result<String, RuntimeError> {
+        self.host.runtime_version()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tezos_smart_rollup::{prelude::*, storage::path::RefPath, testing::prelude::MockHost};
+
+    use super::MeteredHost;
+    use tzwitter_types::metrics::Metrics;
+
+    #[test]
+    fn test_metered_host() {
+        let mut host = MockHost::default();
+        let path = RefPath::assert_from(b"/value");
+
+        let mut metered = MeteredHost::new(&mut host);
+        metered.store_write(&path, &[0x01, 0x02, 0x03], 0).unwrap();
+        let _ = metered.store_read(&path, 1, 10).unwrap();
+        metered
+            .store_copy(&path, &RefPath::assert_from(b"/copy"))
+            .unwrap();
+
+        let expected = Metrics {
+            bytes_read: 2,
+            bytes_written: 3,
+        };
+        assert_eq!(metered.metrics(), expected);
+    }
+}
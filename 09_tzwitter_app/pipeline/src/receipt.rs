@@ -0,0 +1,48 @@
+use tzwitter_types::{hash::Blake2b, metrics::Metrics};
+
+/// definition of a receipt
+///
+/// For now we don't need to know the cause of the error
+/// Because the receipt is only used in the front-end application to give user feedbacks
+pub struct Receipt {
+    hash: Blake2b,
+    level: u32,
+    success: bool,
+    metrics: Metrics,
+}
+
+impl Receipt {
+    pub fn new<E>(hash: Blake2b, level: u32, result: &Result<(), E>) -> Receipt {
+        Receipt {
+            hash,
+            level,
+            success: result.is_ok(),
+            metrics: Metrics::default(),
+        }
+    }
+
+    /// Returns the same receipt with the storage costs of the processing
+    pub fn with_metrics(self, metrics: Metrics) -> Receipt {
+        Receipt { metrics, ..self }
+    }
+
+    /// Returns the hash of the receipt
+    pub fn hash(&self) -> &Blake2b {
+        &self.hash
+    }
+
+    /// Returns the level of the processed message
+    pub fn level(&self) -> u32 {
+        self.level
+    }
+
+    /// Returns a boolean that indicates if the receipt is a success or not
+    pub fn success(&self) -> bool {
+        self.success
+    }
+
+    /// Returns the storage costs of the processing of the message
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+}
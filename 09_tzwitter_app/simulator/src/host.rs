@@ -0,0 +1,256 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use tezos_smart_rollup::testing::prelude::MockHost;
+use tezos_smart_rollup_host::{
+    input::Message,
+    metadata::RollupMetadata,
+    path::Path,
+    runtime::{Runtime, RuntimeError, ValueType},
+    Error,
+};
+
+/// Durable state of the simulation, the keys are the paths of the values
+pub type State = BTreeMap<String, Vec<u8>>;
+
+/// Host of the simulation
+///
+/// The storage is delegated to a mock host, the inbox is filled level by level by the simulation.
+/// The debug messages are printed, the outbox messages are recorded,
+/// and a copy of the durable state is kept to compute the diff of each level.
+pub struct SimulationHost {
+    host: MockHost,
+    inputs: VecDeque<Message>,
+    state: State,
+    pub outbox: Vec<Vec<u8>>,
+}
+
+impl Default for SimulationHost {
+    fn default() -> Self {
+        SimulationHost {
+            host: MockHost::default(),
+            inputs: VecDeque::default(),
+            state: State::default(),
+            outbox: Vec::default(),
+        }
+    }
+}
+
+/// Converts a path to a string
+fn path_to_str(path: &impl Path) -> Result<&str, RuntimeError> {
+    std::str::from_utf8(path.as_bytes()).map_err(|_| RuntimeError::HostErr(Error::StoreInvalidKey))
+}
+
+/// Checks if a key is the path or one of its subkeys
+fn is_in_subtree(key: &str, path: &str) -> bool {
+    key == path || key.starts_with(&format!("{}/", path))
+}
+
+impl SimulationHost {
+    /// Adds the inputs of a level
+    ///
+    /// The external messages are surrounded by the start of level, the info per level and the end of level
+    pub fn add_level(&mut self, level: u32, externals: Vec<Vec<u8>>) {
+        let mut payloads = vec![vec![0x00, 0x01]];
+
+        // Info per level: timestamp and hash of the predecessor
+        let mut info_per_level = vec![0x00, 0x03];
+        info_per_level.extend_from_slice(&0_i64.to_be_bytes());
+        info_per_level.extend_from_slice(&[0x00; 32]);
+        payloads.push(info_per_level);
+
+        for external in externals {
+            let mut payload = vec![0x01];
+            payload.extend(external);
+            payloads.push(payload);
+        }
+        payloads.push(vec![0x00, 0x02]);
+
+        for (index, payload) in payloads.into_iter().enumerate() {
+            let message = Message::new(level, index as u32, payload);
+            self.inputs.push_back(message);
+        }
+    }
+
+    /// Returns a copy of the durable state
+    pub fn state(&self) -> State {
+        self.state.clone()
+    }
+
+    /// Clears the recorded outbox messages
+    pub fn clear_outbox(&mut self) {
+        self.outbox.clear();
+    }
+
+    /// Refreshes the copy of the value at the given path
+    fn refresh(&mut self, path: &str) -> Result<(), RuntimeError> {
+        let owned_path = tezos_smart_rollup_host::path::OwnedPath::try_from(path.to_string())
+            .map_err(|_| RuntimeError::HostErr(Error::StoreInvalidKey))?;
+        let size = self.host.store_value_size(&owned_path)?;
+        let value = self.host.store_read(&owned_path, 0, size)?;
+        self.state.insert(path.to_string(), value);
+        Ok(())
+    }
+}
+
+impl Runtime for SimulationHost {
+    fn write_output(&mut self, from: &[u8]) -> Result<(), RuntimeError> {
+        self.outbox.push(from.to_vec());
+        Ok(())
+    }
+
+    fn write_debug(&self, msg: &str) {
+        print!("{}", msg);
+    }
+
+    fn read_input(&mut self) -> Result<Option<Message>, RuntimeError> {
+        Ok(self.inputs.pop_front())
+    }
+
+    fn store_has<T: Path>(&self, path: &T) -> Result<Option<ValueType>, RuntimeError> {
+        self.host.store_has(path)
+    }
+
+    fn store_read<T: Path>(
+        &self,
+        path: &T,
+        from_offset: usize,
+        max_bytes: usize,
+    ) -> Result<Vec<u8>, RuntimeError> {
+        self.host.store_read(path, from_offset, max_bytes)
+    }
+
+    fn store_read_slice<T: Path>(
+        &self,
+        path: &T,
+        from_offset: usize,
+        buffer: &mut [u8],
+    ) -> Result<usize, RuntimeError> {
+        self.host.store_read_slice(path, from_offset, buffer)
+    }
+
+    fn store_write<T: Path>(
+        &mut self,
+        path: &T,
+        src: &[u8],
+        at_offset: usize,
+    ) -> Result<(), RuntimeError> {
+        self.host.store_write(path, src, at_offset)?;
+        self.refresh(path_to_str(path)?)
+    }
+
+    fn store_delete<T: Path>(&mut self, path: &T) -> Result<(), RuntimeError> {
+        self.host.store_delete(path)?;
+        let path = path_to_str(path)?;
+        self.state.retain(|key, _| !is_in_subtree(key, path));
+        Ok(())
+    }
+
+    fn store_count_subkeys<T: Path>(&self, prefix: &T) -> Result<u64, RuntimeError> {
+        self.host.store_count_subkeys(prefix)
+    }
+
+    fn store_move(
+        &mut self,
+        from_path: &impl Path,
+        to_path: &impl Path,
+    ) -> Result<(), RuntimeError> {
+        self.store_copy(from_path, to_path)?;
+        self.store_delete(from_path)
+    }
+
+    fn store_copy(
+        &mut self,
+        from_path: &impl Path,
+        to_path: &impl Path,
+    ) -> Result<(), RuntimeError> {
+        self.host.store_copy(from_path, to_path)?;
+        let from = path_to_str(from_path)?;
+        let to = path_to_str(to_path)?;
+
+        self.state.retain(|key, _| !is_in_subtree(key, to));
+        let copied: Vec<(String, Vec<u8>)> = self
+            .state
+            .iter()
+            .filter(|(key, _)| is_in_subtree(key, from))
+            .map(|(key, value)| (format!("{}{}", to, &key[from.len()..]), value.clone()))
+            .collect();
+        self.state.extend(copied);
+        Ok(())
+    }
+
+    fn reveal_preimage(
+        &self,
+        hash: &[u8; tezos_smart_rollup_core::PREIMAGE_HASH_SIZE],
+        destination: &mut [u8],
+    ) -> Result<usize, RuntimeError> {
+        self.host.reveal_preimage(hash, destination)
+    }
+
+    fn store_value_size(&self, path: &impl Path) -> Result<usize, RuntimeError> {
+        self.host.store_value_size(path)
+    }
+
+    fn mark_for_reboot(&mut self) -> Result<(), RuntimeError> {
+        self.host.mark_for_reboot()
+    }
+
+    fn reveal_metadata(&self) -> Result<RollupMetadata, RuntimeError> {
+        self.host.reveal_metadata()
+    }
+
+    fn last_run_aborted(&self) -> Result<bool, RuntimeError> {
+        self.host.last_run_aborted()
+    }
+
+    fn upgrade_failed(&self) -> Result<bool, RuntimeError> {
+        self.host.upgrade_failed()
+    }
+
+    fn restart_forced(&self) -> Result<bool, RuntimeError> {
+        self.host.restart_forced()
+    }
+
+    fn reboot_left(&self) -> Result<u32, RuntimeError> {
+        self.host.reboot_left()
+    }
+
+    fn runtime_version(&self) -> Result<String, RuntimeError> {
+        self.host.runtime_version()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tezos_smart_rollup_host::{path::RefPath, runtime::Runtime};
+
+    use super::SimulationHost;
+
+    #[test]
+    fn test_state_follows_the_storage() {
+        let mut host = SimulationHost::default();
+        let a = RefPath::assert_from(b"/a/b");
+        let c = RefPath::assert_from(b"/c");
+
+        host.store_write(&a, &[0x01, 0x02], 0).unwrap();
+        host.store_write(&a, &[0x03], 1).unwrap();
+        assert_eq!(host.state().get("/a/b"), Some(&vec![0x01, 0x03]));
+
+        host.store_move(&RefPath::assert_from(b"/a"), &c).unwrap();
+        assert_eq!(host.state().get("/c/b"), Some(&vec![0x01, 0x03]));
+        assert!(host.state().get("/a/b").is_none());
+    }
+
+    #[test]
+    fn test_level_inputs() {
+        let mut host = SimulationHost::default();
+        host.add_level(3, vec![vec![0x74]]);
+
+        let payloads: Vec<Vec<u8>> = std::iter::from_fn(|| host.read_input().unwrap())
+            .map(|message| message.as_ref().to_vec())
+            .collect();
+        assert_eq!(payloads.len(), 4);
+        assert_eq!(payloads[0], vec![0x00, 0x01]);
+        assert_eq!(payloads[2], vec![0x01, 0x74]);
+        assert_eq!(payloads[3], vec![0x00, 0x02]);
+    }
+}
@@ -0,0 +1,42 @@
+use serde::Deserialize;
+
+/// An input of the inputs.json file of the octez-smart-rollup-wasm-debugger
+///
+/// Only the external messages are supported
+#[derive(Deserialize)]
+pub struct Input {
+    /// Hex of the payload of the external message, without the external tag
+    pub external: String,
+}
+
+/// Inputs of the simulation, one list of inputs per level
+pub type Inputs = Vec<Vec<Input>>;
+
+/// Reads the inputs from a json file
+pub fn read_inputs(path: &str) -> Result<Inputs, String> {
+    let content = std::fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+    serde_json::from_str(&content).map_err(|err| format!("{}: {}", path, err))
+}
+
+/// Decodes the external messages of a level
+pub fn decode_level(inputs: &[Input]) -> Result<Vec<Vec<u8>>, String> {
+    inputs
+        .iter()
+        .map(|input| hex::decode(&input.external).map_err(|err| err.to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inputs_format() {
+        let json = r#"[[{"external": "7401"}, {"external": "7402"}], []]"#;
+        let inputs: Inputs = serde_json::from_str(json).unwrap();
+        assert_eq!(inputs.len(), 2);
+
+        let level = decode_level(&inputs[0]).unwrap();
+        assert_eq!(level, vec![vec![0x74, 0x01], vec![0x74, 0x02]]);
+    }
+}
@@ -0,0 +1,120 @@
+//! Native simulation of the tzwitter kernel
+//!
+//! It reads the inputs.json file of the octez-smart-rollup-wasm-debugger,
+//! and runs the kernel level by level without any octez tooling.
+
+mod host;
+mod inputs;
+
+use clap::Parser;
+use host::{SimulationHost, State};
+
+#[derive(Parser)]
+#[command(about = "Simulates the tzwitter kernel on the inputs of the wasm debugger")]
+struct Cli {
+    /// Path of the inputs file, one list of external messages per level
+    #[arg(default_value = "kernel/inputs.json")]
+    inputs: String,
+
+    /// Level of the first inputs
+    #[arg(long, default_value_t = 1)]
+    level: u32,
+}
+
+/// Formats a value of the durable state
+///
+/// The value is displayed in hex, and as a string when it is printable
+fn format_value(value: &[u8]) -> String {
+    let hex = hex::encode(value);
+    match std::str::from_utf8(value) {
+        Ok(str) if !str.is_empty() && str.chars().all(|c| c.is_ascii_graphic() || c == ' ') => {
+            format!("{} ({:?})", hex, str)
+        }
+        _ => hex,
+    }
+}
+
+/// Prints the receipts written during the level
+fn print_receipts(before: &State, after: &State) {
+    for (key, value) in after {
+        let hash = match key
+            .strip_prefix("/receipts/")
+            .and_then(|key| key.strip_suffix("/success"))
+        {
+            Some(hash) => hash,
+            None => continue,
+        };
+        if before.get(key) == Some(value) {
+            continue;
+        }
+        let status = match value.as_slice() {
+            [0x01] => "success",
+            _ => "failure",
+        };
+        println!("  receipt {}: {}", hash, status);
+    }
+}
+
+/// Prints the difference between two states
+fn print_diff(before: &State, after: &State) {
+    for (key, value) in after {
+        match before.get(key) {
+            None => println!("  + {} = {}", key, format_value(value)),
+            Some(previous) if previous != value => println!(
+                "  ~ {} = {} -> {}",
+                key,
+                format_value(previous),
+                format_value(value)
+            ),
+            Some(_) => {}
+        }
+    }
+    for key in before.keys() {
+        if !after.contains_key(key) {
+            println!("  - {}", key);
+        }
+    }
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let inputs = match inputs::read_inputs(&cli.inputs) {
+        Ok(inputs) => inputs,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let mut host = SimulationHost::default();
+    for (offset, level_inputs) in inputs.iter().enumerate() {
+        let level = cli.level + offset as u32;
+        let externals = match inputs::decode_level(level_inputs) {
+            Ok(externals) => externals,
+            Err(err) => {
+                eprintln!("level {}: {}", level, err);
+                std::process::exit(1);
+            }
+        };
+
+        println!("Level {} ({} external messages)", level, externals.len());
+        println!("Debug:");
+        host.add_level(level, externals);
+
+        let before = host.state();
+        tzwitter_kernel::entry(&mut host);
+        let after = host.state();
+
+        println!("Receipts:");
+        print_receipts(&before, &after);
+        println!("Outbox:");
+        for message in &host.outbox {
+            println!("  {}", hex::encode(message));
+        }
+        println!("State diff:");
+        print_diff(&before, &after);
+        println!();
+
+        host.clear_outbox();
+    }
+}
@@ -0,0 +1,39 @@
+/// Represents the errors of the types
+///
+/// The kernel and the client convert them to their own errors
+#[derive(Debug)]
+pub enum Error {
+    FromUtf8(std::string::FromUtf8Error),
+    Ed25519Compact(ed25519_compact::Error),
+    InvalidSignature,
+    InvalidSecretKey,
+    StateDeserializarion,
+}
+
+impl ToString for Error {
+    fn to_string(&self) -> String {
+        let err = match self {
+            Error::FromUtf8(_) => "Cannot convert bytes to string",
+            Error::Ed25519Compact(_) => "Cannot deserialize Ed25519",
+            Error::InvalidSignature => "Invalid signature",
+            Error::InvalidSecretKey => "Cannot decode the secret key",
+            Error::StateDeserializarion => "State deserialization",
+        };
+        err.to_string()
+    }
+}
+
+macro_rules! register_error {
+    ($name:ident, $error:ty) => {
+        impl From<$error> for Error {
+            fn from(data: $error) -> Self {
+                Error::$name(data)
+            }
+        }
+    };
+}
+
+register_error!(FromUtf8, std::string::FromUtf8Error);
+register_error!(Ed25519Compact, ed25519_compact::Error);
+
+pub type Result<A> = std::result::Result<A, Error>;
@@ -0,0 +1,19 @@
+//! Types of tzwitter
+//!
+//! The messages of the kernel, their signature, and the read model of the durable state.
+//! They are shared by the kernel and its clients, without the runtime of the kernel.
+
+pub mod error;
+pub mod hash;
+pub mod listing;
+pub mod message;
+pub mod metrics;
+pub mod nonce;
+pub mod public_key;
+pub mod public_key_hash;
+pub mod read_model;
+pub mod signature;
+pub mod signer;
+
+/// Prefix of the external messages of tzwitter in the rollup inbox
+pub const MAGIC_BYTE: u8 = 0x74;
@@ -0,0 +1,8 @@
+use crate::public_key_hash::PublicKeyHash;
+
+/// A tweet put on sale by its owner
+pub struct Listing {
+    pub seller: PublicKeyHash,
+    /// Price in mutez
+    pub price: u64,
+}
@@ -0,0 +1,393 @@
+use crate::hash::Blake2b;
+use crate::nonce::Nonce;
+use crate::public_key::PublicKey;
+use crate::public_key_hash::PublicKeyHash;
+use crate::signature::Signature;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize)]
+pub struct PostTweet {
+    pub author: PublicKeyHash, // define a new type for public key
+    pub content: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct Transfer {
+    pub tweet_id: u64,
+    pub destination: PublicKeyHash,
+}
+
+/// Puts a tweet on sale, the price is in mutez
+#[derive(Deserialize, Serialize)]
+pub struct List {
+    pub tweet_id: u64,
+    pub price: u64,
+}
+
+/// Buys a listed tweet, if its price is at most the given one, in mutez
+#[derive(Deserialize, Serialize)]
+pub struct Buy {
+    pub tweet_id: u64,
+    pub max_price: u64,
+}
+
+/// Offers to buy a tweet, the price is in mutez
+#[derive(Deserialize, Serialize)]
+pub struct MakeOffer {
+    pub tweet_id: u64,
+    pub price: u64,
+}
+
+/// Sells a tweet to the buyer of an offer
+#[derive(Deserialize, Serialize)]
+pub struct AcceptOffer {
+    pub tweet_id: u64,
+    pub buyer: PublicKeyHash,
+}
+
+/// Reports an abusive tweet
+#[derive(Deserialize, Serialize)]
+pub struct ReportTweet {
+    pub tweet_id: u64,
+    pub reason: String,
+}
+
+/// Sends an encrypted message to an account
+///
+/// The ciphertext and the ephemeral public key are encoded by the client, the kernel only limits their size
+#[derive(Deserialize, Serialize)]
+pub struct SendDirectMessage {
+    pub to: PublicKeyHash,
+    pub ciphertext: String,
+    pub ephemeral_pk: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub enum Content {
+    PostTweet(PostTweet),
+    LikeTweet(u64),
+    Transfer(Transfer),
+    Collect(u64),
+    List(List),
+    Unlist(u64),
+    Buy(Buy),
+    MakeOffer(MakeOffer),
+    AcceptOffer(AcceptOffer),
+    ReportTweet(ReportTweet),
+    /// Hides a tweet, only the admin can send it
+    Hide(u64),
+    SendDirectMessage(SendDirectMessage),
+    /// Deletes a direct message from the inbox of the sender of the content
+    DeleteDirectMessage(u64),
+    /// Rejects the direct messages of an account
+    Block(PublicKeyHash),
+    Unblock(PublicKeyHash),
+    /// Withdraws mutez from the balance of the sender of the content to the layer 1
+    WithdrawBalance(u64),
+}
+
+/// Content of the messages of an application
+///
+/// The hash of the content and the nonce is what the client should sign,
+/// two different contents should not have the same hash with the same nonce
+pub trait Payload {
+    fn hash(&self, nonce: &Nonce) -> Blake2b;
+
+    /// Name of the kind of the content, the metrics are aggregated by kind
+    fn kind(&self) -> &'static str;
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct Inner<C = Content> {
+    nonce: Nonce,
+    pub content: C,
+}
+
+impl<C> Inner<C> {
+    /// Creates the inner of a message
+    pub fn new(nonce: Nonce, content: C) -> Inner<C> {
+        Inner { nonce, content }
+    }
+
+    /// Returns the nonce of the inner
+    pub fn nonce(&self) -> &Nonce {
+        &self.nonce
+    }
+}
+
+impl<C: Payload> Inner<C> {
+    /// Hash of the message
+    /// This hash is what the client should signed
+    pub fn hash(&self) -> Blake2b {
+        self.content.hash(&self.nonce)
+    }
+}
+
+/// A message signed by an account
+///
+/// The content is the one of the application, by default the tzwitter one
+#[derive(Deserialize, Serialize)]
+pub struct Message<C = Content> {
+    pkey: PublicKey,
+    signature: Signature,
+    pub inner: Inner<C>,
+}
+
+impl<C> Message<C> {
+    /// Creates a message from an inner signed by the public key
+    pub fn new(pkey: PublicKey, signature: Signature, inner: Inner<C>) -> Message<C> {
+        Message {
+            pkey,
+            signature,
+            inner,
+        }
+    }
+
+    /// Returns the public key of the message
+    pub fn public_key(&self) -> &PublicKey {
+        &self.pkey
+    }
+
+    /// Returns the signature of the message
+    pub fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    /// Returns the inner of the message
+    pub fn inner(&self) -> &Inner<C> {
+        &self.inner
+    }
+}
+
+impl<C: Payload> Message<C> {
+    /// Returns the hash of the message
+    pub fn hash(&self) -> Blake2b {
+        self.inner.hash()
+    }
+}
+
+/// Hashes the nonce, the name of a content and its fields
+///
+/// Each field is prefixed by its size, so the fields can't run into each other
+fn hash_fields(nonce: &Nonce, name: &str, fields: &[&[u8]]) -> Blake2b {
+    let mut preimage = format!("{}{}", nonce.to_string(), name).into_bytes();
+    for field in fields {
+        preimage.extend_from_slice(&(field.len() as u32).to_be_bytes());
+        preimage.extend_from_slice(field);
+    }
+    Blake2b::from(&preimage)
+}
+
+impl Payload for Content {
+    /// The marketplace contents are prefixed by their name,
+    /// so their signature can't be used for another content with the same fields
+    fn hash(&self, nonce: &Nonce) -> Blake2b {
+        // The nonce, and content should be hashed
+        match self {
+            Content::PostTweet(PostTweet { author, content }) => {
+                let string = format!("{}{}{}", nonce.to_string(), author.to_string(), content);
+                Blake2b::from(string.as_bytes())
+            }
+            Content::LikeTweet(tweet_id) => {
+                let string = format!("{}{}", nonce.to_string(), tweet_id);
+                Blake2b::from(string.as_bytes())
+            }
+            Content::Transfer(transfer) => {
+                let string = format!(
+                    "{}{}{}",
+                    nonce.to_string(),
+                    transfer.destination.to_string(),
+                    transfer.tweet_id
+                );
+                Blake2b::from(string.as_bytes())
+            }
+            Content::Collect(tweet_id) => {
+                let string = format!("{}{}", nonce.to_string(), tweet_id);
+                Blake2b::from(string.as_bytes())
+            }
+            Content::List(List { tweet_id, price }) => hash_fields(
+                nonce,
+                "list",
+                &[&tweet_id.to_be_bytes(), &price.to_be_bytes()],
+            ),
+            Content::Unlist(tweet_id) => {
+                let string = format!("{}unlist{}", nonce.to_string(), tweet_id);
+                Blake2b::from(string.as_bytes())
+            }
+            Content::Buy(Buy {
+                tweet_id,
+                max_price,
+            }) => hash_fields(
+                nonce,
+                "buy",
+                &[&tweet_id.to_be_bytes(), &max_price.to_be_bytes()],
+            ),
+            Content::MakeOffer(MakeOffer { tweet_id, price }) => hash_fields(
+                nonce,
+                "make_offer",
+                &[&tweet_id.to_be_bytes(), &price.to_be_bytes()],
+            ),
+            Content::AcceptOffer(AcceptOffer { tweet_id, buyer }) => {
+                let string = format!(
+                    "{}accept_offer{}{}",
+                    nonce.to_string(),
+                    tweet_id,
+                    buyer.to_string()
+                );
+                Blake2b::from(string.as_bytes())
+            }
+            Content::ReportTweet(ReportTweet { tweet_id, reason }) => hash_fields(
+                nonce,
+                "report",
+                &[&tweet_id.to_be_bytes(), reason.as_bytes()],
+            ),
+            Content::Hide(tweet_id) => {
+                let string = format!("{}hide{}", nonce.to_string(), tweet_id);
+                Blake2b::from(string.as_bytes())
+            }
+            Content::SendDirectMessage(SendDirectMessage {
+                to,
+                ciphertext,
+                ephemeral_pk,
+            }) => hash_fields(
+                nonce,
+                "direct_message",
+                &[
+                    to.to_string().as_bytes(),
+                    ciphertext.as_bytes(),
+                    ephemeral_pk.as_bytes(),
+                ],
+            ),
+            Content::DeleteDirectMessage(index) => {
+                let string = format!("{}delete_direct_message{}", nonce.to_string(), index);
+                Blake2b::from(string.as_bytes())
+            }
+            Content::Block(account) => {
+                let string = format!("{}block{}", nonce.to_string(), account.to_string());
+                Blake2b::from(string.as_bytes())
+            }
+            Content::Unblock(account) => {
+                let string = format!("{}unblock{}", nonce.to_string(), account.to_string());
+                Blake2b::from(string.as_bytes())
+            }
+            Content::WithdrawBalance(amount) => {
+                hash_fields(nonce, "withdraw_balance", &[&amount.to_be_bytes()])
+            }
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Content::PostTweet(_) => "post_tweet",
+            Content::LikeTweet(_) => "like_tweet",
+            Content::Transfer(_) => "transfer",
+            Content::Collect(_) => "collect",
+            Content::List(_) => "list",
+            Content::Unlist(_) => "unlist",
+            Content::Buy(_) => "buy",
+            Content::MakeOffer(_) => "make_offer",
+            Content::AcceptOffer(_) => "accept_offer",
+            Content::ReportTweet(_) => "report_tweet",
+            Content::Hide(_) => "hide",
+            Content::SendDirectMessage(_) => "send_direct_message",
+            Content::DeleteDirectMessage(_) => "delete_direct_message",
+            Content::Block(_) => "block",
+            Content::Unblock(_) => "unblock",
+            Content::WithdrawBalance(_) => "withdraw_balance",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::ParseIntError;
+
+    use super::{Content, Inner, List, MakeOffer, PostTweet, ReportTweet, SendDirectMessage};
+    use crate::{
+        message::Message, nonce::Nonce, public_key::PublicKey, public_key_hash::PublicKeyHash,
+    };
+
+    #[test]
+    fn test_hash() {
+        let expected = "933dd79f9935573925f774ad0ac8789560e2489f083eb7dce7289485e3648a2d";
+        let author = PublicKey::from_b58("edpkuDMUm7Y53wp4gxeLBXuiAhXZrLn8XB1R83ksvvesH8Lp8bmCfK")
+            .unwrap()
+            .into();
+
+        let inner = Inner {
+            nonce: Nonce::default().next(),
+            content: Content::PostTweet(PostTweet {
+                author,
+                content: "Hello world".to_string(),
+            }),
+        };
+
+        let hash = inner.hash();
+        assert_eq!(expected, hash.to_string());
+    }
+
+    #[test]
+    fn test_fields_do_not_collide() {
+        let hash = |content: Content| {
+            Inner::new(Nonce::default().next(), content)
+                .hash()
+                .to_string()
+        };
+
+        let list = hash(Content::List(List {
+            tweet_id: 1,
+            price: 23,
+        }));
+        let other_list = hash(Content::List(List {
+            tweet_id: 12,
+            price: 3,
+        }));
+        assert_ne!(list, other_list);
+
+        let offer = hash(Content::MakeOffer(MakeOffer {
+            tweet_id: 1,
+            price: 23,
+        }));
+        let other_offer = hash(Content::MakeOffer(MakeOffer {
+            tweet_id: 12,
+            price: 3,
+        }));
+        assert_ne!(offer, other_offer);
+        assert_ne!(list, offer);
+
+        let report = hash(Content::ReportTweet(ReportTweet {
+            tweet_id: 1,
+            reason: "23 spam".to_string(),
+        }));
+        let other_report = hash(Content::ReportTweet(ReportTweet {
+            tweet_id: 12,
+            reason: "3 spam".to_string(),
+        }));
+        assert_ne!(report, other_report);
+        let direct_message = |ciphertext: &str, ephemeral_pk: &str| {
+            hash(Content::SendDirectMessage(SendDirectMessage {
+                to: PublicKeyHash::from_b58("tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv").unwrap(),
+                ciphertext: ciphertext.to_string(),
+                ephemeral_pk: ephemeral_pk.to_string(),
+            }))
+        };
+        assert_ne!(direct_message("abcd", "ef"), direct_message("ab", "cdef"));
+    }
+
+    fn decode_hex(s: &str) -> Result<Vec<u8>, ParseIntError> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16))
+            .collect()
+    }
+
+    #[test]
+    fn test_message_deserizaliation() {
+        let data = "7b22706b6579223a7b2245643235353139223a226564706b75444d556d375935337770346778654c425875694168585a724c6e385842315238336b737676657348384c7038626d43664b227d2c227369676e6174757265223a7b2245643235353139223a22656473696775316d5243745a71754c7673706378615958565a64734b4b5371486e5865766e726d68315436334471315272384d316769564c7661706944464b365451434579593678797464476e4b675a7956534844566e756237707579353462443179227d2c22696e6e6572223a7b226e6f6e6365223a312c22636f6e74656e74223a7b22506f73745477656574223a7b22617574686f72223a7b22547a31223a22747a315146443957714c575a6d6d4175716e6e545050556a666175697459455764736876227d2c22636f6e74656e74223a2248656c6c6f20776f726c64227d7d7d7d";
+
+        let data = decode_hex(data).unwrap();
+        let string = String::from_utf8(data).unwrap();
+        let data = serde_json_wasm::from_str::<Message>(&string);
+        assert!(data.is_ok());
+    }
+}
@@ -0,0 +1,19 @@
+/// Storage costs of the processing of a message
+///
+/// Only the bytes exchanged between the kernel and the durable storage are counted.
+/// The ticks are not measured, the host does not expose the tick counter to the kernel.
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Metrics {
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+}
+
+impl Metrics {
+    /// Returns the sum of two metrics
+    pub fn add(&self, other: &Metrics) -> Metrics {
+        Metrics {
+            bytes_read: self.bytes_read.saturating_add(other.bytes_read),
+            bytes_written: self.bytes_written.saturating_add(other.bytes_written),
+        }
+    }
+}
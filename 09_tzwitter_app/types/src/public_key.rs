@@ -1,31 +1,32 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use tezos_crypto_rs::hash::PublicKeyEd25519;
 
-#[derive(Deserialize)]
+use crate::error::Error;
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Eq, Debug)]
 pub enum PublicKey {
     Ed25519(PublicKeyEd25519),
 }
 
-#[cfg(test)]
-mod tests {
-    use tezos_crypto_rs::hash::PublicKeyEd25519;
-
-    use super::PublicKey;
-    impl PublicKey {
-        pub fn to_b58(&self) -> String {
-            match self {
-                PublicKey::Ed25519(pk) => pk.to_base58_check(),
-            }
+impl PublicKey {
+    pub fn to_b58(&self) -> String {
+        match self {
+            PublicKey::Ed25519(pk) => pk.to_base58_check(),
         }
+    }
 
-        pub fn from_b58(data: &str) -> Result<Self, &'static str> {
-            let ed25519 = PublicKeyEd25519::from_base58_check(data).ok();
-            match ed25519 {
-                Some(pkey) => Ok(PublicKey::Ed25519(pkey)),
-                None => Err("Cannot decode b58"),
-            }
+    pub fn from_b58(data: &str) -> Result<Self, Error> {
+        let ed25519 = PublicKeyEd25519::from_base58_check(data).ok();
+        match ed25519 {
+            Some(pkey) => Ok(PublicKey::Ed25519(pkey)),
+            None => Err(Error::StateDeserializarion),
         }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PublicKey;
 
     #[test]
     fn test_ed25519_pk_deserialization() {
@@ -1,11 +1,11 @@
 use serde::{Deserialize, Serialize};
 use tezos_crypto_rs::hash::ContractTz1Hash;
 
-use crate::core::public_key::PublicKey;
+use crate::public_key::PublicKey;
 
-use crate::core::{error::Error, hash::Blake2b20};
+use crate::{error::Error, hash::Blake2b20};
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Clone, PartialEq, Eq, Debug)]
 pub enum PublicKeyHash {
     Tz1(ContractTz1Hash),
 }
@@ -63,7 +63,7 @@ impl<'a> From<&'a PublicKey> for PublicKeyHash {
 
 #[cfg(test)]
 mod tests {
-    use crate::core::public_key::PublicKey;
+    use crate::public_key::PublicKey;
 
     use super::PublicKeyHash;
 
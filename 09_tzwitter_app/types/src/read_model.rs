@@ -0,0 +1,543 @@
+//! Read model of the durable state of tzwitter
+//!
+//! Turns the raw values written by the kernel into typed views.
+//! The values can come from a dump of a subtree of the durable state,
+//! or from any source implementing the State trait (a rollup node, the sequencer, ...).
+//!
+//! The encoding rules are the ones of storage.rs:
+//! - the u64 (likes, reports, nonces, balances, prices, metrics) and the u32 (levels) are encoded in big endian
+//! - the strings (content, authors, owners) are encoded in utf8, the public keys and their hashes in b58
+//! - the flags are 0x00, only their presence matters
+//! - the booleans (success of the receipts) are 0x00 or 0x01
+
+use std::collections::BTreeMap;
+
+use crate::{
+    error::*, listing::Listing, metrics::Metrics, public_key::PublicKey,
+    public_key_hash::PublicKeyHash,
+};
+
+/// Source of the durable state
+pub trait State {
+    /// Returns the value stored at the given path
+    fn value(&self, path: &str) -> Option<Vec<u8>>;
+
+    /// Returns the direct subkeys of the given path
+    fn subkeys(&self, path: &str) -> Vec<String>;
+}
+
+/// Dump of a subtree of the durable state
+///
+/// The keys are the absolute paths of the values, for instance /tweets/0/likes
+#[derive(Default)]
+pub struct Dump(BTreeMap<String, Vec<u8>>);
+
+impl Dump {
+    /// Adds a value to the dump
+    pub fn insert(&mut self, path: &str, value: Vec<u8>) {
+        self.0.insert(path.to_string(), value);
+    }
+}
+
+impl FromIterator<(String, Vec<u8>)> for Dump {
+    fn from_iter<T: IntoIterator<Item = (String, Vec<u8>)>>(iter: T) -> Self {
+        Dump(iter.into_iter().collect())
+    }
+}
+
+impl State for Dump {
+    fn value(&self, path: &str) -> Option<Vec<u8>> {
+        self.0.get(path).cloned()
+    }
+
+    fn subkeys(&self, path: &str) -> Vec<String> {
+        let prefix = format!("{}/", path.trim_end_matches('/'));
+        let mut subkeys: Vec<String> = self
+            .0
+            .range(prefix.clone()..)
+            .take_while(|(key, _)| key.starts_with(&prefix))
+            .filter_map(|(key, _)| key[prefix.len()..].split('/').next())
+            .map(str::to_string)
+            .collect();
+        subkeys.sort();
+        subkeys.dedup();
+        subkeys
+    }
+}
+
+/// Decodes an u64 encoded in big endian
+pub fn decode_u64(value: &[u8]) -> Result<u64> {
+    let bytes: [u8; 8] = value.try_into().map_err(|_| Error::StateDeserializarion)?;
+    Ok(u64::from_be_bytes(bytes))
+}
+
+/// Decodes an u32 encoded in big endian
+pub fn decode_u32(value: &[u8]) -> Result<u32> {
+    let bytes: [u8; 4] = value.try_into().map_err(|_| Error::StateDeserializarion)?;
+    Ok(u32::from_be_bytes(bytes))
+}
+
+/// Decodes an utf8 string
+pub fn decode_string(value: &[u8]) -> Result<String> {
+    String::from_utf8(value.to_vec()).map_err(Error::from)
+}
+
+/// Decodes a public key hash encoded in b58
+pub fn decode_public_key_hash(value: &[u8]) -> Result<PublicKeyHash> {
+    let string = decode_string(value)?;
+    PublicKeyHash::from_b58(&string)
+}
+
+/// Decodes a public key encoded in b58
+pub fn decode_public_key(value: &[u8]) -> Result<PublicKey> {
+    let string = decode_string(value)?;
+    PublicKey::from_b58(&string)
+}
+
+/// Decodes a boolean, 0x01 is true and 0x00 is false
+pub fn decode_bool(value: &[u8]) -> Result<bool> {
+    match value {
+        [0x00] => Ok(false),
+        [0x01] => Ok(true),
+        _ => Err(Error::StateDeserializarion),
+    }
+}
+
+/// Reads an optional value from the state and decodes it
+fn read<S: State, A>(
+    state: &S,
+    path: &str,
+    decode: impl Fn(&[u8]) -> Result<A>,
+) -> Result<Option<A>> {
+    state.value(path).map(|value| decode(&value)).transpose()
+}
+
+/// Typed view of a tweet
+pub struct TweetView {
+    pub id: u64,
+    pub author: PublicKeyHash,
+    pub content: String,
+    pub likes: u64,
+    /// The tweets created before the owner was recorded don't have one
+    pub owner: Option<PublicKeyHash>,
+    pub written_level: Option<u32>,
+    /// Level of the withdrawal of the tweet, if it has been collected
+    pub collected_level: Option<u32>,
+    pub listing: Option<Listing>,
+    pub report_count: u64,
+    /// The hidden tweets should be blurred by the front-ends
+    pub hidden: bool,
+}
+
+/// Typed view of an account
+pub struct AccountView {
+    pub public_key_hash: PublicKeyHash,
+    pub nonce: u64,
+    /// Balance in mutez
+    pub balance: u64,
+    /// Public key revealed by the first valid message of the account
+    pub public_key: Option<PublicKey>,
+}
+
+/// Typed view of a receipt
+pub struct ReceiptView {
+    pub hash: String,
+    pub success: bool,
+    pub level: Option<u32>,
+    /// Storage costs of the message, missing for the receipts written before the metering
+    pub metrics: Option<Metrics>,
+}
+
+/// Typed view of a direct message
+///
+/// The ciphertext and the ephemeral public key are decrypted by the recipient
+pub struct DirectMessageView {
+    pub index: u64,
+    pub from: PublicKeyHash,
+    pub ciphertext: String,
+    pub ephemeral_pk: String,
+    pub level: u32,
+}
+
+/// Aggregated metrics of a content kind at a given level
+pub struct MetricsView {
+    pub kind: String,
+    pub messages: u64,
+    pub metrics: Metrics,
+}
+
+/// Reads the tweet of the given id
+pub fn read_tweet<S: State>(state: &S, id: u64) -> Result<Option<TweetView>> {
+    let path = |field: &str| format!("/tweets/{}/{}", id, field);
+
+    let author = read(state, &path("author"), decode_public_key_hash)?;
+    let content = read(state, &path("content"), decode_string)?;
+    let likes = read(state, &path("likes"), decode_u64)?;
+    let (author, content, likes) = match (author, content, likes) {
+        (Some(author), Some(content), Some(likes)) => (author, content, likes),
+        _ => return Ok(None),
+    };
+
+    let price = read(state, &path("listing/price"), decode_u64)?;
+    let seller = read(state, &path("listing/seller"), decode_public_key_hash)?;
+    let listing = match (price, seller) {
+        (Some(price), Some(seller)) => Some(Listing { seller, price }),
+        _ => None,
+    };
+
+    Ok(Some(TweetView {
+        id,
+        author,
+        content,
+        likes,
+        owner: read(state, &path("owner"), decode_public_key_hash)?,
+        written_level: read(state, &path("written_level"), decode_u32)?,
+        collected_level: read(state, &path("collected_level"), decode_u32)?,
+        listing,
+        report_count: read(state, &path("report_count"), decode_u64)?.unwrap_or_default(),
+        hidden: state.value(&path("hidden")).is_some(),
+    }))
+}
+
+/// Reads the account of the given public key hash
+///
+/// An unknown account has a nonce and a balance of 0, and no public key
+pub fn read_account<S: State>(state: &S, public_key_hash: &PublicKeyHash) -> Result<AccountView> {
+    let path = |field: &str| format!("/accounts/{}/{}", public_key_hash.to_string(), field);
+    let nonce = read(state, &path("nonce"), decode_u64)?;
+    let balance = read(state, &path("balance"), decode_u64)?;
+    Ok(AccountView {
+        public_key_hash: public_key_hash.clone(),
+        nonce: nonce.unwrap_or_default(),
+        balance: balance.unwrap_or_default(),
+        public_key: read(state, &path("public_key"), decode_public_key)?,
+    })
+}
+
+/// Reads the receipt of the message of the given hash
+pub fn read_receipt<S: State>(state: &S, hash: &str) -> Result<Option<ReceiptView>> {
+    let success = read(state, &format!("/receipts/{}/success", hash), decode_bool)?;
+    let level = read(state, &format!("/receipts/{}/level", hash), decode_u32)?;
+    let bytes_read = read(state, &format!("/receipts/{}/bytes_read", hash), decode_u64)?;
+    let bytes_written = read(
+        state,
+        &format!("/receipts/{}/bytes_written", hash),
+        decode_u64,
+    )?;
+    let metrics = match (bytes_read, bytes_written) {
+        (Some(bytes_read), Some(bytes_written)) => Some(Metrics {
+            bytes_read,
+            bytes_written,
+        }),
+        _ => None,
+    };
+    Ok(success.map(|success| ReceiptView {
+        hash: hash.to_string(),
+        success,
+        level,
+        metrics,
+    }))
+}
+
+/// Reads the metrics of a given level, one entry per content kind
+pub fn read_level_metrics<S: State>(state: &S, level: u32) -> Result<Vec<MetricsView>> {
+    let mut views = Vec::default();
+    for kind in state.subkeys(&format!("/metrics/{}", level)) {
+        let path = |field: &str| format!("/metrics/{}/{}/{}", level, kind, field);
+        let messages = read(state, &path("messages"), decode_u64)?;
+        let bytes_read = read(state, &path("bytes_read"), decode_u64)?;
+        let bytes_written = read(state, &path("bytes_written"), decode_u64)?;
+        views.push(MetricsView {
+            kind,
+            messages: messages.unwrap_or_default(),
+            metrics: Metrics {
+                bytes_read: bytes_read.unwrap_or_default(),
+                bytes_written: bytes_written.unwrap_or_default(),
+            },
+        });
+    }
+    Ok(views)
+}
+
+/// A page of a list
+pub struct Page<A> {
+    pub items: Vec<A>,
+    /// Offset of the next page, if there is one
+    pub next: Option<usize>,
+}
+
+/// Paginates the ids stored as subkeys of the given path
+///
+/// The ids are sorted in ascending order, the subkeys that are not ids are ignored
+fn paginate_ids<S: State>(state: &S, path: &str, offset: usize, limit: usize) -> Page<u64> {
+    let mut ids: Vec<u64> = state
+        .subkeys(path)
+        .iter()
+        .filter_map(|subkey| subkey.parse().ok())
+        .collect();
+    ids.sort_unstable();
+
+    let items: Vec<u64> = ids.iter().skip(offset).take(limit).copied().collect();
+    let next = match offset + items.len() < ids.len() {
+        true => Some(offset + items.len()),
+        false => None,
+    };
+    Page { items, next }
+}
+
+/// Lists the ids of all the tweets
+pub fn tweet_ids<S: State>(state: &S, offset: usize, limit: usize) -> Page<u64> {
+    paginate_ids(state, "/tweets", offset, limit)
+}
+
+/// Lists the tweets
+pub fn tweets<S: State>(state: &S, offset: usize, limit: usize) -> Result<Page<TweetView>> {
+    let Page { items, next } = tweet_ids(state, offset, limit);
+    let mut tweets = Vec::with_capacity(items.len());
+    for id in items {
+        if let Some(tweet) = read_tweet(state, id)? {
+            tweets.push(tweet);
+        }
+    }
+    Ok(Page {
+        items: tweets,
+        next,
+    })
+}
+
+/// Lists the ids of the tweets written by an account
+pub fn tweets_by_author<S: State>(
+    state: &S,
+    author: &PublicKeyHash,
+    offset: usize,
+    limit: usize,
+) -> Page<u64> {
+    let path = format!("/accounts/{}/tweets/written", author.to_string());
+    paginate_ids(state, &path, offset, limit)
+}
+
+/// Lists the ids of the tweets owned by an account
+pub fn tweets_owned_by<S: State>(
+    state: &S,
+    owner: &PublicKeyHash,
+    offset: usize,
+    limit: usize,
+) -> Page<u64> {
+    let path = format!("/accounts/{}/tweets/owned", owner.to_string());
+    paginate_ids(state, &path, offset, limit)
+}
+
+/// Lists the ids of the tweets liked by an account
+pub fn likes_by_account<S: State>(
+    state: &S,
+    account: &PublicKeyHash,
+    offset: usize,
+    limit: usize,
+) -> Page<u64> {
+    let path = format!("/accounts/{}/likes", account.to_string());
+    paginate_ids(state, &path, offset, limit)
+}
+
+/// Reads a direct message of the inbox of an account
+pub fn read_direct_message<S: State>(
+    state: &S,
+    account: &PublicKeyHash,
+    index: u64,
+) -> Result<Option<DirectMessageView>> {
+    let path = |field: &str| {
+        format!(
+            "/accounts/{}/inbox/{}/{}",
+            account.to_string(),
+            index,
+            field
+        )
+    };
+    let from = read(state, &path("from"), decode_public_key_hash)?;
+    let ciphertext = read(state, &path("ciphertext"), decode_string)?;
+    let ephemeral_pk = read(state, &path("ephemeral_pk"), decode_string)?;
+    let level = read(state, &path("level"), decode_u32)?;
+    match (from, ciphertext, ephemeral_pk, level) {
+        (Some(from), Some(ciphertext), Some(ephemeral_pk), Some(level)) => {
+            Ok(Some(DirectMessageView {
+                index,
+                from,
+                ciphertext,
+                ephemeral_pk,
+                level,
+            }))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Lists the direct messages of the inbox of an account
+pub fn direct_messages<S: State>(
+    state: &S,
+    account: &PublicKeyHash,
+    offset: usize,
+    limit: usize,
+) -> Result<Page<DirectMessageView>> {
+    let path = format!("/accounts/{}/inbox", account.to_string());
+    let Page { items, next } = paginate_ids(state, &path, offset, limit);
+    let mut direct_messages = Vec::with_capacity(items.len());
+    for index in items {
+        if let Some(direct_message) = read_direct_message(state, account, index)? {
+            direct_messages.push(direct_message);
+        }
+    }
+    Ok(Page {
+        items: direct_messages,
+        next,
+    })
+}
+
+/// Checks if an account has blocked the direct messages of another one
+pub fn is_blocked<S: State>(state: &S, account: &PublicKeyHash, blocked: &PublicKeyHash) -> bool {
+    let path = format!(
+        "/accounts/{}/blocked/{}",
+        account.to_string(),
+        blocked.to_string()
+    );
+    state.value(&path).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AUTHOR: &str = "tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv";
+    const AUTHOR_PUBLIC_KEY: &str = "edpkuDMUm7Y53wp4gxeLBXuiAhXZrLn8XB1R83ksvvesH8Lp8bmCfK";
+
+    fn dump() -> Dump {
+        let mut dump = Dump::default();
+        for id in 0..3_u64 {
+            dump.insert(&format!("/tweets/{}/author", id), AUTHOR.into());
+            dump.insert(&format!("/tweets/{}/content", id), b"Hello world".to_vec());
+            dump.insert(&format!("/tweets/{}/likes", id), id.to_be_bytes().to_vec());
+            dump.insert(&format!("/tweets/{}/owner", id), AUTHOR.into());
+            dump.insert(
+                &format!("/accounts/{}/tweets/written/{}", AUTHOR, id),
+                vec![0x00],
+            );
+        }
+        dump.insert("/tweets/1/collected_level", 10_u32.to_be_bytes().to_vec());
+        dump.insert("/tweets/2/report_count", 3_u64.to_be_bytes().to_vec());
+        dump.insert("/tweets/2/hidden", vec![0x00]);
+        dump.insert(&format!("/accounts/{}/likes/2", AUTHOR), vec![0x00]);
+        dump.insert(
+            &format!("/accounts/{}/nonce", AUTHOR),
+            4_u64.to_be_bytes().to_vec(),
+        );
+        dump.insert(
+            &format!("/accounts/{}/public_key", AUTHOR),
+            AUTHOR_PUBLIC_KEY.into(),
+        );
+        dump.insert("/receipts/abcd/success", vec![0x01]);
+        dump.insert("/receipts/abcd/level", 3_u32.to_be_bytes().to_vec());
+        dump.insert("/receipts/abcd/bytes_read", 8_u64.to_be_bytes().to_vec());
+        dump.insert(
+            "/receipts/abcd/bytes_written",
+            16_u64.to_be_bytes().to_vec(),
+        );
+        dump.insert(
+            "/metrics/3/like_tweet/messages",
+            2_u64.to_be_bytes().to_vec(),
+        );
+        dump.insert(
+            "/metrics/3/like_tweet/bytes_read",
+            16_u64.to_be_bytes().to_vec(),
+        );
+        dump.insert(
+            "/metrics/3/like_tweet/bytes_written",
+            32_u64.to_be_bytes().to_vec(),
+        );
+        dump
+    }
+
+    #[test]
+    fn test_read_tweet() {
+        let dump = dump();
+        let tweet = read_tweet(&dump, 1).unwrap().unwrap();
+        assert_eq!(tweet.author.to_string(), AUTHOR);
+        assert_eq!(tweet.content, "Hello world");
+        assert_eq!(tweet.likes, 1);
+        assert_eq!(tweet.collected_level, Some(10));
+        assert!(tweet.listing.is_none());
+        assert!(!tweet.hidden);
+
+        let tweet = read_tweet(&dump, 2).unwrap().unwrap();
+        assert_eq!(tweet.report_count, 3);
+        assert!(tweet.hidden);
+
+        assert!(read_tweet(&dump, 3).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_read_account_and_receipt() {
+        let dump = dump();
+        let author = PublicKeyHash::from_b58(AUTHOR).unwrap();
+        let account = read_account(&dump, &author).unwrap();
+        assert_eq!(account.nonce, 4);
+        assert_eq!(account.balance, 0);
+        assert_eq!(account.public_key.unwrap().to_b58(), AUTHOR_PUBLIC_KEY);
+
+        let receipt = read_receipt(&dump, "abcd").unwrap().unwrap();
+        assert!(receipt.success);
+        assert_eq!(receipt.level, Some(3));
+        assert_eq!(receipt.metrics.unwrap().bytes_written, 16);
+
+        let metrics = read_level_metrics(&dump, 3).unwrap();
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(metrics[0].kind, "like_tweet");
+        assert_eq!(metrics[0].messages, 2);
+        assert_eq!(metrics[0].metrics.bytes_read, 16);
+    }
+
+    #[test]
+    fn test_pagination() {
+        let dump = dump();
+        let author = PublicKeyHash::from_b58(AUTHOR).unwrap();
+
+        let page = tweets_by_author(&dump, &author, 0, 2);
+        assert_eq!(page.items, vec![0, 1]);
+        assert_eq!(page.next, Some(2));
+
+        let page = tweets_by_author(&dump, &author, 2, 2);
+        assert_eq!(page.items, vec![2]);
+        assert_eq!(page.next, None);
+
+        let page = likes_by_account(&dump, &author, 0, 10);
+        assert_eq!(page.items, vec![2]);
+
+        let page = tweets(&dump, 1, 10).unwrap();
+        assert_eq!(page.items.len(), 2);
+    }
+
+    #[test]
+    fn test_direct_messages() {
+        let mut dump = Dump::default();
+        let inbox = format!("/accounts/{}/inbox", AUTHOR);
+        dump.insert(&format!("{}/1/from", inbox), AUTHOR.into());
+        dump.insert(&format!("{}/1/ciphertext", inbox), b"00ff".to_vec());
+        dump.insert(&format!("{}/1/ephemeral_pk", inbox), b"edpk".to_vec());
+        dump.insert(&format!("{}/1/level", inbox), 7_u32.to_be_bytes().to_vec());
+        dump.insert(
+            &format!("/accounts/{}/blocked/{}", AUTHOR, AUTHOR),
+            vec![0x00],
+        );
+        let author = PublicKeyHash::from_b58(AUTHOR).unwrap();
+
+        let page = direct_messages(&dump, &author, 0, 10).unwrap();
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(page.items[0].index, 1);
+        assert_eq!(page.items[0].ciphertext, "00ff");
+        assert_eq!(page.items[0].level, 7);
+        assert!(is_blocked(&dump, &author, &author));
+    }
+
+    #[test]
+    fn test_invalid_value() {
+        assert!(decode_u64(&[0x00]).is_err());
+        assert!(decode_bool(&[0x02]).is_err());
+    }
+}
@@ -1,10 +1,10 @@
-use crate::core::error::*;
-use crate::core::hash::Blake2b;
-use crate::core::public_key::PublicKey;
-use serde::Deserialize;
+use crate::error::*;
+use crate::hash::Blake2b;
+use crate::public_key::PublicKey;
+use serde::{Deserialize, Serialize};
 use tezos_crypto_rs::hash::Ed25519Signature;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 pub enum Signature {
     Ed25519(Ed25519Signature),
 }
@@ -34,7 +34,7 @@ mod tests {
     use tezos_crypto_rs::hash::Ed25519Signature;
 
     use super::Signature;
-    use crate::core::public_key::PublicKey;
+    use crate::public_key::PublicKey;
 
     impl Signature {
         pub fn to_b58(&self) -> String {
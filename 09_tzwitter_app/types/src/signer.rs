@@ -0,0 +1,147 @@
+use ed25519_compact::{KeyPair, SecretKey, Seed};
+use tezos_crypto_rs::{
+    base58::FromBase58Check,
+    hash::{Ed25519Signature, PublicKeyEd25519},
+};
+
+use crate::{
+    error::{Error, Result},
+    hash::Blake2b,
+    message::{Inner, Message, Payload},
+    nonce::Nonce,
+    public_key::PublicKey,
+    public_key_hash::PublicKeyHash,
+    signature::Signature,
+};
+
+/// Prefix of the b58 encoded ed25519 seeds (edsk..., 54 characters)
+const ED25519_SEED_PREFIX: [u8; 4] = [13, 15, 58, 7];
+
+/// Prefix of the b58 encoded ed25519 secret keys (edsk..., 98 characters)
+const ED25519_SECRET_KEY_PREFIX: [u8; 4] = [43, 246, 78, 7];
+
+/// Signs the tzwitter messages with a local ed25519 key
+///
+/// It is used by the client, and by the tests of the kernel
+pub struct Signer {
+    keypair: KeyPair,
+}
+
+impl Signer {
+    /// Creates a signer from a b58 encoded secret key
+    ///
+    /// Both the seed and the secret key forms of edsk are accepted
+    pub fn from_b58(secret_key: &str) -> Result<Self> {
+        let bytes = secret_key
+            .from_base58check()
+            .map_err(|_| Error::InvalidSecretKey)?;
+        let (prefix, key) = bytes.split_at(4.min(bytes.len()));
+        let keypair = match (prefix, key.len()) {
+            (prefix, 32) if prefix == ED25519_SEED_PREFIX => {
+                let seed = Seed::from_slice(key)?;
+                KeyPair::from_seed(seed)
+            }
+            (prefix, 64) if prefix == ED25519_SECRET_KEY_PREFIX => {
+                let sk = SecretKey::from_slice(key)?;
+                KeyPair {
+                    pk: sk.public_key(),
+                    sk,
+                }
+            }
+            _ => return Err(Error::InvalidSecretKey),
+        };
+        Ok(Signer { keypair })
+    }
+
+    /// Creates the signer of a given seed, the same seed always gives the same keypair
+    ///
+    /// The keys are not secret, it should only be used by the tests
+    pub fn from_seed(seed: u8) -> Signer {
+        let keypair = KeyPair::from_seed(Seed::new([seed; 32]));
+        Signer { keypair }
+    }
+
+    /// Returns the public key of the signer
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey::Ed25519(PublicKeyEd25519(self.keypair.pk.to_vec()))
+    }
+
+    /// Returns the public key hash of the signer
+    pub fn public_key_hash(&self) -> PublicKeyHash {
+        PublicKeyHash::from(self.public_key())
+    }
+
+    /// Signs the content with the given nonce
+    ///
+    /// The nonce should be the next nonce of the account
+    pub fn sign<C: Payload>(&self, nonce: Nonce, content: C) -> Message<C> {
+        let inner = Inner::new(nonce, content);
+        let hash = signing_hash(&inner);
+        let signature = self.keypair.sk.sign(hash.as_ref(), None);
+        let signature = Signature::Ed25519(Ed25519Signature(signature.to_vec()));
+        Message::new(self.public_key(), signature, inner)
+    }
+}
+
+/// Computes the data signed by the client
+///
+/// The kernel verifies the signature of the blake2b of the inner hash,
+/// as a Tezos wallet signing the hex of the inner hash would do
+pub fn signing_hash<C: Payload>(inner: &Inner<C>) -> Blake2b {
+    let hash = inner.hash();
+    Blake2b::from(hash.as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{signing_hash, Signer};
+    use crate::{hash::Blake2b, message::Content, nonce::Nonce};
+
+    const ALICE_SK: &str = "edsk3QoqBuvdamxouPhin7swCvkQNgq4jP5KZPbwWNnwdZpSpJiEbq";
+    const ALICE_PKH: &str = "tz1VSUr8wwNhLAzempoch5d6hLRiTh8Cjcjb";
+
+    #[test]
+    fn test_signer_from_b58() {
+        let signer = Signer::from_b58(ALICE_SK).unwrap();
+        assert_eq!(signer.public_key_hash().to_string(), ALICE_PKH);
+    }
+
+    #[test]
+    fn test_invalid_secret_key() {
+        let res = Signer::from_b58("tz1VSUr8wwNhLAzempoch5d6hLRiTh8Cjcjb");
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_signer_from_seed() {
+        let signer = Signer::from_seed(1);
+        let other = Signer::from_seed(2);
+        assert_eq!(
+            signer.public_key_hash(),
+            Signer::from_seed(1).public_key_hash()
+        );
+        assert_ne!(signer.public_key_hash(), other.public_key_hash());
+    }
+
+    #[test]
+    fn test_signature_is_verified_by_the_kernel() {
+        let signer = Signer::from_b58(ALICE_SK).unwrap();
+        let message = signer.sign(Nonce(1), Content::LikeTweet(0));
+
+        let hash = message.inner().hash();
+        let res = message
+            .signature()
+            .verify(message.public_key(), hash.as_ref());
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn test_signing_hash() {
+        let signer = Signer::from_b58(ALICE_SK).unwrap();
+        let message = signer.sign(Nonce(1), Content::LikeTweet(0));
+
+        let signed = signing_hash(message.inner());
+        let expected = Blake2b::from(message.hash().as_ref());
+        assert_eq!(signed.to_string(), expected.to_string());
+    }
+}
@@ -4,12 +4,30 @@ use actix_web::{
     web::{self, Json, Query},
     App, HttpResponseBuilder, HttpServer, Responder,
 };
-use sequencer::{Kernel, NativeNode, Node, Runtime};
+use sequencer::{Confirmation, Kernel, NativeNode, Node, RollupConfig, Runtime, WasmKernel};
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 
 #[derive(Deserialize, Serialize)]
 pub struct Path {
     path: String,
+    /// Reads the state confirmed by the inbox instead of the simulated one
+    #[serde(default)]
+    confirmed: bool,
+}
+
+impl Path {
+    fn confirmation(&self) -> Confirmation {
+        match self.confirmed {
+            true => Confirmation::Hard,
+            false => Confirmation::Soft,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct Level {
+    level: u32,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -26,7 +44,10 @@ async fn post_message<N: Node>(node: web::Data<N>, body: Json<Body>) -> impl Res
 }
 
 async fn get_state_value<N: Node>(node: web::Data<N>, query: Query<Path>) -> impl Responder {
-    let res = node.as_ref().get_value(&query.path).await;
+    let res = node
+        .as_ref()
+        .get_value(&query.path, query.confirmation())
+        .await;
     match res {
         Some(data) => {
             let res = hex::encode(data);
@@ -37,7 +58,10 @@ async fn get_state_value<N: Node>(node: web::Data<N>, query: Query<Path>) -> imp
 }
 
 async fn get_state_subkeys<N: Node>(node: web::Data<N>, query: Query<Path>) -> impl Responder {
-    let res = node.as_ref().get_subkeys(&query.path).await;
+    let res = node
+        .as_ref()
+        .get_subkeys(&query.path, query.confirmation())
+        .await;
     match res {
         Some(data) => {
             let json = serde_json::to_string(&data);
@@ -50,6 +74,21 @@ async fn get_state_subkeys<N: Node>(node: web::Data<N>, query: Query<Path>) -> i
     }
 }
 
+async fn get_outbox<N: Node>(node: web::Data<N>, query: Query<Level>) -> impl Responder {
+    let res = node.as_ref().get_outbox(query.level).await;
+    match res {
+        Some(outbox) => {
+            let outbox = outbox.iter().map(hex::encode).collect::<Vec<String>>();
+            let json = serde_json::to_string(&outbox);
+            match json {
+                Ok(json) => HttpResponseBuilder::new(StatusCode::OK).body(json),
+                Err(_) => HttpResponseBuilder::new(StatusCode::INTERNAL_SERVER_ERROR).finish(),
+            }
+        }
+        None => HttpResponseBuilder::new(StatusCode::INTERNAL_SERVER_ERROR).finish(),
+    }
+}
+
 struct MyKernel {}
 
 impl Kernel for MyKernel {
@@ -58,14 +97,8 @@ impl Kernel for MyKernel {
     }
 }
 
-#[actix_web::main]
-async fn main() -> std::io::Result<()> {
-    let sled_database_uri = "/tmp/sequencer-storage";
-    let tezos_node_uri = "http://localhost:18731";
-    let rollup_node_uri = "http://localhost:8932";
-
-    let node =
-        sequencer::NativeNode::new::<MyKernel>(sled_database_uri, tezos_node_uri, rollup_node_uri);
+/// Serves the endpoints of the node
+async fn serve<N: Node + Send + Sync + 'static>(node: N) -> std::io::Result<()> {
     let state = web::Data::new(node);
 
     HttpServer::new(move || {
@@ -77,14 +110,47 @@ async fn main() -> std::io::Result<()> {
         App::new()
             .wrap(cors)
             .app_data(state.clone())
-            .route("/operations", web::post().to(post_message::<NativeNode>))
-            .route("/state/value", web::get().to(get_state_value::<NativeNode>))
-            .route(
-                "/state/subkeys",
-                web::get().to(get_state_subkeys::<NativeNode>),
-            )
+            .route("/operations", web::post().to(post_message::<N>))
+            .route("/state/value", web::get().to(get_state_value::<N>))
+            .route("/state/subkeys", web::get().to(get_state_subkeys::<N>))
+            .route("/outbox", web::get().to(get_outbox::<N>))
     })
     .bind(("127.0.0.1", 8080))?
     .run()
     .await
 }
+
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    let sled_database_uri = "/tmp/sequencer-storage";
+    let tezos_node_uri = "http://localhost:18731";
+    let rollup_node_uri = "http://localhost:8932";
+    let rollup_config = RollupConfig {
+        address: "sr1RYurGZtN8KNSpkMcCt9CgWeUaNkzsAfXf".to_string(),
+        origination_level: 0,
+        preimages_dir: "/tmp/sequencer-preimages".into(),
+        // The WASM binary of the kernel, run instead of the native kernel when it is set
+        boot_kernel: std::env::var_os("BOOT_KERNEL").map(PathBuf::from),
+    };
+
+    match rollup_config.boot_kernel {
+        Some(_) => {
+            let node = NativeNode::new::<WasmKernel>(
+                sled_database_uri,
+                tezos_node_uri,
+                rollup_node_uri,
+                rollup_config,
+            );
+            serve(node).await
+        }
+        None => {
+            let node = NativeNode::new::<MyKernel>(
+                sled_database_uri,
+                tezos_node_uri,
+                rollup_node_uri,
+                rollup_config,
+            );
+            serve(node).await
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use sequencer::{Database, SledDatabase};
+
+/// Number of children of the benchmarked directory
+const CHILDREN: u32 = 100_000;
+
+/// Database with a /tweets directory of 100k children
+fn tweets() -> SledDatabase {
+    let database = SledDatabase::new(&format!("/tmp/{}", uuid::Uuid::new_v4()));
+    for id in 0..CHILDREN {
+        let path = format!("/tweets/{}/author", id);
+        let _ = database.write(&path, b"tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv");
+    }
+    database
+}
+
+fn bench_directory(c: &mut Criterion) {
+    let database = tweets();
+
+    let mut id = CHILDREN;
+    c.bench_function("write a new child in a directory of 100k children", |b| {
+        b.iter(|| {
+            let path = format!("/tweets/{}/author", id);
+            let _ = database.write(&path, b"tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv");
+            id += 1;
+        })
+    });
+
+    c.bench_function("read a child in a directory of 100k children", |b| {
+        b.iter(|| database.read("/tweets/50000/author"))
+    });
+
+    c.bench_function("list a directory of 100k children", |b| {
+        b.iter(|| database.get_subkeys("/tweets"))
+    });
+
+    c.bench_function("copy a child of a directory of 100k children", |b| {
+        b.iter(|| database.copy("/tweets/50000", "/copied"))
+    });
+}
+
+criterion_group!(benches, bench_directory);
+criterion_main!(benches);
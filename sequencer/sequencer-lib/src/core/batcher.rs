@@ -2,6 +2,9 @@ use super::tezos_header::TezosHeader;
 use tezos_smart_rollup_host::input::Message;
 
 pub trait Batcher {
+    /// Assigns its message in the inbox to an operation
     fn on_operation(&mut self, operation: Vec<u8>) -> Message;
-    fn on_tezos_header(&mut self, tezos_header: &TezosHeader) -> Vec<Vec<u8>>;
+
+    /// Starts assigning the operations to the level following the header
+    fn on_tezos_header(&mut self, tezos_header: &TezosHeader);
 }
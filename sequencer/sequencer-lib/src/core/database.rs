@@ -49,4 +49,31 @@ pub trait Database: Clone {
 
     /// Copy a node to a new path
     fn copy(&self, from: &str, to: &str) -> Result<(), DatabaseError>;
+
+    /// Opens the snapshot of a level
+    ///
+    /// The changes made from now on are recorded in this snapshot
+    fn snapshot(&self, level: u32) -> Result<(), DatabaseError>;
+
+    /// Reverts the changes of the snapshot of the given level and of the following ones
+    fn revert(&self, level: u32) -> Result<(), DatabaseError>;
+
+    /// Reads the data of a path as it was when the snapshot of the level was opened
+    fn read_at(&self, level: u32, path: &str) -> Result<Option<Vec<u8>>, DatabaseError>;
+
+    /// Reads a node as it was when the snapshot of the level was opened
+    fn read_node_at(&self, level: u32, path: &str) -> Result<Option<TreeNode>, DatabaseError>;
+
+    /// Discards the snapshots of the given level and of the previous ones
+    ///
+    /// Their changes can't be reverted anymore
+    fn discard(&self, level: u32) -> Result<(), DatabaseError>;
+
+    /// Appends a message to the outbox of a level
+    ///
+    /// The outbox is not part of the state of the kernel, but it is reverted with the snapshots
+    fn write_output(&self, level: u32, output: &[u8]) -> Result<(), DatabaseError>;
+
+    /// Reads the messages of the outbox of a level, in the order they were written
+    fn read_outbox(&self, level: u32) -> Result<Vec<Vec<u8>>, DatabaseError>;
 }
@@ -0,0 +1,52 @@
+use super::tezos_header::TezosHeader;
+
+const INTERNAL_TAG: u8 = 0x00;
+const EXTERNAL_TAG: u8 = 0x01;
+const TRANSFER_TAG: u8 = 0x00;
+const START_OF_LEVEL_TAG: u8 = 0x01;
+const END_OF_LEVEL_TAG: u8 = 0x02;
+const INFO_PER_LEVEL_TAG: u8 = 0x03;
+
+/// Payload of an external message
+pub fn external_message(operation: &[u8]) -> Vec<u8> {
+    let mut payload = vec![EXTERNAL_TAG];
+    payload.extend_from_slice(operation);
+    payload
+}
+
+/// Payload of the transfer internal message
+///
+/// The payload is the binary Micheline of the parameters, the sender is a contract hash,
+/// the source is the tagged public key hash of the account and the destination is the rollup address
+pub fn transfer(payload: &[u8], sender: &[u8], source: &[u8], destination: &[u8]) -> Vec<u8> {
+    let mut message = vec![INTERNAL_TAG, TRANSFER_TAG];
+    message.extend_from_slice(payload);
+    message.extend_from_slice(sender);
+    message.extend_from_slice(source);
+    message.extend_from_slice(destination);
+    message
+}
+
+/// Payload of the start of level internal message
+pub fn start_of_level() -> Vec<u8> {
+    vec![INTERNAL_TAG, START_OF_LEVEL_TAG]
+}
+
+/// Payload of the end of level internal message
+pub fn end_of_level() -> Vec<u8> {
+    vec![INTERNAL_TAG, END_OF_LEVEL_TAG]
+}
+
+/// Payload of the info per level internal message
+///
+/// The given header is the predecessor of the level
+pub fn info_per_level(tezos_header: &TezosHeader) -> Vec<u8> {
+    // The headers come from the tezos node, their hash is well formed
+    let predecessor = tezos_header.hash_bytes().unwrap_or([0x00; 32]);
+    let timestamp = tezos_header.timestamp.timestamp();
+
+    let mut payload = vec![INTERNAL_TAG, INFO_PER_LEVEL_TAG];
+    payload.extend_from_slice(&timestamp.to_be_bytes());
+    payload.extend_from_slice(&predecessor);
+    payload
+}
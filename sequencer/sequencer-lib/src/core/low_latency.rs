@@ -7,5 +7,16 @@ where
     D: Database,
 {
     fn on_message<K: Kernel>(&mut self, message: Message);
-    fn on_tezos_header(&mut self, tezos_header: &TezosHeader);
+    fn on_tezos_header<K: Kernel>(&mut self, tezos_header: &TezosHeader);
+
+    /// Returns the first level which is not confirmed by the inbox yet
+    fn unconfirmed_level(&self) -> Option<u32>;
+
+    /// Confirms a level with the messages of its inbox
+    ///
+    /// If the inbox diverged from the simulation, the level is executed again
+    fn on_inbox<K: Kernel>(&mut self, level: u32, inbox: Vec<Vec<u8>>);
+
+    /// Confirms a level with the simulated messages, when its inbox can't be read
+    fn confirm(&mut self, level: u32);
 }
@@ -1,17 +1,21 @@
 mod batcher;
 mod database;
+mod inbox;
 mod injector;
 mod kernel;
 mod listen_tezos_header;
 mod low_latency;
 mod node;
+mod read_inbox;
 mod tezos_header;
 
 pub use self::batcher::*;
 pub use self::database::*;
+pub use self::inbox::*;
 pub use self::injector::*;
 pub use self::kernel::*;
 pub use self::listen_tezos_header::*;
 pub use self::low_latency::*;
 pub use self::node::*;
+pub use self::read_inbox::*;
 pub use self::tezos_header::*;
@@ -1,6 +1,12 @@
 use super::{
-    batcher::Batcher, database::Database, injector::Injector, kernel::Kernel,
-    listen_tezos_header::ListenTezosHeader, low_latency::LowLatency, tezos_header::TezosHeader,
+    batcher::Batcher,
+    database::{Database, DatabaseError, TreeNode},
+    injector::Injector,
+    kernel::Kernel,
+    listen_tezos_header::ListenTezosHeader,
+    low_latency::LowLatency,
+    read_inbox::{ReadInbox, ReadInboxError},
+    tezos_header::TezosHeader,
 };
 use async_trait::async_trait;
 use tokio::sync::{
@@ -8,11 +14,24 @@ use tokio::sync::{
     oneshot,
 };
 
+/// Number of successors after which a tezos block is final
+const FINALITY: u32 = 2;
+
+/// Confirmation of the state read from the node
+#[derive(Clone, Copy)]
+pub enum Confirmation {
+    /// State of the simulation, including the levels which are not final yet
+    Soft,
+    /// State of the last level confirmed by the inbox
+    Hard,
+}
+
 #[async_trait]
 pub trait Node {
     async fn submit_operation(&self, operation: Vec<u8>);
-    async fn get_value(&self, path: &str) -> Option<Vec<u8>>;
-    async fn get_subkeys(&self, path: &str) -> Option<Vec<String>>;
+    async fn get_value(&self, path: &str, confirmation: Confirmation) -> Option<Vec<u8>>;
+    async fn get_subkeys(&self, path: &str, confirmation: Confirmation) -> Option<Vec<String>>;
+    async fn get_outbox(&self, level: u32) -> Option<Vec<Vec<u8>>>;
 }
 
 enum QueueContent {
@@ -30,6 +49,23 @@ pub struct NodeImpl<D: Database> {
     database: D,
 }
 
+impl<D: Database> NodeImpl<D> {
+    /// Reads a node of the state
+    ///
+    /// The snapshots of the confirmed levels are discarded,
+    /// so the state before all the remaining snapshots is the confirmed one.
+    fn read_node(
+        &self,
+        path: &str,
+        confirmation: Confirmation,
+    ) -> Result<Option<TreeNode>, DatabaseError> {
+        match confirmation {
+            Confirmation::Soft => self.database.read_node(path),
+            Confirmation::Hard => self.database.read_node_at(0, path),
+        }
+    }
+}
+
 #[async_trait]
 impl<D: Database + Send + Sync> Node for NodeImpl<D> {
     async fn submit_operation(&self, operation: Vec<u8>) {
@@ -43,39 +79,49 @@ impl<D: Database + Send + Sync> Node for NodeImpl<D> {
         println!("Message submitted")
     }
 
-    async fn get_value(&self, path: &str) -> Option<Vec<u8>> {
-        match self.database.read(path) {
-            Ok(Some(value)) => Some(value),
+    async fn get_value(&self, path: &str, confirmation: Confirmation) -> Option<Vec<u8>> {
+        match self.read_node(path, confirmation) {
+            Ok(Some(node)) => node.value(),
             _ => None,
         }
     }
 
-    async fn get_subkeys(&self, path: &str) -> Option<Vec<String>> {
+    async fn get_subkeys(&self, path: &str, confirmation: Confirmation) -> Option<Vec<String>> {
         if path == "/" {
             return None;
         }
-        match self.database.get_subkeys(path) {
-            Ok(value) => Some(value),
+        match self.read_node(path, confirmation) {
+            Ok(node) => Some(node.map(|node| node.children()).unwrap_or_default()),
             _ => None,
         }
     }
+
+    async fn get_outbox(&self, level: u32) -> Option<Vec<Vec<u8>>> {
+        self.database.read_outbox(level).ok()
+    }
 }
 
-pub trait NodeBuilder<A, B, C, D, E>
+pub trait NodeBuilder<A, B, C, D, E, F>
 where
     A: ListenTezosHeader + Send + Sync + 'static,
     B: LowLatency<D> + Send + Sync + 'static,
     C: Batcher + Send + Sync + 'static,
     D: Database,
     E: Injector + Send + Sync + 'static,
+    F: ReadInbox + Send + Sync + 'static,
     Self: Sized,
 {
+    /// Starts the node
+    ///
+    /// The operations are injected as soon as they are received, to be included in the simulated level.
+    /// When a level is final, its inbox is read to confirm the simulation.
     fn start<K>(
         tezos_listener: A,
         mut low_latency: B,
         mut batcher: C,
         database: D,
         injector: E,
+        inbox_reader: F,
     ) -> NodeImpl<D>
     where
         K: Kernel,
@@ -93,14 +139,32 @@ where
                         }
 
                         match content {
-                            QueueContent::Message(msg) => {
-                                let msg = batcher.on_operation(msg);
+                            QueueContent::Message(operation) => {
+                                let msg = batcher.on_operation(operation.clone());
                                 low_latency.on_message::<K>(msg);
+                                let _ = injector.inject(vec![operation]).await;
                             }
                             QueueContent::TezosHeader(tezos_header) => {
-                                let batch = batcher.on_tezos_header(&tezos_header);
-                                low_latency.on_tezos_header(&tezos_header);
-                                let _ = injector.inject(batch).await;
+                                batcher.on_tezos_header(&tezos_header);
+                                low_latency.on_tezos_header::<K>(&tezos_header);
+
+                                while let Some(level) = low_latency.unconfirmed_level() {
+                                    if level + FINALITY > tezos_header.level {
+                                        break;
+                                    }
+                                    match inbox_reader.read_inbox(level).await {
+                                        Ok(inbox) => low_latency.on_inbox::<K>(level, inbox),
+                                        // The next levels are not blocked by this one
+                                        Err(ReadInboxError::Undecodable) => {
+                                            println!(
+                                                "The inbox of the level {} can't be decoded, confirming the simulated one",
+                                                level
+                                            );
+                                            low_latency.confirm(level);
+                                        }
+                                        Err(ReadInboxError::Unavailable) => break,
+                                    }
+                                }
                             }
                         }
                     }
@@ -0,0 +1,15 @@
+use async_trait::async_trait;
+
+#[derive(Debug, PartialEq)]
+pub enum ReadInboxError {
+    /// The block can't be fetched yet, the read can be retried later
+    Unavailable,
+    /// The operations of the block can't be converted to an inbox
+    Undecodable,
+}
+
+#[async_trait]
+pub trait ReadInbox {
+    /// Reads the payloads of the messages of the inbox at a given level
+    async fn read_inbox(&self, level: u32) -> Result<Vec<Vec<u8>>, ReadInboxError>;
+}
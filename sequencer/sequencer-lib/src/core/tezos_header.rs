@@ -1,8 +1,24 @@
+use chrono::{DateTime, Utc};
 use serde::Deserialize;
 
+/// Prefix of the b58 encoded block hashes
+const BLOCK_HASH_PREFIX: [u8; 2] = [1, 52];
+
 #[derive(Deserialize)]
 pub struct TezosHeader {
     pub hash: String,
     pub level: u32,
     pub predecessor: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl TezosHeader {
+    /// Decodes the b58 hash of the block
+    ///
+    /// Returns None if the hash is not a valid block hash
+    pub fn hash_bytes(&self) -> Option<[u8; 32]> {
+        let bytes = bs58::decode(&self.hash).with_check(None).into_vec().ok()?;
+        let hash = bytes.strip_prefix(BLOCK_HASH_PREFIX.as_slice())?;
+        hash.try_into().ok()
+    }
 }
@@ -1,5 +1,7 @@
-use super::native_runtime::{Host, NativeRuntime};
-use crate::core::{Database, Kernel, TezosHeader};
+use std::collections::BTreeMap;
+
+use super::native_runtime::{Host, NativeRuntime, RollupConfig};
+use crate::core::{end_of_level, info_per_level, start_of_level, Database, Kernel, TezosHeader};
 use tezos_smart_rollup_host::input::Message;
 
 pub struct LowLatency<D>
@@ -7,15 +9,65 @@ where
     D: Database,
 {
     native_runtime: NativeRuntime<D>,
+    database: D,
+    // Level and hash of the last tezos header
+    head: Option<(u32, String)>,
+    // Level of the simulated inbox, none until the first tezos header
+    level: Option<u32>,
+    // Last level confirmed by the inbox
+    confirmed_level: Option<u32>,
+    // Payloads of the simulated levels which are not confirmed yet
+    inputs: BTreeMap<u32, Vec<Vec<u8>>>,
 }
 
 impl<D> LowLatency<D>
 where
     D: Database,
 {
-    pub fn new(database: D) -> Self {
+    pub fn new(database: D, config: RollupConfig) -> Self {
         Self {
-            native_runtime: NativeRuntime::new(database),
+            native_runtime: NativeRuntime::new(database.clone(), config),
+            database,
+            head: None,
+            level: None,
+            confirmed_level: None,
+            inputs: BTreeMap::default(),
+        }
+    }
+
+    /// Adds an internal message at the end of the simulated inbox level
+    fn add_internal_message(&mut self, level: u32, payload: Vec<u8>) {
+        let inputs = self.inputs.entry(level).or_default();
+        let msg = Message::new(level, inputs.len() as u32, payload.clone());
+        inputs.push(payload);
+        self.native_runtime.add_message(msg);
+    }
+
+    /// Reverts the state to the first unconfirmed level
+    /// and executes again the inputs of the unconfirmed levels
+    fn replay<K: Kernel>(&mut self) {
+        let LowLatency {
+            native_runtime,
+            database,
+            inputs,
+            ..
+        } = self;
+
+        let first_level = match inputs.keys().next() {
+            Some(level) => *level,
+            None => return,
+        };
+        if database.revert(first_level).is_err() {
+            println!("Cannot revert the state to the level {}", first_level);
+            return;
+        }
+        for (level, payloads) in inputs.iter() {
+            let _ = database.snapshot(*level);
+            for (index, payload) in payloads.iter().enumerate() {
+                let msg = Message::new(*level, index as u32, payload.clone());
+                native_runtime.add_message(msg);
+            }
+            native_runtime.run::<K>();
         }
     }
 }
@@ -25,14 +77,207 @@ where
     D: Database,
 {
     fn on_message<K: Kernel>(&mut self, message: Message) {
-        // TODO Add the message to the runtime
+        // The operations received before the first tezos header are not confirmed
+        if let Some(inputs) = self.inputs.get_mut(&message.level) {
+            inputs.push(message.as_ref().to_vec());
+        }
         self.native_runtime.add_message(message);
 
         // Call the kernel function
-        K::entry(&mut self.native_runtime);
+        self.native_runtime.run::<K>();
+    }
+
+    /// Simulates the internal messages of the L1 inbox
+    ///
+    /// The current level is closed with an end of level,
+    /// and the next one is opened with a start of level and an info per level.
+    /// If the header does not follow the previous one, the chain has been reorganized:
+    /// the levels after the header are dropped and the unconfirmed ones are executed again.
+    fn on_tezos_header<K: Kernel>(&mut self, tezos_header: &TezosHeader) {
+        if let Some(level) = self.level {
+            self.add_internal_message(level, end_of_level());
+            self.native_runtime.run::<K>();
+        }
+
+        let reorganized = match &self.head {
+            Some((level, hash)) => {
+                tezos_header.level != level + 1 || &tezos_header.predecessor != hash
+            }
+            None => false,
+        };
+        if reorganized {
+            println!("Reorganization at the level {}", tezos_header.level);
+            self.inputs.retain(|level, _| *level <= tezos_header.level);
+            self.replay::<K>();
+        }
+        self.head = Some((tezos_header.level, tezos_header.hash.clone()));
+
+        let level = tezos_header.level + 1;
+        self.level = Some(level);
+        let _ = self.database.snapshot(level);
+        self.add_internal_message(level, start_of_level());
+        self.add_internal_message(level, info_per_level(tezos_header));
+        self.native_runtime.run::<K>();
+    }
+
+    fn unconfirmed_level(&self) -> Option<u32> {
+        match self.confirmed_level {
+            Some(level) => Some(level + 1),
+            None => self.inputs.keys().next().copied(),
+        }
+    }
+
+    fn on_inbox<K: Kernel>(&mut self, level: u32, inbox: Vec<Vec<u8>>) {
+        if self.inputs.get(&level) != Some(&inbox) {
+            println!(
+                "The inbox of the level {} diverged, executing it again",
+                level
+            );
+            self.inputs.insert(level, inbox);
+            self.replay::<K>();
+        }
+
+        self.confirm(level);
+    }
+
+    fn confirm(&mut self, level: u32) {
+        self.inputs.remove(&level);
+        self.confirmed_level = Some(level);
+        let _ = self.database.discard(level);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tezos_smart_rollup_host::{input::Message, path::OwnedPath, runtime::Runtime};
+
+    use super::LowLatency;
+    use crate::{
+        core::{
+            end_of_level, info_per_level, start_of_level, Database, Kernel, LowLatency as _,
+            TezosHeader,
+        },
+        implementations::{RollupConfig, SledDatabase},
+    };
+
+    const BLOCK_HASH: &str = "BLockGenesisGenesisGenesisGenesisGenesisf79b5d1CoW2";
+
+    /// Kernel storing its inputs under /inputs/{level}/{id}
+    struct RecordingKernel {}
+
+    impl Kernel for RecordingKernel {
+        fn entry<Host: Runtime>(host: &mut Host) {
+            while let Ok(Some(message)) = host.read_input() {
+                let path = format!("/inputs/{}/{}", message.level, message.id);
+                let path = OwnedPath::try_from(path).unwrap();
+                host.store_write(&path, message.as_ref(), 0).unwrap();
+            }
+        }
+    }
+
+    fn header(level: u32) -> TezosHeader {
+        TezosHeader {
+            hash: BLOCK_HASH.to_string(),
+            level,
+            predecessor: BLOCK_HASH.to_string(),
+            timestamp: "2023-04-20T10:00:00Z".parse().unwrap(),
+        }
+    }
+
+    fn database() -> SledDatabase {
+        SledDatabase::new(&format!("/tmp/{}", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn test_internal_messages() {
+        let database = database();
+        let mut low_latency = LowLatency::new(database.clone(), RollupConfig::default());
+
+        low_latency.on_tezos_header::<RecordingKernel>(&header(3));
+        low_latency.on_message::<RecordingKernel>(Message::new(4, 2, vec![0x01, 0x88]));
+        low_latency.on_tezos_header::<RecordingKernel>(&header(4));
+
+        let read = |path: &str| database.read(path).unwrap();
+
+        assert_eq!(read("/inputs/4/0"), Some(vec![0x00, 0x01]));
+        assert_eq!(read("/inputs/4/2"), Some(vec![0x01, 0x88]));
+        assert_eq!(read("/inputs/4/3"), Some(vec![0x00, 0x02]));
+        assert_eq!(read("/inputs/5/0"), Some(vec![0x00, 0x01]));
+
+        let info_per_level = read("/inputs/5/1").unwrap();
+        assert_eq!(info_per_level[..2], [0x00, 0x03]);
+        assert_eq!(info_per_level[2..10], 1_681_984_800_i64.to_be_bytes());
+        assert_eq!(
+            hex::encode(&info_per_level[10..]),
+            "8fcf233671b6a04fcf679d2a381c2544ea6c1ea29ba6157776ed8424c7ccd00b"
+        );
+    }
+
+    #[test]
+    fn test_divergent_inbox() {
+        let database = database();
+        let mut low_latency = LowLatency::new(database.clone(), RollupConfig::default());
+
+        low_latency.on_tezos_header::<RecordingKernel>(&header(3));
+        low_latency.on_message::<RecordingKernel>(Message::new(4, 2, vec![0x01, 0x88]));
+        low_latency.on_tezos_header::<RecordingKernel>(&header(4));
+        assert_eq!(low_latency.unconfirmed_level(), Some(4));
+
+        // The operation has not been included in the level 4
+        let inbox = vec![start_of_level(), info_per_level(&header(3)), end_of_level()];
+        low_latency.on_inbox::<RecordingKernel>(4, inbox);
+        assert_eq!(low_latency.unconfirmed_level(), Some(5));
+
+        let read = |path: &str| database.read(path).unwrap();
+        assert_eq!(read("/inputs/4/2"), Some(vec![0x00, 0x02]));
+        assert_eq!(read("/inputs/4/3"), None);
+        assert_eq!(read("/inputs/5/0"), Some(vec![0x00, 0x01]));
+    }
+
+    #[test]
+    fn test_confirmed_state() {
+        let database = database();
+        let mut low_latency = LowLatency::new(database.clone(), RollupConfig::default());
+
+        low_latency.on_tezos_header::<RecordingKernel>(&header(3));
+        low_latency.on_message::<RecordingKernel>(Message::new(4, 2, vec![0x01, 0x88]));
+        low_latency.on_tezos_header::<RecordingKernel>(&header(4));
+
+        // The confirmed state is read before the snapshots of the unconfirmed levels
+        assert_eq!(database.read_at(0, "/inputs/4/2").unwrap(), None);
+
+        let inbox = vec![
+            start_of_level(),
+            info_per_level(&header(3)),
+            vec![0x01, 0x88],
+            end_of_level(),
+        ];
+        low_latency.on_inbox::<RecordingKernel>(4, inbox);
+
+        let confirmed = |path: &str| database.read_at(0, path).unwrap();
+        assert_eq!(confirmed("/inputs/4/2"), Some(vec![0x01, 0x88]));
+        assert_eq!(confirmed("/inputs/5/0"), None);
+        assert_eq!(
+            database.read("/inputs/5/0").unwrap(),
+            Some(vec![0x00, 0x01])
+        );
     }
 
-    fn on_tezos_header(&mut self, _tezos_header: &TezosHeader) {
-        println!("TODO: simulate the end of level and the two first message of the inbox")
+    #[test]
+    fn test_confirm_simulated_level() {
+        let database = database();
+        let mut low_latency = LowLatency::new(database.clone(), RollupConfig::default());
+
+        low_latency.on_tezos_header::<RecordingKernel>(&header(3));
+        low_latency.on_message::<RecordingKernel>(Message::new(4, 2, vec![0x01, 0x88]));
+        low_latency.on_tezos_header::<RecordingKernel>(&header(4));
+
+        // The inbox of the level 4 can't be read, the simulated messages are confirmed
+        low_latency.confirm(4);
+        assert_eq!(low_latency.unconfirmed_level(), Some(5));
+        assert_eq!(
+            database.read_at(0, "/inputs/4/2").unwrap(),
+            Some(vec![0x01, 0x88])
+        );
     }
 }
@@ -3,13 +3,18 @@ mod native_batcher;
 mod native_runtime;
 mod rollup_batcher_injector;
 mod sled_database;
+mod tezos_inbox_reader;
 mod tezos_listener;
+mod wasm_kernel;
 
 pub use low_latency::*;
 pub use native_batcher::*;
+pub use native_runtime::RollupConfig;
 pub use rollup_batcher_injector::*;
 pub use sled_database::*;
+pub use tezos_inbox_reader::*;
 pub use tezos_listener::*;
+pub use wasm_kernel::*;
 
 // TODO:
 // implement host (but do not expose it, it will be part of the low latency module)
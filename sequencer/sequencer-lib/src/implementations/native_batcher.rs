@@ -1,50 +1,51 @@
 use tezos_smart_rollup_host::input::Message;
 
-use crate::core::TezosHeader;
+use crate::core::{external_message, TezosHeader};
 
+/// The start of level and the info per level are the first messages of a level
+const FIRST_EXTERNAL_INDEX: u32 = 2;
+
+/// Batcher assigning the position of the operations in the inbox
+///
+/// The operations are assigned to the level following the last tezos header,
+/// after the start of level and the info per level of this level.
+/// The L1 transfers and the messages posted directly to the inbox can't be predicted,
+/// if they are included in the same level they shift the indices of the batch.
 pub struct NativeBatcher {
-    tezos_level: u32,
-    batch: Vec<Vec<u8>>,
+    level: u32,
+    index: u32,
 }
 
 impl NativeBatcher {
     pub fn new() -> Self {
         Self {
-            tezos_level: 0,
-            batch: Vec::default(),
+            level: 1,
+            index: FIRST_EXTERNAL_INDEX,
         }
     }
 }
 
 impl crate::core::Batcher for NativeBatcher {
     fn on_operation(&mut self, operation: Vec<u8>) -> Message {
-        let message_payload = {
-            let mut data = vec![0x01];
-            let mut payload = operation.clone();
-            data.append(&mut payload);
-            data
-        };
-
-        let index = self.batch.len().try_into().unwrap(); // TODO: should we increment the index by 2 ? (because of the SOL and IOL)
-        let msg = Message::new(self.tezos_level, index, message_payload);
-
-        // Add the message to the batch
-        self.batch.push(operation);
-
+        let msg = Message::new(self.level, self.index, external_message(&operation));
+        self.index += 1;
         msg
     }
 
-    fn on_tezos_header(&mut self, tezos_header: &TezosHeader) -> Vec<Vec<u8>> {
-        self.tezos_level = tezos_header.level;
-        let batch = self.batch.clone();
-        self.batch = Vec::default();
-        batch
+    fn on_tezos_header(&mut self, tezos_header: &TezosHeader) {
+        self.level = tezos_header.level + 1;
+        self.index = FIRST_EXTERNAL_INDEX;
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{core::Batcher, implementations::NativeBatcher};
+    use crate::{
+        core::{Batcher, TezosHeader},
+        implementations::NativeBatcher,
+    };
+
+    use super::FIRST_EXTERNAL_INDEX;
 
     #[test]
     fn test_message_is_added() {
@@ -52,7 +53,7 @@ mod tests {
         let payload = vec![0x02, 0x03, 0x04];
         let _ = batcher.on_operation(payload);
 
-        assert_eq!(1, batcher.batch.len())
+        assert_eq!(FIRST_EXTERNAL_INDEX + 1, batcher.index)
     }
 
     #[test]
@@ -69,4 +70,21 @@ mod tests {
             payload
         );
     }
+
+    #[test]
+    fn test_message_position() {
+        let mut batcher = NativeBatcher::new();
+        batcher.on_tezos_header(&TezosHeader {
+            hash: "BLockGenesisGenesisGenesisGenesisGenesisf79b5d1CoW2".to_string(),
+            level: 7,
+            predecessor: "BLockGenesisGenesisGenesisGenesisGenesisf79b5d1CoW2".to_string(),
+            timestamp: "2023-04-20T10:00:00Z".parse().unwrap(),
+        });
+
+        let first = batcher.on_operation(vec![0x02]);
+        let second = batcher.on_operation(vec![0x03]);
+
+        assert_eq!((first.level, first.id), (8, 2));
+        assert_eq!((second.level, second.id), (8, 3));
+    }
 }
@@ -1,13 +1,91 @@
-use std::collections::VecDeque;
+use std::{cell::Cell, collections::VecDeque, path::PathBuf};
 
+use tezos_smart_rollup_core::{MAX_FILE_CHUNK_SIZE, MAX_OUTPUT_SIZE, PREIMAGE_HASH_SIZE};
 use tezos_smart_rollup_host::{
     input::Message,
+    metadata::RollupMetadata,
     path::Path,
     runtime::{Runtime, RuntimeError, ValueType},
     Error,
 };
 
-use crate::core::Database;
+use crate::core::{Database, Kernel};
+
+/// Maximum number of messages in the outbox of a level
+pub const MAX_OUTBOX_SIZE: usize = 100;
+
+/// Maximum number of reboots of the kernel in a level
+pub const MAX_REBOOTS: u32 = 1000;
+
+/// Maximum number of ticks of a run of the kernel
+pub const MAX_TICKS: u64 = 11_000_000_000;
+
+/// Approximate number of ticks of a call to the host
+const TICKS_PER_HOST_CALL: u64 = 10_000;
+
+/// Approximate number of ticks per byte exchanged with the host
+const TICKS_PER_BYTE: u64 = 100;
+
+/// Version of the WASM PVM simulated by the runtime
+const WASM_VERSION: &str = "2.0.0";
+
+/// Prefix of the b58check encoding of the smart rollup addresses
+pub(crate) const ROLLUP_ADDRESS_PREFIX: [u8; 3] = [6, 124, 117];
+
+thread_local! {
+    /// Ticks consumed by the current run of the kernel
+    ///
+    /// They are counted per thread, so the wasm kernel can add the ticks of its instructions
+    static TICKS: Cell<u64> = Cell::new(0);
+}
+
+/// Adds ticks to the current run of the kernel
+pub(crate) fn add_ticks(ticks: u64) {
+    TICKS.with(|cell| cell.set(cell.get().saturating_add(ticks)));
+}
+
+/// Returns the ticks left before the tick limit of the current run
+pub(crate) fn ticks_left() -> u64 {
+    MAX_TICKS.saturating_sub(TICKS.with(Cell::get))
+}
+
+/// Runs a function without counting the ticks of its host calls
+pub(crate) fn uncharged<T>(f: impl FnOnce() -> T) -> T {
+    let ticks = TICKS.with(Cell::get);
+    let res = f();
+    TICKS.with(|cell| cell.set(ticks));
+    res
+}
+
+/// Configuration of the rollup simulated by the runtime
+#[derive(Clone, Default)]
+pub struct RollupConfig {
+    /// Address of the rollup, in b58check (sr1...)
+    pub address: String,
+    /// Level of the origination of the rollup
+    pub origination_level: u32,
+    /// Directory of the preimages, named by the hex encoding of their hash
+    ///
+    /// It is the layout written by the get-reveal-installer command of the upgrade client
+    pub preimages_dir: PathBuf,
+    /// Kernel of the origination, installed at /kernel/boot.wasm in a new storage
+    pub boot_kernel: Option<PathBuf>,
+}
+
+impl RollupConfig {
+    /// Returns the metadata of the rollup, if the address is valid
+    pub fn metadata(&self) -> Option<RollupMetadata> {
+        let bytes = bs58::decode(&self.address)
+            .with_check(None)
+            .into_vec()
+            .ok()?;
+        let address = bytes.strip_prefix(ROLLUP_ADDRESS_PREFIX.as_slice())?;
+        Some(RollupMetadata {
+            raw_rollup_address: address.try_into().ok()?,
+            origination_level: self.origination_level,
+        })
+    }
+}
 
 pub struct NativeRuntime<D>
 where
@@ -15,23 +93,62 @@ where
 {
     inputs: VecDeque<Message>,
     db: D,
+    config: RollupConfig,
+    // Level of the last input read by the kernel
+    level: u32,
+    // Level of the last input added, the reboots are counted per level
+    inbox_level: u32,
+    reboots_left: u32,
+    reboot_requested: bool,
+    // Set when the inputs of a level have been dropped, until the end of the next run
+    restart_forced: bool,
+    last_run_aborted: bool,
 }
 
 impl<D> NativeRuntime<D>
 where
     D: Database,
 {
-    pub fn new(db: D) -> Self {
+    pub fn new(db: D, config: RollupConfig) -> Self {
         NativeRuntime {
             inputs: VecDeque::default(),
             db,
+            config,
+            level: 0,
+            inbox_level: 0,
+            reboots_left: MAX_REBOOTS,
+            reboot_requested: false,
+            restart_forced: false,
+            last_run_aborted: false,
         }
     }
+
+    /// Counts the ticks of a call to the host
+    ///
+    /// The execution of a native kernel is not metered, only its host calls
+    fn consume_ticks(&self, bytes: usize) {
+        add_ticks(
+            TICKS_PER_HOST_CALL.saturating_add((bytes as u64).saturating_mul(TICKS_PER_BYTE)),
+        );
+    }
+
+    /// Reads the value of a path
+    ///
+    /// Like the PVM host, a missing value is an error
+    fn read_value<T: Path>(&self, path: &T) -> Result<Vec<u8>, RuntimeError> {
+        let path = std::str::from_utf8(path.as_bytes())
+            .map_err(|_| RuntimeError::HostErr(Error::StoreInvalidKey))?;
+
+        self.db
+            .read(path)
+            .map_err(|_| RuntimeError::HostErr(Error::GenericInvalidAccess))?
+            .ok_or(RuntimeError::PathNotFound)
+    }
 }
 
 /// Check the size of the data
 ///
-/// The data should have a size greater than 2^31
+/// The data should not have a size greater than 2^31
 pub fn check_data_size(data: &[u8]) -> Result<&[u8], RuntimeError> {
     i32::try_from(data.len())
         .map_err(|_| RuntimeError::HostErr(Error::StoreValueSizeExceeded))
@@ -40,6 +157,9 @@ pub fn check_data_size(data: &[u8]) -> Result<&[u8], RuntimeError> {
 
 pub trait Host<D: Database>: Runtime {
     fn add_message(&mut self, msg: Message);
+
+    /// Runs the kernel, and reboots it while it requests it
+    fn run<K: Kernel>(&mut self);
 }
 
 impl<D> Host<D> for NativeRuntime<D>
@@ -47,16 +167,74 @@ where
     D: Database,
 {
     fn add_message(&mut self, msg: Message) {
+        if msg.level != self.inbox_level {
+            self.inbox_level = msg.level;
+            self.reboots_left = MAX_REBOOTS;
+        }
         self.inputs.push_back(msg);
     }
+
+    /// Like the PVM, the kernel can be rebooted at most 1000 times per level,
+    /// the inputs which have not been read are kept for the next run.
+    /// When the reboots are exhausted, the inputs left in the level are dropped
+    /// and the next run is flagged by `restart_forced`.
+    /// A run exceeding the tick limit is flagged as aborted.
+    fn run<K: Kernel>(&mut self) {
+        loop {
+            self.reboot_requested = false;
+            TICKS.with(|ticks| ticks.set(0));
+            K::entry(self);
+            self.restart_forced = false;
+
+            self.last_run_aborted = TICKS.with(Cell::get) > MAX_TICKS;
+            if self.last_run_aborted {
+                println!(
+                    "The kernel exceeded the tick limit at the level {}",
+                    self.level
+                );
+            }
+            if !self.reboot_requested {
+                break;
+            }
+            if self.reboots_left == 0 {
+                println!(
+                    "The kernel has no reboot left at the level {}, dropping its inputs",
+                    self.inbox_level
+                );
+                let inbox_level = self.inbox_level;
+                self.inputs.retain(|msg| msg.level != inbox_level);
+                self.restart_forced = true;
+                break;
+            }
+            self.reboots_left -= 1;
+        }
+    }
 }
 
 impl<D> Runtime for NativeRuntime<D>
 where
     D: Database,
 {
-    fn write_output(&mut self, _from: &[u8]) -> Result<(), RuntimeError> {
-        todo!()
+    /// Writes a message in the outbox of the level of the last input
+    ///
+    /// Like the PVM, a level can't have more than 100 outbox messages
+    fn write_output(&mut self, from: &[u8]) -> Result<(), RuntimeError> {
+        self.consume_ticks(from.len());
+        if from.len() > MAX_OUTPUT_SIZE {
+            return Err(RuntimeError::HostErr(Error::InputOutputTooLarge));
+        }
+
+        let outbox = self
+            .db
+            .read_outbox(self.level)
+            .map_err(|_| RuntimeError::HostErr(Error::GenericInvalidAccess))?;
+        if outbox.len() >= MAX_OUTBOX_SIZE {
+            return Err(RuntimeError::HostErr(Error::FullOutbox));
+        }
+
+        self.db
+            .write_output(self.level, from)
+            .map_err(|_| RuntimeError::HostErr(Error::GenericInvalidAccess))
     }
 
     fn write_debug(&self, msg: &str) {
@@ -64,10 +242,16 @@ where
     }
 
     fn read_input(&mut self) -> Result<Option<Message>, RuntimeError> {
-        Ok(self.inputs.pop_front())
+        let input = self.inputs.pop_front();
+        if let Some(message) = &input {
+            self.consume_ticks(message.as_ref().len());
+            self.level = message.level;
+        }
+        Ok(input)
     }
 
     fn store_has<T: Path>(&self, path: &T) -> Result<Option<ValueType>, RuntimeError> {
+        self.consume_ticks(0);
         let path = std::str::from_utf8(path.as_bytes())
             .map_err(|_| RuntimeError::HostErr(Error::StoreInvalidKey))?;
         self.db
@@ -84,79 +268,84 @@ where
             })
     }
 
+    /// Reads at most MAX_FILE_CHUNK_SIZE bytes of a value, from an offset
     fn store_read<T: Path>(
         &self,
         path: &T,
         from_offset: usize,
         max_bytes: usize,
     ) -> Result<Vec<u8>, RuntimeError> {
-        let NativeRuntime { db, .. } = self;
-        let path = std::str::from_utf8(path.as_bytes())
-            .map_err(|_| RuntimeError::HostErr(Error::StoreInvalidKey))?;
-
-        let res = db.read(path);
-        match res {
-            Ok(Some(vec)) => {
-                let mut data = vec
-                    .to_vec()
-                    .iter()
-                    .skip(from_offset)
-                    .copied()
-                    .collect::<Vec<u8>>();
-                if data.len() > max_bytes {
-                    data.resize(max_bytes, 0);
-                }
-                Ok(data)
-            }
-            Err(_) => Err(RuntimeError::HostErr(Error::GenericInvalidAccess)),
-            Ok(None) => Err(RuntimeError::PathNotFound),
+        let value = self.read_value(path)?;
+        if from_offset > value.len() {
+            return Err(RuntimeError::HostErr(Error::StoreInvalidAccess));
         }
+
+        let size = max_bytes
+            .min(MAX_FILE_CHUNK_SIZE)
+            .min(value.len() - from_offset);
+        self.consume_ticks(size);
+        Ok(value[from_offset..from_offset + size].to_vec())
     }
 
+    /// Reads a value from an offset into a buffer
+    ///
+    /// Returns the number of bytes read, at most the size of the buffer and MAX_FILE_CHUNK_SIZE
     fn store_read_slice<T: Path>(
         &self,
         path: &T,
         from_offset: usize,
         buffer: &mut [u8],
     ) -> Result<usize, RuntimeError> {
-        let path = std::str::from_utf8(path.as_bytes())
-            .map_err(|_| RuntimeError::HostErr(Error::StoreInvalidKey))?;
-
-        let res = self
-            .db
-            .read(path)
-            .map_err(|_| RuntimeError::HostErr(Error::GenericInvalidAccess))?;
-
-        match res {
-            Some(res) => {
-                let data = res.iter().skip(from_offset).copied().collect::<Vec<u8>>();
-                buffer.copy_from_slice(&data);
-                Ok(data.len())
-            }
-            None => Ok(0),
+        let value = self.read_value(path)?;
+        if from_offset > value.len() {
+            return Err(RuntimeError::HostErr(Error::StoreInvalidAccess));
         }
+
+        let size = buffer
+            .len()
+            .min(MAX_FILE_CHUNK_SIZE)
+            .min(value.len() - from_offset);
+        self.consume_ticks(size);
+        buffer[..size].copy_from_slice(&value[from_offset..from_offset + size]);
+        Ok(size)
     }
 
+    /// Writes the bytes at an offset of a value
+    ///
+    /// The value is extended if needed, the offset can't be after the end of the value
     fn store_write<T: Path>(
         &mut self,
         path: &T,
         src: &[u8],
         at_offset: usize,
     ) -> Result<(), RuntimeError> {
-        let NativeRuntime { db, .. } = self;
-        let path = std::str::from_utf8(path.as_bytes())
-            .map_err(|_| RuntimeError::HostErr(Error::StoreInvalidKey))?;
+        self.consume_ticks(src.len());
+        let mut value = match self.read_value(path) {
+            Ok(value) => value,
+            Err(RuntimeError::PathNotFound) => Vec::default(),
+            Err(err) => return Err(err),
+        };
+        if at_offset > value.len() {
+            return Err(RuntimeError::HostErr(Error::StoreInvalidAccess));
+        }
 
-        let src = check_data_size(src)?;
-        let data = src.iter().skip(at_offset).copied().collect::<Vec<u8>>();
-        let res = db.write(path, &data);
-        match res {
-            Ok(_) => Ok(()),
-            Err(_) => Err(RuntimeError::HostErr(Error::GenericInvalidAccess)),
+        let end = at_offset + src.len();
+        if end > value.len() {
+            value.resize(end, 0);
         }
+        value[at_offset..end].copy_from_slice(src);
+        let value = check_data_size(&value)?;
+
+        let path = std::str::from_utf8(path.as_bytes())
+            .map_err(|_| RuntimeError::HostErr(Error::StoreInvalidKey))?;
+        self.db
+            .write(path, value)
+            .map(|_| ())
+            .map_err(|_| RuntimeError::HostErr(Error::GenericInvalidAccess))
     }
 
     fn store_delete<T: Path>(&mut self, path: &T) -> Result<(), RuntimeError> {
+        self.consume_ticks(0);
         let path = std::str::from_utf8(path.as_bytes())
             .map_err(|_| RuntimeError::HostErr(Error::StoreInvalidKey))?;
 
@@ -166,6 +355,7 @@ where
     }
 
     fn store_count_subkeys<T: Path>(&self, prefix: &T) -> Result<u64, RuntimeError> {
+        self.consume_ticks(0);
         let path = std::str::from_utf8(prefix.as_bytes())
             .map_err(|_| RuntimeError::HostErr(Error::StoreInvalidKey))?;
 
@@ -196,6 +386,7 @@ where
         from_path: &impl Path,
         to_path: &impl Path,
     ) -> Result<(), RuntimeError> {
+        self.consume_ticks(0);
         let from = std::str::from_utf8(from_path.as_bytes())
             .map_err(|_| RuntimeError::HostErr(Error::StoreInvalidKey))?;
 
@@ -207,53 +398,246 @@ where
             .map_err(|_| RuntimeError::HostErr(Error::GenericInvalidAccess))
     }
 
+    /// Reads the preimage of a hash from the preimages directory
+    ///
+    /// The preimage is truncated to the size of the destination
     fn reveal_preimage(
         &self,
-        _hash: &[u8; tezos_smart_rollup_core::PREIMAGE_HASH_SIZE],
-        _destination: &mut [u8],
+        hash: &[u8; PREIMAGE_HASH_SIZE],
+        destination: &mut [u8],
     ) -> Result<usize, RuntimeError> {
-        todo!()
+        let path = self.config.preimages_dir.join(hex::encode(hash));
+        let preimage =
+            std::fs::read(path).map_err(|_| RuntimeError::HostErr(Error::GenericInvalidAccess))?;
+
+        let size = preimage.len().min(destination.len());
+        self.consume_ticks(size);
+        destination[..size].copy_from_slice(&preimage[..size]);
+        Ok(size)
     }
 
     fn store_value_size(&self, path: &impl Path) -> Result<usize, RuntimeError> {
-        let path = std::str::from_utf8(path.as_bytes())
-            .map_err(|_| RuntimeError::HostErr(Error::StoreInvalidKey))?;
-
-        let data = self
-            .db
-            .read(path)
-            .map_err(|_| RuntimeError::HostErr(Error::GenericInvalidAccess))?;
-
-        Ok(data.map(|data| data.len()).unwrap_or_default())
+        self.consume_ticks(0);
+        self.read_value(path).map(|value| value.len())
     }
 
     fn mark_for_reboot(&mut self) -> Result<(), RuntimeError> {
+        self.reboot_requested = true;
         Ok(())
     }
 
-    fn reveal_metadata(
-        &self,
-    ) -> Result<tezos_smart_rollup_host::metadata::RollupMetadata, RuntimeError> {
-        todo!()
+    fn reveal_metadata(&self) -> Result<RollupMetadata, RuntimeError> {
+        self.config
+            .metadata()
+            .ok_or(RuntimeError::HostErr(Error::GenericInvalidAccess))
     }
 
+    /// The native execution of the kernel is not interrupted,
+    /// but a run exceeding the tick limit is reported as aborted
     fn last_run_aborted(&self) -> Result<bool, RuntimeError> {
-        todo!()
+        Ok(self.last_run_aborted)
     }
 
     fn upgrade_failed(&self) -> Result<bool, RuntimeError> {
-        todo!()
+        Ok(false)
     }
 
     fn restart_forced(&self) -> Result<bool, RuntimeError> {
-        todo!()
+        Ok(self.restart_forced)
     }
 
     fn reboot_left(&self) -> Result<u32, RuntimeError> {
-        Ok(1000)
+        Ok(self.reboots_left)
     }
 
     fn runtime_version(&self) -> Result<String, RuntimeError> {
-        todo!()
+        Ok(WASM_VERSION.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tezos_smart_rollup_core::MAX_FILE_CHUNK_SIZE;
+    use tezos_smart_rollup_host::{
+        input::Message,
+        path::RefPath,
+        runtime::{Runtime, RuntimeError},
+        Error,
+    };
+    use tezos_smart_rollup_mock::MockHost;
+
+    use super::{Host, NativeRuntime, RollupConfig, MAX_OUTBOX_SIZE, MAX_REBOOTS};
+    use crate::{
+        core::{Database, Kernel},
+        implementations::SledDatabase,
+    };
+
+    const RUNS: RefPath = RefPath::assert_from(b"/runs");
+
+    /// Kernel reading one input per run, and rebooting after each input
+    ///
+    /// The number of runs is stored under /runs
+    struct RebootKernel {}
+
+    impl Kernel for RebootKernel {
+        fn entry<R: Runtime>(host: &mut R) {
+            let runs = match host.store_read(&RUNS, 0, 4) {
+                Ok(bytes) => u32::from_be_bytes(bytes.try_into().unwrap()),
+                Err(_) => 0,
+            };
+            host.store_write(&RUNS, &(runs + 1).to_be_bytes(), 0)
+                .unwrap();
+            if let Ok(Some(_)) = host.read_input() {
+                host.mark_for_reboot().unwrap();
+            }
+        }
+    }
+
+    fn database() -> SledDatabase {
+        SledDatabase::new(&format!("/tmp/{}", uuid::Uuid::new_v4()))
+    }
+
+    /// Store operations which should behave the same on the PVM host and on the native runtime
+    fn store_conformance<R: Runtime>(host: &mut R) {
+        let path = RefPath::assert_from(b"/value");
+        let missing = RefPath::assert_from(b"/missing");
+
+        // Writes at an offset, up to the end of the value
+        host.store_write(&path, &[0x01, 0x02, 0x03], 0).unwrap();
+        host.store_write(&path, &[0x04, 0x05], 2).unwrap();
+        assert_eq!(
+            host.store_read(&path, 0, 10).unwrap(),
+            vec![0x01, 0x02, 0x04, 0x05]
+        );
+        assert!(host.store_write(&path, &[0x06], 5).is_err());
+
+        // Reads from an offset
+        assert_eq!(host.store_read(&path, 1, 2).unwrap(), vec![0x02, 0x04]);
+        assert!(host.store_read(&path, 4, 1).unwrap().is_empty());
+        assert!(host.store_read(&path, 5, 1).is_err());
+        let res = host.store_read(&missing, 0, 1);
+        assert!(matches!(res, Err(RuntimeError::PathNotFound)));
+
+        // Reads in buffers of any size
+        let mut buffer = [0x00; 8];
+        assert_eq!(host.store_read_slice(&path, 1, &mut buffer).unwrap(), 3);
+        assert_eq!(buffer[..3], [0x02, 0x04, 0x05]);
+        let mut buffer = [0x00; 2];
+        assert_eq!(host.store_read_slice(&path, 0, &mut buffer).unwrap(), 2);
+        assert_eq!(buffer, [0x01, 0x02]);
+        assert!(host.store_read_slice(&missing, 0, &mut buffer).is_err());
+
+        // Sizes of the values
+        assert_eq!(host.store_value_size(&path).unwrap(), 4);
+        let res = host.store_value_size(&missing);
+        assert!(matches!(res, Err(RuntimeError::PathNotFound)));
+
+        // A read returns at most one chunk
+        let large = RefPath::assert_from(b"/large");
+        let chunk = vec![0x07; MAX_FILE_CHUNK_SIZE];
+        host.store_write(&large, &chunk, 0).unwrap();
+        host.store_write(&large, &chunk, MAX_FILE_CHUNK_SIZE)
+            .unwrap();
+        assert_eq!(
+            host.store_value_size(&large).unwrap(),
+            2 * MAX_FILE_CHUNK_SIZE
+        );
+        let read = host.store_read(&large, 0, usize::MAX).unwrap();
+        assert_eq!(read.len(), MAX_FILE_CHUNK_SIZE);
+        let mut buffer = vec![0x00; 2 * MAX_FILE_CHUNK_SIZE];
+        let size = host.store_read_slice(&large, 0, &mut buffer).unwrap();
+        assert_eq!(size, MAX_FILE_CHUNK_SIZE);
+    }
+
+    #[test]
+    fn test_store_conformance() {
+        store_conformance(&mut MockHost::default());
+        store_conformance(&mut NativeRuntime::new(database(), RollupConfig::default()));
+    }
+
+    #[test]
+    fn test_full_outbox() {
+        let database = database();
+        let mut runtime = NativeRuntime::new(database.clone(), RollupConfig::default());
+
+        runtime.add_message(Message::new(5, 0, vec![0x00, 0x01]));
+        let _ = runtime.read_input().unwrap();
+        for _ in 0..MAX_OUTBOX_SIZE {
+            runtime.write_output(&[0x01]).unwrap();
+        }
+        let res = runtime.write_output(&[0x01]);
+        assert!(matches!(res, Err(RuntimeError::HostErr(Error::FullOutbox))));
+        assert_eq!(database.read_outbox(5).unwrap().len(), MAX_OUTBOX_SIZE);
+
+        // The next level has its own outbox
+        runtime.add_message(Message::new(6, 0, vec![0x00, 0x01]));
+        let _ = runtime.read_input().unwrap();
+        runtime.write_output(&[0x02]).unwrap();
+        assert_eq!(database.read_outbox(6).unwrap(), vec![vec![0x02]]);
+    }
+
+    #[test]
+    fn test_reveal() {
+        let preimages_dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        std::fs::create_dir_all(&preimages_dir).unwrap();
+        let hash = [0x01; 33];
+        std::fs::write(preimages_dir.join(hex::encode(hash)), [0x0a, 0x0b, 0x0c]).unwrap();
+
+        let config = RollupConfig {
+            address: "sr1RYurGZtN8KNSpkMcCt9CgWeUaNkzsAfXf".to_string(),
+            origination_level: 42,
+            preimages_dir,
+            boot_kernel: None,
+        };
+        let runtime = NativeRuntime::new(database(), config);
+
+        let metadata = runtime.reveal_metadata().unwrap();
+        assert_eq!(
+            hex::encode(metadata.raw_rollup_address),
+            "d601f22256d2ad1faec0c64374e527c6e62f2e5a"
+        );
+        assert_eq!(metadata.origination_level, 42);
+
+        let mut destination = [0x00; 2];
+        assert_eq!(runtime.reveal_preimage(&hash, &mut destination).unwrap(), 2);
+        assert_eq!(destination, [0x0a, 0x0b]);
+        assert!(runtime
+            .reveal_preimage(&[0x02; 33], &mut destination)
+            .is_err());
+    }
+
+    #[test]
+    fn test_reboots() {
+        let database = database();
+        let mut runtime = NativeRuntime::new(database.clone(), RollupConfig::default());
+        let runs = || {
+            let bytes = database.read("/runs").unwrap().unwrap();
+            u32::from_be_bytes(bytes.try_into().unwrap())
+        };
+
+        for index in 0..3 {
+            runtime.add_message(Message::new(5, index, vec![0x00, 0x01]));
+        }
+        runtime.run::<RebootKernel>();
+        assert_eq!(runs(), 4);
+        assert_eq!(runtime.reboot_left().unwrap(), MAX_REBOOTS - 3);
+
+        assert!(!runtime.restart_forced().unwrap());
+
+        // The reboots are limited per level, the inputs left are dropped
+        for index in 0..MAX_REBOOTS + 10 {
+            runtime.add_message(Message::new(6, index, vec![0x00, 0x01]));
+        }
+        runtime.run::<RebootKernel>();
+        assert_eq!(runs(), 4 + MAX_REBOOTS + 1);
+        assert_eq!(runtime.reboot_left().unwrap(), 0);
+        assert!(runtime.inputs.is_empty());
+        assert!(runtime.restart_forced().unwrap());
+
+        // The forced restart is only reported to the next run
+        runtime.add_message(Message::new(7, 0, vec![0x00, 0x01]));
+        runtime.run::<RebootKernel>();
+        assert_eq!(runs(), 4 + MAX_REBOOTS + 1 + 2);
+        assert!(!runtime.restart_forced().unwrap());
     }
 }
@@ -1,27 +1,217 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+use sled::{
+    transaction::{ConflictableTransactionError, TransactionError},
+    Transactional,
+};
 
 use crate::core::{Database, DatabaseError, TreeNode};
 
+const VALUE_PREFIX: u8 = b'v';
+const CHILD_PREFIX: u8 = b'c';
+const CHILD_SEPARATOR: u8 = 0x00;
+const OUTBOX_PREFIX: u8 = b'o';
+
 /// Database using sled
+///
+/// The values and the children of the nodes are stored in two ranges of keys:
+///  - "v{path}" holds the value of a path
+///  - "c{path}\0{child}" records a child of a path, without value
+///
+/// The outbox messages are stored under "o{level}{index}", with big endian integers.
+///
+/// So the children of a path are listed with a prefix scan, and a subtree is a range of keys.
+///
+/// The snapshots are undo logs: the first time a key is changed in a snapshot,
+/// its previous value is recorded under the level of the snapshot followed by the key.
 #[derive(Clone)]
 pub struct SledDatabase {
     inner: sled::Db,
+    undo: sled::Tree,
+    snapshot: Arc<Mutex<Option<u32>>>,
+}
+
+/// Converts the error of a sled transaction
+fn transaction_error(error: TransactionError<DatabaseError>) -> DatabaseError {
+    match error {
+        TransactionError::Abort(error) => error,
+        TransactionError::Storage(_) => DatabaseError::IO,
+    }
+}
+
+/// Key of the previous value of a key in the undo log of a level
+fn undo_key(level: u32, key: &[u8]) -> Vec<u8> {
+    let mut undo_key = level.to_be_bytes().to_vec();
+    undo_key.extend_from_slice(key);
+    undo_key
+}
+
+/// Key of the value of a path
+fn value_key(path: &str) -> Vec<u8> {
+    let mut key = vec![VALUE_PREFIX];
+    key.extend_from_slice(path.as_bytes());
+    key
+}
+
+/// Prefix of the keys of the children of a path
+fn children_prefix(path: &str) -> Vec<u8> {
+    let mut key = vec![CHILD_PREFIX];
+    key.extend_from_slice(path.as_bytes());
+    key.push(CHILD_SEPARATOR);
+    key
+}
+
+/// Key of a child of a path
+fn child_key(path: &str, child: &str) -> Vec<u8> {
+    let mut key = children_prefix(path);
+    key.extend_from_slice(child.as_bytes());
+    key
+}
+
+/// Prefix of the keys of the outbox of a level
+fn outbox_prefix(level: u32) -> Vec<u8> {
+    let mut key = vec![OUTBOX_PREFIX];
+    key.extend_from_slice(&level.to_be_bytes());
+    key
+}
+
+/// Appends a subkey to a path
+fn join(path: &str, subkey: &str) -> String {
+    if path == "/" {
+        format!("/{}", subkey)
+    } else {
+        format!("{}/{}", path, subkey)
+    }
+}
+
+/// Returns the prefixes of the keys of the subtree of a path, the path excluded
+fn subtree_prefixes(path: &str) -> [Vec<u8>; 3] {
+    let descendants = join(path, "");
+    let mut values = vec![VALUE_PREFIX];
+    values.extend_from_slice(descendants.as_bytes());
+    let mut children = vec![CHILD_PREFIX];
+    children.extend_from_slice(descendants.as_bytes());
+    [values, children, children_prefix(path)]
 }
 
 impl SledDatabase {
     /// Open a connection to the sled database
     pub fn new(path: &str) -> Self {
         let inner = sled::open(path).unwrap();
-        Self { inner }
+        let undo = inner.open_tree("undo").unwrap();
+        Self {
+            inner,
+            undo,
+            snapshot: Arc::new(Mutex::new(None)),
+        }
     }
 
-    fn write_node<'a>(&self, node: &'a TreeNode) -> Result<&'a TreeNode, DatabaseError> {
-        let bytes = bincode::serialize(&node).map_err(|_| DatabaseError::EncodingError)?;
-        let _ = self
-            .inner
-            .insert(node.key(), bytes)
-            .map_err(|_| DatabaseError::IO)?;
-        Ok(node)
+    /// Sets or removes the value of a key
+    ///
+    /// The previous value is recorded in the opened snapshot, in the same transaction
+    fn set(&self, key: &[u8], value: Option<Vec<u8>>) -> Result<(), DatabaseError> {
+        let snapshot = *self.snapshot.lock().map_err(|_| DatabaseError::IO)?;
+
+        (&*self.inner, &self.undo)
+            .transaction(|(inner, undo)| {
+                let previous = match &value {
+                    Some(value) => inner.insert(key, value.clone())?,
+                    None => inner.remove(key)?,
+                };
+
+                if let Some(level) = snapshot {
+                    let undo_key = undo_key(level, key);
+                    if undo.get(&undo_key)?.is_none() {
+                        let previous = previous.map(|previous| previous.to_vec());
+                        let bytes = bincode::serialize(&previous).map_err(|_| {
+                            ConflictableTransactionError::Abort(DatabaseError::EncodingError)
+                        })?;
+                        undo.insert(undo_key, bytes)?;
+                    }
+                }
+                Ok(())
+            })
+            .map_err(transaction_error)
+    }
+
+    /// Lists the levels of the undo logs from a level
+    ///
+    /// Each level is found by seeking the first key after the previous level,
+    /// so the undo logs are not scanned.
+    fn undo_levels(&self, from: u32) -> Result<Vec<u32>, DatabaseError> {
+        let mut levels = Vec::default();
+        let mut next = Some(from);
+        while let Some(from) = next {
+            let entry = match self.undo.range(from.to_be_bytes()..).next() {
+                Some(entry) => entry,
+                None => break,
+            };
+            let (undo_key, _) = entry.map_err(|_| DatabaseError::IO)?;
+            let level = undo_key[..4]
+                .try_into()
+                .map(u32::from_be_bytes)
+                .map_err(|_| DatabaseError::EncodingError)?;
+            levels.push(level);
+            next = level.checked_add(1);
+        }
+        Ok(levels)
+    }
+
+    /// Reads the value of a key as it was when the snapshot of the level was opened
+    ///
+    /// The first previous value recorded from this level is the value at the opening of the snapshot,
+    /// if the key has not been changed since then it's the current value.
+    fn get_at(&self, level: u32, key: &[u8]) -> Result<Option<Vec<u8>>, DatabaseError> {
+        for level in self.undo_levels(level)? {
+            let bytes = self
+                .undo
+                .get(undo_key(level, key))
+                .map_err(|_| DatabaseError::IO)?;
+            if let Some(bytes) = bytes {
+                return bincode::deserialize(&bytes).map_err(|_| DatabaseError::EncodingError);
+            }
+        }
+        let current = self.inner.get(key).map_err(|_| DatabaseError::IO)?;
+        Ok(current.map(|current| current.to_vec()))
+    }
+
+    /// Lists the children of a path as they were when the snapshot of the level was opened
+    ///
+    /// The children added since then are removed from the current ones, and the deleted ones are added back
+    fn children_at(&self, level: u32, path: &str) -> Result<Vec<String>, DatabaseError> {
+        let prefix = children_prefix(path);
+        let mut keys = BTreeSet::<Vec<u8>>::default();
+        for entry in self.inner.scan_prefix(&prefix) {
+            let (key, _) = entry.map_err(|_| DatabaseError::IO)?;
+            keys.insert(key.to_vec());
+        }
+
+        let mut changed = HashSet::<Vec<u8>>::default();
+        for level in self.undo_levels(level)? {
+            for entry in self.undo.scan_prefix(undo_key(level, &prefix)) {
+                let (undo_key, bytes) = entry.map_err(|_| DatabaseError::IO)?;
+                let key = &undo_key[4..];
+                if !changed.insert(key.to_vec()) {
+                    continue;
+                }
+                let previous: Option<Vec<u8>> =
+                    bincode::deserialize(&bytes).map_err(|_| DatabaseError::EncodingError)?;
+                match previous {
+                    Some(_) => keys.insert(key.to_vec()),
+                    None => keys.remove(key),
+                };
+            }
+        }
+
+        keys.iter()
+            .map(|key| {
+                String::from_utf8(key[prefix.len()..].to_vec())
+                    .map_err(|_| DatabaseError::EncodingError)
+            })
+            .collect()
     }
 
     /// Retrieve all the subkeys of a key
@@ -41,11 +231,7 @@ impl SledDatabase {
 
         let (_, subkeys) =
             splitted.fold(("/".to_string(), subkeys), |(path, mut subkeys), subkey| {
-                let next_key = if path == "/" {
-                    format!("/{}", subkey)
-                } else {
-                    format!("{}/{}", path, subkey)
-                };
+                let next_key = join(&path, subkey);
 
                 subkeys.insert(path, subkey.to_string());
 
@@ -55,275 +241,403 @@ impl SledDatabase {
         subkeys
     }
 
+    /// Records a child of a path, if it's not already recorded
     fn add_subkey(&self, path: &str, subkey: &str) -> Result<(), DatabaseError> {
-        let node = self.read_node(path)?;
-        let node = match node {
-            None => TreeNode {
-                key: path.to_string(),
-                value: None,
-                children: vec![subkey.to_string()],
-            },
-            Some(node) => {
-                let subkey = subkey.to_string();
-                if !node.children().contains(&subkey) {
-                    let mut children = node.children;
-                    children.push(subkey);
-                    TreeNode { children, ..node }
-                } else {
-                    node
-                }
-            }
-        };
-        let _ = self.write_node(&node)?;
+        let key = child_key(path, subkey);
+        let exists = self
+            .inner
+            .contains_key(&key)
+            .map_err(|_| DatabaseError::IO)?;
+        if !exists {
+            self.set(&key, Some(Vec::default()))?;
+        }
+        Ok(())
+    }
+
+    /// Removes all the keys starting with a prefix
+    fn remove_prefix(&self, prefix: &[u8]) -> Result<(), DatabaseError> {
+        for entry in self.inner.scan_prefix(prefix) {
+            let (key, _) = entry.map_err(|_| DatabaseError::IO)?;
+            self.set(&key, None)?;
+        }
         Ok(())
     }
 }
 
 impl Database for SledDatabase {
     fn write<'a>(&self, path: &str, data: &'a [u8]) -> Result<&'a [u8], DatabaseError> {
-        println!("write node {}", path);
-        let subkeys = SledDatabase::get_all_subkeys(path);
-        println!("{:?}", &subkeys);
-
         // Creates/Update node's subkeys
-        for (path, subkey) in subkeys {
-            println!("add subkey {} to path {}", subkey, path);
+        for (path, subkey) in SledDatabase::get_all_subkeys(path) {
             self.add_subkey(&path, &subkey)?;
         }
 
-        let node = TreeNode {
-            key: path.to_string(),
-            value: Some(data.to_vec()),
-            children: Vec::new(),
-        };
-
-        let _ = self.write_node(&node)?;
+        self.set(&value_key(path), Some(data.to_vec()))?;
         Ok(data)
     }
 
     fn read(&self, path: &str) -> Result<Option<Vec<u8>>, DatabaseError> {
-        let node = self.read_node(path)?;
-        match node {
-            None => Ok(None),
-            Some(TreeNode { value, .. }) => Ok(value),
-        }
+        let value = self
+            .inner
+            .get(value_key(path))
+            .map_err(|_| DatabaseError::IO)?;
+        Ok(value.map(|value| value.to_vec()))
     }
 
     fn get_subkeys(&self, path: &str) -> Result<Vec<String>, DatabaseError> {
-        let node = self.read_node(path)?;
-        match node {
-            None => Ok(Vec::default()),
-            Some(TreeNode { children, .. }) => {
-                println!("children of the node {}: {:?}", path, children);
-                Ok(children)
-            }
-        }
+        let prefix = children_prefix(path);
+        self.inner
+            .scan_prefix(&prefix)
+            .map(|entry| {
+                let (key, _) = entry.map_err(|_| DatabaseError::IO)?;
+                String::from_utf8(key[prefix.len()..].to_vec())
+                    .map_err(|_| DatabaseError::EncodingError)
+            })
+            .collect()
     }
 
+    /// Deletes the value of a path and its subtree
+    ///
+    /// The path is also removed from the children of its parent
     fn delete(&self, path: &str) -> Result<(), DatabaseError> {
-        let node = self.read_node(path)?;
-        match node {
-            None => Ok(()),
-            Some(node) => {
-                for child in node.children() {
-                    let path = if path == "/" {
-                        format!("/{}", child)
-                    } else {
-                        format!("{}/{}", path, child)
-                    };
-                    println!("deleting: {path}");
-                    self.delete(&path)?;
-                }
-                self.inner.remove(path).map_err(|_| DatabaseError::IO)?;
-                Ok(())
-            }
+        for prefix in subtree_prefixes(path) {
+            self.remove_prefix(&prefix)?;
+        }
+        self.set(&value_key(path), None)?;
+
+        if let Some((parent, child)) = path.rsplit_once('/') {
+            let parent = if parent.is_empty() { "/" } else { parent };
+            self.set(&child_key(parent, child), None)?;
         }
+        Ok(())
     }
 
     fn read_node(&self, path: &str) -> Result<Option<TreeNode>, DatabaseError> {
-        let res = self.inner.get(path).map_err(|_| DatabaseError::IO)?;
-        match res {
-            None => Ok(None),
-            Some(bytes) => {
-                let node =
-                    bincode::deserialize(&bytes).map_err(|_| DatabaseError::EncodingError)?;
-                Ok(Some(node))
-            }
+        let value = self.read(path)?;
+        let children = self.get_subkeys(path)?;
+        match (value, children.is_empty()) {
+            (None, true) => Ok(None),
+            (value, _) => Ok(Some(TreeNode {
+                key: path.to_string(),
+                value,
+                children,
+            })),
         }
     }
 
     /// Copy a node to a new path
     ///
-    /// TODO: this is really not optimized
+    /// The subtree of the destination is replaced by the one of the source
     fn copy(&self, from: &str, to: &str) -> Result<(), DatabaseError> {
-        let node = self.read_node(from)?;
-        match node {
-            None => Ok(()),
-            Some(node) => {
-                for child in &node.children() {
-                    let from_path = if from == "/" {
-                        format!("/{}", child)
-                    } else {
-                        format!("{}/{}", from, child)
-                    };
-                    let to_path = if from == "/" {
-                        format!("/{}", child)
-                    } else {
-                        format!("{}/{}", to, child)
-                    };
-                    self.copy(&from_path, &to_path)?;
-                }
-                let copied = TreeNode {
-                    key: to.to_string(),
-                    value: node.value(),
-                    children: node.children(),
-                };
-                self.write_node(&copied)?;
+        if from == to {
+            return Ok(());
+        }
+
+        let [descendants, _, _] = subtree_prefixes(from);
+        let mut values = Vec::default();
+        if let Some(value) = self.read(from)? {
+            values.push((to.to_string(), value));
+        }
+        for entry in self.inner.scan_prefix(&descendants) {
+            let (key, value) = entry.map_err(|_| DatabaseError::IO)?;
+            // The key is the value prefix followed by the path of the descendant
+            let subpath = std::str::from_utf8(&key[descendants.len()..])
+                .map_err(|_| DatabaseError::EncodingError)?;
+            values.push((join(to, subpath), value.to_vec()));
+        }
+        if values.is_empty() {
+            return Ok(());
+        }
+
+        self.delete(to)?;
+        for (path, value) in values {
+            self.write(&path, &value)?;
+        }
+        Ok(())
+    }
 
-                let subkeys = SledDatabase::get_all_subkeys(to);
-                // Creates/Update node's subkeys
-                for (path, subkey) in subkeys {
-                    self.add_subkey(&path, &subkey)?;
+    fn snapshot(&self, level: u32) -> Result<(), DatabaseError> {
+        let mut snapshot = self.snapshot.lock().map_err(|_| DatabaseError::IO)?;
+        *snapshot = Some(level);
+        Ok(())
+    }
+
+    /// Revert the changes of the snapshots
+    ///
+    /// The undo logs are applied from the last level to the given one,
+    /// so the keys get back the value they had when the given snapshot was opened
+    fn revert(&self, level: u32) -> Result<(), DatabaseError> {
+        let mut snapshot = self.snapshot.lock().map_err(|_| DatabaseError::IO)?;
+
+        let mut entries = Vec::default();
+        for entry in self.undo.range(level.to_be_bytes()..).rev() {
+            let (undo_key, bytes) = entry.map_err(|_| DatabaseError::IO)?;
+            let previous: Option<Vec<u8>> =
+                bincode::deserialize(&bytes).map_err(|_| DatabaseError::EncodingError)?;
+            entries.push((undo_key, previous));
+        }
+
+        (&*self.inner, &self.undo)
+            .transaction(|(inner, undo)| {
+                for (undo_key, previous) in &entries {
+                    let key = &undo_key[4..];
+                    match previous {
+                        Some(previous) => inner.insert(key, previous.clone())?,
+                        None => inner.remove(key)?,
+                    };
+                    undo.remove(undo_key.clone())?;
                 }
                 Ok(())
-            }
+            })
+            .map_err(transaction_error)?;
+
+        *snapshot = None;
+        Ok(())
+    }
+
+    fn read_at(&self, level: u32, path: &str) -> Result<Option<Vec<u8>>, DatabaseError> {
+        self.get_at(level, &value_key(path))
+    }
+
+    fn read_node_at(&self, level: u32, path: &str) -> Result<Option<TreeNode>, DatabaseError> {
+        let value = self.read_at(level, path)?;
+        let children = self.children_at(level, path)?;
+        match (value, children.is_empty()) {
+            (None, true) => Ok(None),
+            (value, _) => Ok(Some(TreeNode {
+                key: path.to_string(),
+                value,
+                children,
+            })),
         }
     }
+
+    fn discard(&self, level: u32) -> Result<(), DatabaseError> {
+        let end = level.saturating_add(1).to_be_bytes();
+        for entry in self.undo.range(..end) {
+            let (undo_key, _) = entry.map_err(|_| DatabaseError::IO)?;
+            self.undo.remove(undo_key).map_err(|_| DatabaseError::IO)?;
+        }
+        Ok(())
+    }
+
+    fn write_output(&self, level: u32, output: &[u8]) -> Result<(), DatabaseError> {
+        let mut key = outbox_prefix(level);
+        let index = self.inner.scan_prefix(&key).count() as u32;
+        key.extend_from_slice(&index.to_be_bytes());
+        self.set(&key, Some(output.to_vec()))
+    }
+
+    fn read_outbox(&self, level: u32) -> Result<Vec<Vec<u8>>, DatabaseError> {
+        self.inner
+            .scan_prefix(outbox_prefix(level))
+            .map(|entry| {
+                let (_, output) = entry.map_err(|_| DatabaseError::IO)?;
+                Ok(output.to_vec())
+            })
+            .collect()
+    }
 }
 
-// #[cfg(test)]
-// mod tests {
-//     use std::fs;
-
-//     use crate::_database::Database;
-
-//     use super::SledDatabase;
-
-//     /// Wrapper of the SledDatabase to wipe it at then end of the tests
-//     struct Db {
-//         inner: SledDatabase,
-//         path: String,
-//     }
-
-//     impl AsRef<SledDatabase> for Db {
-//         fn as_ref(&self) -> &SledDatabase {
-//             &self.inner
-//         }
-//     }
-
-//     impl Drop for Db {
-//         fn drop(&mut self) {
-//             let _ = fs::remove_dir(&self.path);
-//         }
-//     }
-
-//     impl Default for Db {
-//         fn default() -> Self {
-//             let path = format!("/tmp/{}", uuid::Uuid::new_v4());
-//             let inner = SledDatabase::new(&path);
-//             Self { inner, path }
-//         }
-//     }
-
-//     #[test]
-//     fn test_write() {
-//         let database = Db::default();
-//         let database = database.as_ref();
-//         let data = [0x01, 0x02, 0x03, 0x04];
-//         let res = database.write("/path", &data);
-//         assert!(res.is_ok())
-//     }
-
-//     #[test]
-//     fn test_read() {
-//         let database = Db::default();
-//         let database = database.as_ref();
-//         let data = [0x01, 0x02, 0x03, 0x04];
-
-//         let _ = database.write("/path", &data).unwrap();
-//         let res = database.read("/path").unwrap().unwrap();
-
-//         assert_eq!(res, data.to_vec());
-//     }
-
-//     #[test]
-//     fn test_read_unknown() {
-//         let database = Db::default();
-//         let database = database.as_ref();
-//         let res = database.read("/path");
-
-//         assert!(res.is_ok());
-//         assert!(res.unwrap().is_none());
-//     }
-
-//     #[test]
-//     fn test_delete() {
-//         let database = Db::default();
-//         let database = database.as_ref();
-//         let data = [0x01, 0x02, 0x03, 0x04];
-
-//         let _ = database.write("/path", &data).unwrap();
-//         let () = database.delete("/path").unwrap();
-//         let res = database.read("/path").unwrap();
-
-//         assert!(res.is_none());
-//     }
-
-//     #[test]
-//     fn test_get_subkeys() {
-//         let database = Db::default();
-//         let database = database.as_ref();
-//         let data = [0x01, 0x02, 0x03, 0x04];
-
-//         let _ = database.write("/path/sub", &data).unwrap();
-//         let root_res = database.get_subkeys("/").unwrap();
-//         let path_res = database.get_subkeys("/path").unwrap();
-//         let sub_res = database.get_subkeys("/path/sub").unwrap();
-
-//         assert_eq!(root_res, vec!["path"]);
-//         assert_eq!(path_res, vec!["sub"]);
-//         assert!(sub_res.is_empty());
-//     }
-
-//     #[test]
-//     fn test_get_delete() {
-//         let database = Db::default();
-//         let database = database.as_ref();
-//         let data = [0x01, 0x02, 0x03, 0x04];
-
-//         let _ = database.write("/path/sub", &data).unwrap();
-//         let _ = database.delete("/").unwrap();
-
-//         let root_res = database.get_subkeys("/").unwrap();
-//         let path_res = database.get_subkeys("/path").unwrap();
-//         let sub_res = database.get_subkeys("/path/sub").unwrap();
-
-//         let empty: Vec<String> = Vec::default();
-//         assert_eq!(root_res, empty);
-//         assert_eq!(path_res, empty);
-//         assert_eq!(sub_res, empty);
-//     }
-
-//     #[test]
-//     fn test_copy() {
-//         let database = Db::default();
-//         let database = database.as_ref();
-//         let data = [0x01, 0x02, 0x03, 0x04];
-
-//         let _ = database.write("/path/a/b", &data).unwrap();
-//         let _ = database.copy("/path", "/c").unwrap();
-
-//         let c_res = database.get_subkeys("/c").unwrap();
-//         let a_res = database.get_subkeys("/c/a").unwrap();
-//         let b_res = database.get_subkeys("/c/a/b").unwrap();
-//         let root_res = database.get_subkeys("/").unwrap();
-//         let copied_res = database.read("/c/a/b").unwrap().unwrap();
-
-//         let empty: Vec<String> = Vec::default();
-//         assert_eq!(c_res, vec!["a"]);
-//         assert_eq!(a_res, vec!["b"]);
-//         assert_eq!(b_res, empty);
-//         assert_eq!(root_res, vec!["path", "c"]);
-//         assert_eq!(copied_res, data)
-//     }
-// }
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::core::Database;
+
+    use super::SledDatabase;
+
+    /// Wrapper of the SledDatabase to wipe it at then end of the tests
+    struct Db {
+        inner: SledDatabase,
+        path: String,
+    }
+
+    impl AsRef<SledDatabase> for Db {
+        fn as_ref(&self) -> &SledDatabase {
+            &self.inner
+        }
+    }
+
+    impl Drop for Db {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+
+    impl Default for Db {
+        fn default() -> Self {
+            let path = format!("/tmp/{}", uuid::Uuid::new_v4());
+            let inner = SledDatabase::new(&path);
+            Self { inner, path }
+        }
+    }
+
+    #[test]
+    fn test_write() {
+        let database = Db::default();
+        let database = database.as_ref();
+        let data = [0x01, 0x02, 0x03, 0x04];
+        let res = database.write("/path", &data);
+        assert!(res.is_ok())
+    }
+
+    #[test]
+    fn test_read() {
+        let database = Db::default();
+        let database = database.as_ref();
+        let data = [0x01, 0x02, 0x03, 0x04];
+
+        let _ = database.write("/path", &data).unwrap();
+        let res = database.read("/path").unwrap().unwrap();
+
+        assert_eq!(res, data.to_vec());
+    }
+
+    #[test]
+    fn test_read_unknown() {
+        let database = Db::default();
+        let database = database.as_ref();
+        let res = database.read("/path");
+
+        assert!(res.is_ok());
+        assert!(res.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_delete() {
+        let database = Db::default();
+        let database = database.as_ref();
+        let data = [0x01, 0x02, 0x03, 0x04];
+
+        let _ = database.write("/path", &data).unwrap();
+        let () = database.delete("/path").unwrap();
+        let res = database.read("/path").unwrap();
+
+        assert!(res.is_none());
+    }
+
+    #[test]
+    fn test_get_subkeys() {
+        let database = Db::default();
+        let database = database.as_ref();
+        let data = [0x01, 0x02, 0x03, 0x04];
+
+        let _ = database.write("/path/sub", &data).unwrap();
+        let root_res = database.get_subkeys("/").unwrap();
+        let path_res = database.get_subkeys("/path").unwrap();
+        let sub_res = database.get_subkeys("/path/sub").unwrap();
+
+        assert_eq!(root_res, vec!["path"]);
+        assert_eq!(path_res, vec!["sub"]);
+        assert!(sub_res.is_empty());
+    }
+
+    #[test]
+    fn test_get_delete() {
+        let database = Db::default();
+        let database = database.as_ref();
+        let data = [0x01, 0x02, 0x03, 0x04];
+
+        let _ = database.write("/path/sub", &data).unwrap();
+        let _ = database.delete("/").unwrap();
+
+        let root_res = database.get_subkeys("/").unwrap();
+        let path_res = database.get_subkeys("/path").unwrap();
+        let sub_res = database.get_subkeys("/path/sub").unwrap();
+
+        let empty: Vec<String> = Vec::default();
+        assert_eq!(root_res, empty);
+        assert_eq!(path_res, empty);
+        assert_eq!(sub_res, empty);
+    }
+
+    #[test]
+    fn test_delete_keeps_siblings() {
+        let database = Db::default();
+        let database = database.as_ref();
+        let data = [0x01, 0x02, 0x03, 0x04];
+
+        let _ = database.write("/a/b", &data).unwrap();
+        let _ = database.write("/ab", &data).unwrap();
+        let _ = database.delete("/a").unwrap();
+
+        assert_eq!(database.get_subkeys("/").unwrap(), vec!["ab"]);
+        assert_eq!(database.read("/ab").unwrap(), Some(data.to_vec()));
+        assert!(database.read("/a/b").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_copy() {
+        let database = Db::default();
+        let database = database.as_ref();
+        let data = [0x01, 0x02, 0x03, 0x04];
+
+        let _ = database.write("/path/a/b", &data).unwrap();
+        let _ = database.copy("/path", "/c").unwrap();
+
+        let c_res = database.get_subkeys("/c").unwrap();
+        let a_res = database.get_subkeys("/c/a").unwrap();
+        let b_res = database.get_subkeys("/c/a/b").unwrap();
+        let root_res = database.get_subkeys("/").unwrap();
+        let copied_res = database.read("/c/a/b").unwrap().unwrap();
+
+        let empty: Vec<String> = Vec::default();
+        assert_eq!(c_res, vec!["a"]);
+        assert_eq!(a_res, vec!["b"]);
+        assert_eq!(b_res, empty);
+        assert_eq!(root_res, vec!["c", "path"]);
+        assert_eq!(copied_res, data)
+    }
+
+    #[test]
+    fn test_read_at_snapshot() {
+        let database = Db::default();
+        let database = database.as_ref();
+
+        let _ = database.write("/tweets/0", &[0x00]).unwrap();
+        database.snapshot(1).unwrap();
+        let _ = database.write("/tweets/1", &[0x01]).unwrap();
+        let _ = database.delete("/tweets/0").unwrap();
+
+        assert_eq!(database.get_subkeys("/tweets").unwrap(), vec!["1"]);
+        let node = database.read_node_at(1, "/tweets").unwrap().unwrap();
+        assert_eq!(node.children(), vec!["0"]);
+        assert_eq!(database.read_at(1, "/tweets/0").unwrap(), Some(vec![0x00]));
+
+        // The first change from the level is the one read
+        database.snapshot(3).unwrap();
+        let _ = database.write("/tweets/0", &[0x03]).unwrap();
+        assert_eq!(database.read_at(1, "/tweets/0").unwrap(), Some(vec![0x00]));
+        assert_eq!(database.read_at(3, "/tweets/0").unwrap(), None);
+        let node = database.read_node_at(1, "/tweets").unwrap().unwrap();
+        assert_eq!(node.children(), vec!["0"]);
+        let node = database.read_node_at(3, "/tweets").unwrap().unwrap();
+        assert_eq!(node.children(), vec!["1"]);
+
+        database.revert(1).unwrap();
+        assert_eq!(database.get_subkeys("/tweets").unwrap(), vec!["0"]);
+        assert!(database.read("/tweets/1").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_outbox() {
+        let database = Db::default();
+        let database = database.as_ref();
+
+        database.write_output(3, &[0x01]).unwrap();
+        database.snapshot(4).unwrap();
+        database.write_output(4, &[0x02]).unwrap();
+        database.write_output(4, &[0x03]).unwrap();
+
+        assert_eq!(database.read_outbox(3).unwrap(), vec![vec![0x01]]);
+        assert_eq!(
+            database.read_outbox(4).unwrap(),
+            vec![vec![0x02], vec![0x03]]
+        );
+        // The outbox is not visible from the state
+        assert!(database.get_subkeys("/").unwrap().is_empty());
+
+        database.revert(4).unwrap();
+        assert!(database.read_outbox(4).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,565 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::native_runtime::ROLLUP_ADDRESS_PREFIX;
+use crate::core::{
+    end_of_level, external_message, info_per_level, start_of_level, transfer, ReadInbox,
+    ReadInboxError, TezosHeader,
+};
+
+/// Prefix of the b58check encoding of the contract hashes (KT1...)
+const CONTRACT_HASH_PREFIX: [u8; 3] = [2, 90, 121];
+
+/// Prefixes of the b58check encoding of the public key hashes, in the order of their tag
+const PUBLIC_KEY_HASH_PREFIXES: [[u8; 3]; 4] = [
+    [6, 161, 159], // tz1
+    [6, 161, 161], // tz2
+    [6, 161, 164], // tz3
+    [6, 161, 166], // tz4
+];
+
+// Tags of the binary encoding of Micheline
+const INT_TAG: u8 = 0x00;
+const STRING_TAG: u8 = 0x01;
+const SEQ_TAG: u8 = 0x02;
+const PRIM_TAG: u8 = 0x03;
+const PRIM_WITH_ARGS_TAG: u8 = 0x09;
+const BYTES_TAG: u8 = 0x0a;
+
+/// Michelson primitives, in the order of their code
+const PRIMITIVES: [&str; 157] = [
+    "parameter",
+    "storage",
+    "code",
+    "False",
+    "Elt",
+    "Left",
+    "None",
+    "Pair",
+    "Right",
+    "Some",
+    "True",
+    "Unit",
+    "PACK",
+    "UNPACK",
+    "BLAKE2B",
+    "SHA256",
+    "SHA512",
+    "ABS",
+    "ADD",
+    "AMOUNT",
+    "AND",
+    "BALANCE",
+    "CAR",
+    "CDR",
+    "CHECK_SIGNATURE",
+    "COMPARE",
+    "CONCAT",
+    "CONS",
+    "CREATE_ACCOUNT",
+    "CREATE_CONTRACT",
+    "IMPLICIT_ACCOUNT",
+    "DIP",
+    "DROP",
+    "DUP",
+    "EDIV",
+    "EMPTY_MAP",
+    "EMPTY_SET",
+    "EQ",
+    "EXEC",
+    "FAILWITH",
+    "GE",
+    "GET",
+    "GT",
+    "HASH_KEY",
+    "IF",
+    "IF_CONS",
+    "IF_LEFT",
+    "IF_NONE",
+    "INT",
+    "LAMBDA",
+    "LE",
+    "LEFT",
+    "LOOP",
+    "LSL",
+    "LSR",
+    "LT",
+    "MAP",
+    "MEM",
+    "MUL",
+    "NEG",
+    "NEQ",
+    "NIL",
+    "NONE",
+    "NOT",
+    "NOW",
+    "OR",
+    "PAIR",
+    "PUSH",
+    "RIGHT",
+    "SIZE",
+    "SOME",
+    "SOURCE",
+    "SENDER",
+    "SELF",
+    "STEPS_TO_QUOTA",
+    "SUB",
+    "SWAP",
+    "TRANSFER_TOKENS",
+    "SET_DELEGATE",
+    "UNIT",
+    "UPDATE",
+    "XOR",
+    "ITER",
+    "LOOP_LEFT",
+    "ADDRESS",
+    "CONTRACT",
+    "ISNAT",
+    "CAST",
+    "RENAME",
+    "bool",
+    "contract",
+    "int",
+    "key",
+    "key_hash",
+    "lambda",
+    "list",
+    "map",
+    "big_map",
+    "nat",
+    "option",
+    "or",
+    "pair",
+    "set",
+    "signature",
+    "string",
+    "bytes",
+    "mutez",
+    "timestamp",
+    "unit",
+    "operation",
+    "address",
+    "SLICE",
+    "DIG",
+    "DUG",
+    "EMPTY_BIG_MAP",
+    "APPLY",
+    "chain_id",
+    "CHAIN_ID",
+    "LEVEL",
+    "SELF_ADDRESS",
+    "never",
+    "NEVER",
+    "UNPAIR",
+    "VOTING_POWER",
+    "TOTAL_VOTING_POWER",
+    "KECCAK",
+    "SHA3",
+    "PAIRING_CHECK",
+    "bls12_381_g1",
+    "bls12_381_g2",
+    "bls12_381_fr",
+    "sapling_state",
+    "sapling_transaction_deprecated",
+    "SAPLING_EMPTY_STATE",
+    "SAPLING_VERIFY_UPDATE",
+    "ticket",
+    "TICKET_DEPRECATED",
+    "READ_TICKET",
+    "SPLIT_TICKET",
+    "JOIN_TICKETS",
+    "GET_AND_UPDATE",
+    "chest",
+    "chest_key",
+    "OPEN_CHEST",
+    "VIEW",
+    "view",
+    "constant",
+    "SUB_MUTEZ",
+    "tx_rollup_l2_address",
+    "MIN_BLOCK_TIME",
+    "sapling_transaction",
+    "EMIT",
+    "Lambda_rec",
+    "LAMBDA_REC",
+    "TICKET",
+    "BYTES",
+    "NAT",
+];
+
+#[derive(Deserialize)]
+struct OperationResult {
+    status: String,
+}
+
+#[derive(Deserialize)]
+struct Parameters {
+    value: Value,
+}
+
+#[derive(Deserialize)]
+struct InternalOperation {
+    kind: String,
+    source: String,
+    destination: Option<String>,
+    parameters: Option<Parameters>,
+    result: OperationResult,
+}
+
+#[derive(Deserialize)]
+struct Metadata {
+    operation_result: OperationResult,
+    #[serde(default)]
+    internal_operation_results: Vec<InternalOperation>,
+}
+
+#[derive(Deserialize)]
+struct Content {
+    kind: String,
+    source: Option<String>,
+    #[serde(default)]
+    message: Vec<String>,
+    metadata: Option<Metadata>,
+}
+
+#[derive(Deserialize)]
+struct Operation {
+    contents: Vec<Content>,
+}
+
+/// Reads the inbox from the blocks of a tezos node
+///
+/// The inbox is made of the internal messages of the level,
+/// of the external messages of the applied smart_rollup_add_messages operations
+/// and of the transfers of the contracts to the rollups.
+pub struct TezosInboxReader {
+    url: String,
+    client: Client,
+}
+
+impl TezosInboxReader {
+    pub fn new(url: String) -> Self {
+        Self {
+            url,
+            client: Client::new(),
+        }
+    }
+
+    async fn get<T: for<'de> Deserialize<'de>>(&self, path: &str) -> Result<T, ()> {
+        let bytes = self
+            .client
+            .get(format!("{}{}", self.url, path))
+            .send()
+            .await
+            .map_err(|_| ())?
+            .bytes()
+            .await
+            .map_err(|_| ())?;
+        serde_json::from_slice(&bytes).map_err(|_| ())
+    }
+}
+
+/// Decodes a b58check encoded hash with the given prefix
+fn decode_hash(hash: &str, prefix: &[u8]) -> Result<Vec<u8>, ()> {
+    let bytes = bs58::decode(hash)
+        .with_check(None)
+        .into_vec()
+        .map_err(|_| ())?;
+    bytes
+        .strip_prefix(prefix)
+        .map(|hash| hash.to_vec())
+        .ok_or(())
+}
+
+/// Decodes a public key hash, prefixed by the tag of its curve
+fn decode_public_key_hash(hash: &str) -> Result<Vec<u8>, ()> {
+    for (tag, prefix) in PUBLIC_KEY_HASH_PREFIXES.iter().enumerate() {
+        if let Ok(hash) = decode_hash(hash, prefix) {
+            let mut bytes = vec![tag as u8];
+            bytes.extend_from_slice(&hash);
+            return Ok(bytes);
+        }
+    }
+    Err(())
+}
+
+/// Writes bytes prefixed by their size on 4 bytes
+fn write_dynamic(
+    out: &mut Vec<u8>,
+    write: impl FnOnce(&mut Vec<u8>) -> Result<(), ()>,
+) -> Result<(), ()> {
+    let start = out.len();
+    out.extend_from_slice(&[0; 4]);
+    write(out)?;
+    let size = u32::try_from(out.len() - start - 4).map_err(|_| ())?;
+    out[start..start + 4].copy_from_slice(&size.to_be_bytes());
+    Ok(())
+}
+
+/// Divides a decimal number, given by its digits, in place
+///
+/// Returns the remainder of the division
+fn divide_decimal(digits: &mut [u8], divisor: u32) -> u8 {
+    let mut remainder = 0;
+    for digit in digits.iter_mut() {
+        let value = remainder * 10 + *digit as u32;
+        *digit = (value / divisor) as u8;
+        remainder = value % divisor;
+    }
+    remainder as u8
+}
+
+/// Writes a signed integer in the zarith encoding
+///
+/// The first byte holds the sign and 6 bits, the next ones 7 bits
+/// The Michelson integers are unbounded, they are converted from their decimal digits
+fn write_zarith(int: &str, out: &mut Vec<u8>) -> Result<(), ()> {
+    let (sign, abs) = match int.strip_prefix('-') {
+        Some(abs) => (0x40, abs),
+        None => (0x00, int),
+    };
+    if abs.is_empty() || !abs.bytes().all(|digit| digit.is_ascii_digit()) {
+        return Err(());
+    }
+    let mut digits: Vec<u8> = abs.bytes().map(|digit| digit - b'0').collect();
+
+    let mut byte = divide_decimal(&mut digits, 1 << 6) | sign;
+    while digits.iter().any(|digit| *digit != 0) {
+        out.push(byte | 0x80);
+        byte = divide_decimal(&mut digits, 1 << 7);
+    }
+    out.push(byte);
+    Ok(())
+}
+
+/// Writes the binary encoding of a Micheline expression given in JSON
+fn write_micheline(expr: &Value, out: &mut Vec<u8>) -> Result<(), ()> {
+    if let Value::Array(items) = expr {
+        out.push(SEQ_TAG);
+        return write_dynamic(out, |out| {
+            items.iter().try_for_each(|item| write_micheline(item, out))
+        });
+    }
+
+    let object = expr.as_object().ok_or(())?;
+    if let Some(int) = object.get("int") {
+        out.push(INT_TAG);
+        return write_zarith(int.as_str().ok_or(())?, out);
+    }
+    if let Some(string) = object.get("string") {
+        let string = string.as_str().ok_or(())?;
+        out.push(STRING_TAG);
+        return write_dynamic(out, |out| {
+            out.extend_from_slice(string.as_bytes());
+            Ok(())
+        });
+    }
+    if let Some(bytes) = object.get("bytes") {
+        let bytes = hex::decode(bytes.as_str().ok_or(())?).map_err(|_| ())?;
+        out.push(BYTES_TAG);
+        return write_dynamic(out, |out| {
+            out.extend_from_slice(&bytes);
+            Ok(())
+        });
+    }
+
+    let prim = object.get("prim").and_then(Value::as_str).ok_or(())?;
+    let code = PRIMITIVES.iter().position(|name| *name == prim).ok_or(())?;
+    let args = match object.get("args") {
+        Some(args) => args.as_array().ok_or(())?.as_slice(),
+        None => &[],
+    };
+    let annots = match object.get("annots") {
+        Some(annots) => annots
+            .as_array()
+            .ok_or(())?
+            .iter()
+            .map(|annot| annot.as_str().ok_or(()))
+            .collect::<Result<Vec<&str>, ()>>()?,
+        None => Vec::default(),
+    };
+
+    // The primitives with at most 2 arguments have their own tags, with and without annotations
+    if args.len() <= 2 {
+        out.push(PRIM_TAG + 2 * args.len() as u8 + u8::from(!annots.is_empty()));
+        out.push(code as u8);
+        for arg in args {
+            write_micheline(arg, out)?;
+        }
+    } else {
+        out.push(PRIM_WITH_ARGS_TAG);
+        out.push(code as u8);
+        write_dynamic(out, |out| {
+            args.iter().try_for_each(|arg| write_micheline(arg, out))
+        })?;
+    }
+    if !annots.is_empty() || args.len() > 2 {
+        write_dynamic(out, |out| {
+            out.extend_from_slice(annots.join(" ").as_bytes());
+            Ok(())
+        })?;
+    }
+    Ok(())
+}
+
+/// Payload of the transfer message of an internal operation, if it is a transfer to a rollup
+///
+/// The source of the message is the account of the manager operation,
+/// the sender is the contract emitting the internal operation.
+fn transfer_message(source: &str, operation: &InternalOperation) -> Result<Option<Vec<u8>>, ()> {
+    let destination = match &operation.destination {
+        Some(destination) if destination.starts_with("sr1") => destination,
+        _ => return Ok(None),
+    };
+    if operation.kind != "transaction" || operation.result.status != "applied" {
+        return Ok(None);
+    }
+
+    let mut payload = Vec::default();
+    match &operation.parameters {
+        Some(parameters) => write_micheline(&parameters.value, &mut payload)?,
+        None => write_micheline(&serde_json::json!({ "prim": "Unit" }), &mut payload)?,
+    }
+    let sender = decode_hash(&operation.source, &CONTRACT_HASH_PREFIX)?;
+    let source = decode_public_key_hash(source)?;
+    let destination = decode_hash(destination, &ROLLUP_ADDRESS_PREFIX)?;
+    Ok(Some(transfer(&payload, &sender, &source, &destination)))
+}
+
+/// Builds the inbox of a level from its manager operations
+///
+/// The messages are added in the order of the operations
+fn inbox(predecessor: &TezosHeader, operations: &[Operation]) -> Result<Vec<Vec<u8>>, ()> {
+    let mut inbox = vec![start_of_level(), info_per_level(predecessor)];
+    for content in operations.iter().flat_map(|operation| &operation.contents) {
+        let metadata = match &content.metadata {
+            Some(metadata) if metadata.operation_result.status == "applied" => metadata,
+            _ => continue,
+        };
+        if content.kind == "smart_rollup_add_messages" {
+            for message in &content.message {
+                let operation = hex::decode(message).map_err(|_| ())?;
+                inbox.push(external_message(&operation));
+            }
+        }
+        for operation in &metadata.internal_operation_results {
+            let source = content.source.as_deref().ok_or(())?;
+            if let Some(transfer) = transfer_message(source, operation)? {
+                inbox.push(transfer);
+            }
+        }
+    }
+    inbox.push(end_of_level());
+
+    Ok(inbox)
+}
+
+#[async_trait]
+impl ReadInbox for TezosInboxReader {
+    async fn read_inbox(&self, level: u32) -> Result<Vec<Vec<u8>>, ReadInboxError> {
+        let predecessor_level = level.checked_sub(1).ok_or(ReadInboxError::Undecodable)?;
+        let predecessor = self
+            .get::<TezosHeader>(&format!("/chains/main/blocks/{}/header", predecessor_level))
+            .await
+            .map_err(|_| ReadInboxError::Unavailable)?;
+        let operations = self
+            .get::<Vec<Operation>>(&format!("/chains/main/blocks/{}/operations/3", level))
+            .await
+            .map_err(|_| ReadInboxError::Unavailable)?;
+
+        inbox(&predecessor, &operations).map_err(|_| ReadInboxError::Undecodable)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{inbox, write_zarith, Operation};
+    use crate::core::{
+        end_of_level, external_message, info_per_level, start_of_level, TezosHeader,
+    };
+
+    #[test]
+    fn test_transfer() {
+        let predecessor = TezosHeader {
+            hash: "BLockGenesisGenesisGenesisGenesisGenesisf79b5d1CoW2".to_string(),
+            level: 3,
+            predecessor: "BLockGenesisGenesisGenesisGenesisGenesisf79b5d1CoW2".to_string(),
+            timestamp: "2023-04-20T10:00:00Z".parse().unwrap(),
+        };
+        let operations: Vec<Operation> = serde_json::from_str(
+            r#"[
+              { "contents": [
+                { "kind": "smart_rollup_add_messages",
+                  "source": "tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv",
+                  "message": [ "0102" ],
+                  "metadata": { "operation_result": { "status": "applied" } } } ] },
+              { "contents": [
+                { "kind": "transaction",
+                  "source": "tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv",
+                  "destination": "KT1RycYvM4EVs6BAXWEsGXaAaRqiMP53KT4w",
+                  "metadata": {
+                    "operation_result": { "status": "applied" },
+                    "internal_operation_results": [
+                      { "kind": "transaction",
+                        "source": "KT1RycYvM4EVs6BAXWEsGXaAaRqiMP53KT4w",
+                        "destination": "sr1RYurGZtN8KNSpkMcCt9CgWeUaNkzsAfXf",
+                        "parameters": {
+                          "entrypoint": "default",
+                          "value": { "prim": "Pair", "args": [ { "int": "-65" }, { "bytes": "0a" } ] } },
+                        "result": { "status": "applied" } } ] } } ] }
+            ]"#,
+        )
+        .unwrap();
+
+        let transfer = [
+            "0000",
+            // Pair (-65) 0x0a
+            "0707",
+            "00c101",
+            "0a000000010a",
+            // KT1RycYvM4EVs6BAXWEsGXaAaRqiMP53KT4w
+            "becc2e32eca0e6fe70de0e3fb884e8f6aefffa3d",
+            // tz1QFD9WqLWZmmAuqnnTPPUjfauitYEWdshv
+            "003287ca0e2768be954c0142783bad9ae1b3dae200",
+            // sr1RYurGZtN8KNSpkMcCt9CgWeUaNkzsAfXf
+            "d601f22256d2ad1faec0c64374e527c6e62f2e5a",
+        ]
+        .concat();
+
+        assert_eq!(
+            inbox(&predecessor, &operations).unwrap(),
+            vec![
+                start_of_level(),
+                info_per_level(&predecessor),
+                external_message(&[0x01, 0x02]),
+                hex::decode(transfer).unwrap(),
+                end_of_level(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_zarith() {
+        let zarith = |int: &str| {
+            let mut out = Vec::default();
+            write_zarith(int, &mut out).map(|_| hex::encode(out))
+        };
+
+        assert_eq!(zarith("0"), Ok("00".to_string()));
+        assert_eq!(zarith("64"), Ok("8001".to_string()));
+        assert_eq!(zarith("-65"), Ok("c101".to_string()));
+        // The Michelson integers are not bounded by the integers of rust
+        assert_eq!(
+            zarith("340282366920938463463374607431768211456"),
+            Ok("80808080808080808080808080808080808008".to_string())
+        );
+        assert_eq!(
+            zarith("-1267650600228229401496703205383"),
+            Ok("c78080808080808080808080808008".to_string())
+        );
+        assert_eq!(zarith("1e3"), Err(()));
+        assert_eq!(zarith("-"), Err(()));
+    }
+}
@@ -0,0 +1,731 @@
+use std::sync::Mutex;
+
+use tezos_smart_rollup_core::{MAX_FILE_CHUNK_SIZE, PREIMAGE_HASH_SIZE};
+use tezos_smart_rollup_host::{
+    input::Message,
+    metadata::RollupMetadata,
+    path::{OwnedPath, RefPath},
+    runtime::{Runtime, RuntimeError, ValueType},
+    Error,
+};
+use wasmtime::{Caller, Config, Engine, Linker, Memory, Module, Store, Trap};
+
+use super::native_runtime::{add_ticks, ticks_left, uncharged};
+use crate::core::Kernel;
+
+/// Path of the kernel in the durable storage
+pub const KERNEL_PATH: &str = "/kernel/boot.wasm";
+
+const KERNEL: RefPath = RefPath::assert_from(KERNEL_PATH.as_bytes());
+
+/// Flag written by the kernel to request a reboot
+const REBOOT: RefPath = RefPath::assert_from(b"/kernel/env/reboot");
+
+/// Module of the host functions imported by the kernels
+const HOST_MODULE: &str = "smart_rollup_core";
+
+/// Maximum size of a revealed preimage, the size of a page of the PVM
+const REVEAL_PAGE_SIZE: usize = 4096;
+
+/// Ticks of a unit of fuel, wasmtime consumes about a unit per instruction
+const TICKS_PER_FUEL: u64 = 1;
+
+// Error codes of the host functions
+const STORE_INVALID_KEY: i32 = -2;
+const STORE_NOT_A_VALUE: i32 = -3;
+const STORE_INVALID_ACCESS: i32 = -4;
+const STORE_VALUE_SIZE_EXCEEDED: i32 = -5;
+const MEMORY_INVALID_ACCESS: i32 = -6;
+const INPUT_OUTPUT_TOO_LARGE: i32 = -7;
+const GENERIC_INVALID_ACCESS: i32 = -8;
+const STORE_NOT_A_NODE: i32 = -10;
+const FULL_OUTBOX: i32 = -11;
+
+/// Kernel executing the WASM binary stored at /kernel/boot.wasm
+///
+/// The host functions of the binary are forwarded to the runtime,
+/// so the sequencer runs the kernel deployed on the rollup, and its upgrades.
+pub struct WasmKernel {}
+
+/// Kernel compiled by the previous run
+///
+/// It is keyed by its binary: whatever wrote the storage (an upgrade, a revert, another runtime),
+/// the kernel is compiled again as soon as the binary in the storage is a different one
+struct Compiled {
+    engine: Engine,
+    wasm: Vec<u8>,
+    module: Module,
+}
+
+static COMPILED: Mutex<Option<Compiled>> = Mutex::new(None);
+
+/// Object safe view of the runtime, called by the host functions
+trait WasmHost {
+    fn read_input(&mut self) -> Result<Option<Message>, RuntimeError>;
+    fn write_output(&mut self, from: &[u8]) -> Result<(), RuntimeError>;
+    fn write_debug(&self, msg: &str);
+    fn store_has(&self, path: &OwnedPath) -> Result<Option<ValueType>, RuntimeError>;
+    fn store_read(
+        &self,
+        path: &OwnedPath,
+        from_offset: usize,
+        max_bytes: usize,
+    ) -> Result<Vec<u8>, RuntimeError>;
+    fn store_write(
+        &mut self,
+        path: &OwnedPath,
+        src: &[u8],
+        at_offset: usize,
+    ) -> Result<(), RuntimeError>;
+    fn store_delete(&mut self, path: &OwnedPath) -> Result<(), RuntimeError>;
+    fn store_count_subkeys(&self, path: &OwnedPath) -> Result<u64, RuntimeError>;
+    fn store_move(&mut self, from: &OwnedPath, to: &OwnedPath) -> Result<(), RuntimeError>;
+    fn store_copy(&mut self, from: &OwnedPath, to: &OwnedPath) -> Result<(), RuntimeError>;
+    fn reveal_preimage(
+        &self,
+        hash: &[u8; PREIMAGE_HASH_SIZE],
+        destination: &mut [u8],
+    ) -> Result<usize, RuntimeError>;
+    fn store_value_size(&self, path: &OwnedPath) -> Result<usize, RuntimeError>;
+    fn reveal_metadata(&self) -> Result<RollupMetadata, RuntimeError>;
+}
+
+impl<R: Runtime> WasmHost for R {
+    fn read_input(&mut self) -> Result<Option<Message>, RuntimeError> {
+        Runtime::read_input(self)
+    }
+
+    fn write_output(&mut self, from: &[u8]) -> Result<(), RuntimeError> {
+        Runtime::write_output(self, from)
+    }
+
+    fn write_debug(&self, msg: &str) {
+        Runtime::write_debug(self, msg)
+    }
+
+    fn store_has(&self, path: &OwnedPath) -> Result<Option<ValueType>, RuntimeError> {
+        Runtime::store_has(self, path)
+    }
+
+    fn store_read(
+        &self,
+        path: &OwnedPath,
+        from_offset: usize,
+        max_bytes: usize,
+    ) -> Result<Vec<u8>, RuntimeError> {
+        Runtime::store_read(self, path, from_offset, max_bytes)
+    }
+
+    fn store_write(
+        &mut self,
+        path: &OwnedPath,
+        src: &[u8],
+        at_offset: usize,
+    ) -> Result<(), RuntimeError> {
+        Runtime::store_write(self, path, src, at_offset)
+    }
+
+    fn store_delete(&mut self, path: &OwnedPath) -> Result<(), RuntimeError> {
+        Runtime::store_delete(self, path)
+    }
+
+    fn store_count_subkeys(&self, path: &OwnedPath) -> Result<u64, RuntimeError> {
+        Runtime::store_count_subkeys(self, path)
+    }
+
+    fn store_move(&mut self, from: &OwnedPath, to: &OwnedPath) -> Result<(), RuntimeError> {
+        Runtime::store_move(self, from, to)
+    }
+
+    fn store_copy(&mut self, from: &OwnedPath, to: &OwnedPath) -> Result<(), RuntimeError> {
+        Runtime::store_copy(self, from, to)
+    }
+
+    fn reveal_preimage(
+        &self,
+        hash: &[u8; PREIMAGE_HASH_SIZE],
+        destination: &mut [u8],
+    ) -> Result<usize, RuntimeError> {
+        Runtime::reveal_preimage(self, hash, destination)
+    }
+
+    fn store_value_size(&self, path: &OwnedPath) -> Result<usize, RuntimeError> {
+        Runtime::store_value_size(self, path)
+    }
+
+    fn reveal_metadata(&self) -> Result<RollupMetadata, RuntimeError> {
+        Runtime::reveal_metadata(self)
+    }
+}
+
+/// Data of the wasm store
+///
+/// The runtime is only borrowed for the run of the kernel,
+/// the store is dropped before the end of the borrow.
+struct State {
+    host: *mut dyn WasmHost,
+    // Ticks left after the last host call which has been charged in fuel
+    ticks_left: u64,
+    // Fuel consumed by the host calls, the rest is consumed by the instructions
+    host_fuel: u64,
+}
+
+/// Converts the error of the runtime to the code returned by a host function
+fn error_code(error: RuntimeError) -> i32 {
+    match error {
+        RuntimeError::PathNotFound => STORE_NOT_A_VALUE,
+        RuntimeError::HostErr(error) => match error {
+            Error::StoreInvalidKey => STORE_INVALID_KEY,
+            Error::StoreNotAValue => STORE_NOT_A_VALUE,
+            Error::StoreInvalidAccess => STORE_INVALID_ACCESS,
+            Error::StoreValueSizeExceeded => STORE_VALUE_SIZE_EXCEEDED,
+            Error::InputOutputTooLarge => INPUT_OUTPUT_TOO_LARGE,
+            Error::StoreNotANode => STORE_NOT_A_NODE,
+            Error::FullOutbox => FULL_OUTBOX,
+            _ => GENERIC_INVALID_ACCESS,
+        },
+        _ => GENERIC_INVALID_ACCESS,
+    }
+}
+
+/// Charges the ticks of the previous host calls in fuel
+///
+/// If the fuel is not enough, it is exhausted and the next instruction traps
+fn charge_host_calls(caller: &mut Caller<'_, State>) {
+    let ticks_left = ticks_left();
+    let fuel = caller.data().ticks_left.saturating_sub(ticks_left) / TICKS_PER_FUEL;
+    let fuel_left = caller.consume_fuel(0).unwrap_or(0);
+    let fuel = fuel.min(fuel_left);
+    let _ = caller.consume_fuel(fuel);
+
+    let state = caller.data_mut();
+    state.ticks_left = ticks_left;
+    state.host_fuel += fuel;
+}
+
+fn host<'a>(caller: &'a mut Caller<'_, State>) -> &'a mut dyn WasmHost {
+    charge_host_calls(caller);
+    // Safety: the pointer is valid while the store exists, see State
+    unsafe { &mut *caller.data().host }
+}
+
+fn memory(caller: &mut Caller<'_, State>) -> Result<Memory, i32> {
+    caller
+        .get_export("memory")
+        .and_then(|export| export.into_memory())
+        .ok_or(MEMORY_INVALID_ACCESS)
+}
+
+fn read_memory(caller: &mut Caller<'_, State>, ptr: i32, len: i32) -> Result<Vec<u8>, i32> {
+    let memory = memory(caller)?;
+    let start = ptr as u32 as usize;
+    let end = start + len as u32 as usize;
+    memory
+        .data(&*caller)
+        .get(start..end)
+        .map(|bytes| bytes.to_vec())
+        .ok_or(MEMORY_INVALID_ACCESS)
+}
+
+fn write_memory(caller: &mut Caller<'_, State>, ptr: i32, bytes: &[u8]) -> Result<(), i32> {
+    let memory = memory(caller)?;
+    memory
+        .write(&mut *caller, ptr as u32 as usize, bytes)
+        .map_err(|_| MEMORY_INVALID_ACCESS)
+}
+
+fn read_path(caller: &mut Caller<'_, State>, ptr: i32, len: i32) -> Result<OwnedPath, i32> {
+    let bytes = read_memory(caller, ptr, len)?;
+    OwnedPath::try_from(bytes).map_err(|_| STORE_INVALID_KEY)
+}
+
+/// Returns the result of a host function, or its error code
+fn result(res: Result<i32, i32>) -> i32 {
+    res.unwrap_or_else(|code| code)
+}
+
+/// Writes the level and the id of the message at info, and its payload at dst
+fn read_input(caller: &mut Caller<'_, State>, info: i32, dst: i32, max: i32) -> Result<i32, i32> {
+    let message = match host(caller).read_input().map_err(error_code)? {
+        Some(message) => message,
+        None => return Ok(0),
+    };
+    let mut message_info = message.level.to_le_bytes().to_vec();
+    message_info.extend_from_slice(&message.id.to_le_bytes());
+    write_memory(caller, info, &message_info)?;
+
+    let payload = message.as_ref();
+    let size = payload.len().min(max as u32 as usize);
+    write_memory(caller, dst, &payload[..size])?;
+    Ok(size as i32)
+}
+
+fn write_output(caller: &mut Caller<'_, State>, src: i32, len: i32) -> Result<i32, i32> {
+    let output = read_memory(caller, src, len)?;
+    host(caller).write_output(&output).map_err(error_code)?;
+    Ok(0)
+}
+
+fn write_debug(caller: &mut Caller<'_, State>, src: i32, len: i32) {
+    if let Ok(msg) = read_memory(caller, src, len) {
+        host(caller).write_debug(&String::from_utf8_lossy(&msg));
+    }
+}
+
+fn store_has(caller: &mut Caller<'_, State>, path: i32, len: i32) -> Result<i32, i32> {
+    let path = read_path(caller, path, len)?;
+    let value_type = host(caller).store_has(&path).map_err(error_code)?;
+    Ok(match value_type {
+        None => 0,
+        Some(ValueType::Value) => 1,
+        Some(ValueType::Subtree) => 2,
+        Some(ValueType::ValueWithSubtree) => 3,
+    })
+}
+
+fn store_read(
+    caller: &mut Caller<'_, State>,
+    path: i32,
+    len: i32,
+    offset: i32,
+    dst: i32,
+    max: i32,
+) -> Result<i32, i32> {
+    let path = read_path(caller, path, len)?;
+    let bytes = host(caller)
+        .store_read(&path, offset as u32 as usize, max as u32 as usize)
+        .map_err(error_code)?;
+    write_memory(caller, dst, &bytes)?;
+    Ok(bytes.len() as i32)
+}
+
+fn store_write(
+    caller: &mut Caller<'_, State>,
+    path: i32,
+    len: i32,
+    offset: i32,
+    src: i32,
+    size: i32,
+) -> Result<i32, i32> {
+    let path = read_path(caller, path, len)?;
+    let bytes = read_memory(caller, src, size)?;
+    host(caller)
+        .store_write(&path, &bytes, offset as u32 as usize)
+        .map_err(error_code)?;
+    Ok(0)
+}
+
+fn store_delete(caller: &mut Caller<'_, State>, path: i32, len: i32) -> Result<i32, i32> {
+    let path = read_path(caller, path, len)?;
+    host(caller).store_delete(&path).map_err(error_code)?;
+    Ok(0)
+}
+
+fn store_list_size(caller: &mut Caller<'_, State>, path: i32, len: i32) -> Result<i64, i32> {
+    let path = read_path(caller, path, len)?;
+    let size = host(caller)
+        .store_count_subkeys(&path)
+        .map_err(error_code)?;
+    Ok(size as i64)
+}
+
+fn store_move(
+    caller: &mut Caller<'_, State>,
+    from: i32,
+    from_len: i32,
+    to: i32,
+    to_len: i32,
+) -> Result<i32, i32> {
+    let from = read_path(caller, from, from_len)?;
+    let to = read_path(caller, to, to_len)?;
+    host(caller).store_move(&from, &to).map_err(error_code)?;
+    Ok(0)
+}
+
+fn store_copy(
+    caller: &mut Caller<'_, State>,
+    from: i32,
+    from_len: i32,
+    to: i32,
+    to_len: i32,
+) -> Result<i32, i32> {
+    let from = read_path(caller, from, from_len)?;
+    let to = read_path(caller, to, to_len)?;
+    host(caller).store_copy(&from, &to).map_err(error_code)?;
+    Ok(0)
+}
+
+fn reveal_preimage(
+    caller: &mut Caller<'_, State>,
+    hash: i32,
+    hash_len: i32,
+    dst: i32,
+    max: i32,
+) -> Result<i32, i32> {
+    let hash: [u8; PREIMAGE_HASH_SIZE] = read_memory(caller, hash, hash_len)?
+        .try_into()
+        .map_err(|_| GENERIC_INVALID_ACCESS)?;
+    // The buffer is bounded by the page size, whatever the size requested by the kernel
+    let mut preimage = [0; REVEAL_PAGE_SIZE];
+    let max = (max as u32 as usize).min(REVEAL_PAGE_SIZE);
+    let size = host(caller)
+        .reveal_preimage(&hash, &mut preimage[..max])
+        .map_err(error_code)?;
+    write_memory(caller, dst, &preimage[..size])?;
+    Ok(size as i32)
+}
+
+fn store_value_size(caller: &mut Caller<'_, State>, path: i32, len: i32) -> Result<i32, i32> {
+    let path = read_path(caller, path, len)?;
+    let size = host(caller).store_value_size(&path).map_err(error_code)?;
+    Ok(size as i32)
+}
+
+/// Writes the address of the rollup followed by its origination level
+fn reveal_metadata(caller: &mut Caller<'_, State>, dst: i32, max: i32) -> Result<i32, i32> {
+    let metadata = host(caller).reveal_metadata().map_err(error_code)?;
+    let mut bytes = metadata.raw_rollup_address.to_vec();
+    bytes.extend_from_slice(&metadata.origination_level.to_be_bytes());
+    let size = bytes.len().min(max as u32 as usize);
+    write_memory(caller, dst, &bytes[..size])?;
+    Ok(size as i32)
+}
+
+/// Defines the host functions of the smart rollups
+fn link(linker: &mut Linker<State>) -> wasmtime::Result<()> {
+    linker.func_wrap(
+        HOST_MODULE,
+        "read_input",
+        |mut caller: Caller<'_, State>, info: i32, dst: i32, max: i32| {
+            result(read_input(&mut caller, info, dst, max))
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "write_output",
+        |mut caller: Caller<'_, State>, src: i32, len: i32| {
+            result(write_output(&mut caller, src, len))
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "write_debug",
+        |mut caller: Caller<'_, State>, src: i32, len: i32| write_debug(&mut caller, src, len),
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "store_has",
+        |mut caller: Caller<'_, State>, path: i32, len: i32| {
+            result(store_has(&mut caller, path, len))
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "store_read",
+        |mut caller: Caller<'_, State>, path: i32, len: i32, offset: i32, dst: i32, max: i32| {
+            result(store_read(&mut caller, path, len, offset, dst, max))
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "store_write",
+        |mut caller: Caller<'_, State>, path: i32, len: i32, offset: i32, src: i32, size: i32| {
+            result(store_write(&mut caller, path, len, offset, src, size))
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "store_delete",
+        |mut caller: Caller<'_, State>, path: i32, len: i32| {
+            result(store_delete(&mut caller, path, len))
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "store_list_size",
+        |mut caller: Caller<'_, State>, path: i32, len: i32| {
+            store_list_size(&mut caller, path, len).unwrap_or_else(|code| code as i64)
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "store_move",
+        |mut caller: Caller<'_, State>, from: i32, from_len: i32, to: i32, to_len: i32| {
+            result(store_move(&mut caller, from, from_len, to, to_len))
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "store_copy",
+        |mut caller: Caller<'_, State>, from: i32, from_len: i32, to: i32, to_len: i32| {
+            result(store_copy(&mut caller, from, from_len, to, to_len))
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "reveal_preimage",
+        |mut caller: Caller<'_, State>, hash: i32, hash_len: i32, dst: i32, max: i32| {
+            result(reveal_preimage(&mut caller, hash, hash_len, dst, max))
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "store_value_size",
+        |mut caller: Caller<'_, State>, path: i32, len: i32| {
+            result(store_value_size(&mut caller, path, len))
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "reveal_metadata",
+        |mut caller: Caller<'_, State>, dst: i32, max: i32| {
+            result(reveal_metadata(&mut caller, dst, max))
+        },
+    )?;
+    Ok(())
+}
+
+/// Reads the binary of the kernel, chunk by chunk
+fn read_kernel<R: Runtime>(host: &R) -> wasmtime::Result<Vec<u8>> {
+    let size = Runtime::store_value_size(host, &KERNEL)
+        .map_err(|_| wasmtime::Error::msg("There is no kernel in the storage"))?;
+
+    let mut wasm = Vec::with_capacity(size);
+    while wasm.len() < size {
+        let chunk = Runtime::store_read(host, &KERNEL, wasm.len(), MAX_FILE_CHUNK_SIZE)
+            .map_err(|_| wasmtime::Error::msg("Cannot read the kernel"))?;
+        if chunk.is_empty() {
+            break;
+        }
+        wasm.extend_from_slice(&chunk);
+    }
+    Ok(wasm)
+}
+
+/// Compiles the kernel, with the fuel metering its instructions
+fn compile(wasm: Vec<u8>) -> wasmtime::Result<Compiled> {
+    let mut config = Config::new();
+    config.consume_fuel(true);
+    let engine = Engine::new(&config)?;
+    let module = Module::new(&engine, &wasm)?;
+    Ok(Compiled {
+        engine,
+        wasm,
+        module,
+    })
+}
+
+/// Calls the kernel_run function of the kernel
+///
+/// Like the PVM, loading the kernel does not consume ticks.
+/// The instructions are metered by the fuel, the run traps when it exceeds the tick limit.
+/// The reboot flag written by the kernel is forwarded to the runtime
+fn run<R: Runtime>(host: &mut R) -> wasmtime::Result<()> {
+    let wasm = uncharged(|| read_kernel(host))?;
+
+    let mut cache = COMPILED
+        .lock()
+        .map_err(|_| wasmtime::Error::msg("The compiled kernel is poisoned"))?;
+    let compiled = match cache.take() {
+        Some(compiled) if compiled.wasm == wasm => compiled,
+        _ => compile(wasm)?,
+    };
+    let Compiled { engine, module, .. } = cache.insert(compiled);
+
+    {
+        let host: *mut (dyn WasmHost + '_) = &mut *host;
+        // Safety: the store does not outlive the borrow of the host
+        let host: *mut (dyn WasmHost + 'static) = unsafe { std::mem::transmute(host) };
+        let ticks_left = ticks_left();
+        let state = State {
+            host,
+            ticks_left,
+            host_fuel: 0,
+        };
+        let mut store = Store::new(engine, state);
+        // One more unit of fuel, so exhausting it exceeds the tick limit
+        store.add_fuel(ticks_left / TICKS_PER_FUEL + 1)?;
+        let mut linker = Linker::new(engine);
+        link(&mut linker)?;
+
+        let res = linker.instantiate(&mut store, module).and_then(|instance| {
+            let kernel_run = instance.get_typed_func::<(), ()>(&mut store, "kernel_run")?;
+            kernel_run.call(&mut store, ())
+        });
+
+        let fuel = store.fuel_consumed().unwrap_or(0);
+        let instructions_fuel = fuel.saturating_sub(store.data().host_fuel);
+        add_ticks(instructions_fuel.saturating_mul(TICKS_PER_FUEL));
+        if let Err(err) = res {
+            if matches!(err.downcast_ref::<Trap>(), Some(Trap::OutOfFuel)) {
+                return Err(wasmtime::Error::msg("The kernel exceeded the tick limit"));
+            }
+            return Err(err);
+        }
+    }
+
+    if let Ok(Some(_)) = Runtime::store_has(host, &REBOOT) {
+        let _ = Runtime::store_delete(host, &REBOOT);
+        let _ = Runtime::mark_for_reboot(host);
+    }
+    Ok(())
+}
+
+impl Kernel for WasmKernel {
+    fn entry<Host: Runtime>(host: &mut Host) {
+        if let Err(err) = run(host) {
+            println!("The wasm kernel failed: {}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tezos_smart_rollup_host::{input::Message, path::RefPath, runtime::Runtime};
+
+    use super::{WasmKernel, KERNEL_PATH};
+    use crate::{
+        core::{Database, Kernel},
+        implementations::{
+            native_runtime::{add_ticks, ticks_left, Host, NativeRuntime},
+            RollupConfig, SledDatabase,
+        },
+    };
+
+    const KERNEL: RefPath = RefPath::assert_from(KERNEL_PATH.as_bytes());
+
+    /// Kernel storing the payload of its first input under a path
+    fn kernel(path: &str) -> String {
+        format!(
+            r#"
+        (module
+          (import "smart_rollup_core" "read_input"
+            (func $read_input (param i32 i32 i32) (result i32)))
+          (import "smart_rollup_core" "store_write"
+            (func $store_write (param i32 i32 i32 i32 i32) (result i32)))
+          (memory (export "memory") 1)
+          (data (i32.const 0) "{path}")
+          (func (export "kernel_run")
+            (local $size i32)
+            (local.set $size (call $read_input (i32.const 16) (i32.const 32) (i32.const 4096)))
+            (drop (call $store_write (i32.const 0) (i32.const {len}) (i32.const 0) (i32.const 32) (local.get $size)))))
+    "#,
+            path = path,
+            len = path.len()
+        )
+    }
+
+    /// Kernel looping forever
+    const LOOP_KERNEL: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (func (export "kernel_run")
+            (loop $loop (br $loop))))
+    "#;
+
+    #[test]
+    fn test_wasm_kernel() {
+        let database = SledDatabase::new(&format!("/tmp/{}", uuid::Uuid::new_v4()));
+        let mut runtime = NativeRuntime::new(database.clone(), RollupConfig::default());
+        runtime
+            .store_write(&KERNEL, kernel("/input").as_bytes(), 0)
+            .unwrap();
+
+        runtime.add_message(Message::new(3, 0, vec![0x01, 0x02, 0x03]));
+        runtime.run::<WasmKernel>();
+
+        assert_eq!(
+            database.read("/input").unwrap(),
+            Some(vec![0x01, 0x02, 0x03])
+        );
+
+        // The kernel of the same size written by the runtime is compiled again
+        runtime
+            .store_write(&KERNEL, kernel("/value").as_bytes(), 0)
+            .unwrap();
+        runtime.add_message(Message::new(4, 0, vec![0x04]));
+        runtime.run::<WasmKernel>();
+
+        assert_eq!(database.read("/value").unwrap(), Some(vec![0x04]));
+    }
+
+    #[test]
+    fn test_kernel_written_without_runtime() {
+        let database = SledDatabase::new(&format!("/tmp/{}", uuid::Uuid::new_v4()));
+        let mut runtime = NativeRuntime::new(database.clone(), RollupConfig::default());
+        database
+            .write(KERNEL_PATH, kernel("/first").as_bytes())
+            .unwrap();
+        runtime.add_message(Message::new(3, 0, vec![0x01]));
+        runtime.run::<WasmKernel>();
+
+        // The kernel of the same size in another storage is not mistaken for the compiled one
+        let other = SledDatabase::new(&format!("/tmp/{}", uuid::Uuid::new_v4()));
+        let mut other_runtime = NativeRuntime::new(other.clone(), RollupConfig::default());
+        other
+            .write(KERNEL_PATH, kernel("/other").as_bytes())
+            .unwrap();
+        other_runtime.add_message(Message::new(3, 0, vec![0x02]));
+        other_runtime.run::<WasmKernel>();
+
+        assert_eq!(database.read("/first").unwrap(), Some(vec![0x01]));
+        assert_eq!(other.read("/other").unwrap(), Some(vec![0x02]));
+        assert_eq!(other.read("/first").unwrap(), None);
+    }
+
+    /// Kernel revealing a preimage with no limit, and storing the revealed size under /size
+    const REVEAL_KERNEL: &str = r#"
+        (module
+          (import "smart_rollup_core" "reveal_preimage"
+            (func $reveal_preimage (param i32 i32 i32 i32) (result i32)))
+          (import "smart_rollup_core" "store_write"
+            (func $store_write (param i32 i32 i32 i32 i32) (result i32)))
+          (memory (export "memory") 1)
+          (data (i32.const 0) "/size")
+          (data (i32.const 64) "\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01")
+          (func (export "kernel_run")
+            (i32.store (i32.const 32)
+              (call $reveal_preimage (i32.const 64) (i32.const 33) (i32.const 128) (i32.const -1)))
+            (drop (call $store_write (i32.const 0) (i32.const 5) (i32.const 0) (i32.const 32) (i32.const 4)))))
+    "#;
+
+    #[test]
+    fn test_reveal_page_size() {
+        let preimages_dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        std::fs::create_dir_all(&preimages_dir).unwrap();
+        let hash = [0x01; 33];
+        std::fs::write(preimages_dir.join(hex::encode(hash)), vec![0x07; 5000]).unwrap();
+
+        let database = SledDatabase::new(&format!("/tmp/{}", uuid::Uuid::new_v4()));
+        let config = RollupConfig {
+            preimages_dir,
+            ..RollupConfig::default()
+        };
+        let mut runtime = NativeRuntime::new(database.clone(), config);
+        runtime
+            .store_write(&KERNEL, REVEAL_KERNEL.as_bytes(), 0)
+            .unwrap();
+        WasmKernel::entry(&mut runtime);
+
+        // At most a page is revealed, whatever the size requested by the kernel
+        assert_eq!(
+            database.read("/size").unwrap(),
+            Some(4096_u32.to_le_bytes().to_vec())
+        );
+    }
+
+    #[test]
+    fn test_tick_limit() {
+        let database = SledDatabase::new(&format!("/tmp/{}", uuid::Uuid::new_v4()));
+        let mut runtime = NativeRuntime::new(database, RollupConfig::default());
+        runtime
+            .store_write(&KERNEL, LOOP_KERNEL.as_bytes(), 0)
+            .unwrap();
+
+        // Only a few ticks are left in the run, the loop traps when it exhausts them
+        add_ticks(ticks_left() - 1_000);
+        WasmKernel::entry(&mut runtime);
+
+        assert_eq!(ticks_left(), 0);
+    }
+}
@@ -1,10 +1,17 @@
 mod core;
 mod implementations;
 
+pub use crate::core::Confirmation;
+pub use crate::core::Database;
+pub use crate::core::DatabaseError;
 pub use crate::core::Kernel;
 pub use crate::core::Node;
 use crate::core::NodeBuilder;
 use crate::core::NodeImpl;
+pub use crate::core::TreeNode;
+pub use crate::implementations::RollupConfig;
+pub use crate::implementations::SledDatabase;
+pub use crate::implementations::WasmKernel;
 use crate::implementations::*;
 use async_trait::async_trait;
 pub use tezos_smart_rollup_host::runtime::Runtime;
@@ -20,6 +27,7 @@ impl
         NativeBatcher,
         SledDatabase,
         RollupBatcherInjector,
+        TezosInboxReader,
     > for NativeNode
 {
 }
@@ -31,14 +39,29 @@ impl NativeNode {
         sled_database_uri: &str,
         tezos_node_uri: &str,
         rollup_node_uri: &str,
+        rollup_config: RollupConfig,
     ) -> impl Node {
         let database = SledDatabase::new(sled_database_uri);
+        // The kernel of the origination is installed in a new storage
+        if let Some(boot_kernel) = &rollup_config.boot_kernel {
+            if database.read(KERNEL_PATH).unwrap().is_none() {
+                let kernel = std::fs::read(boot_kernel).unwrap();
+                let _ = database.write(KERNEL_PATH, &kernel).unwrap();
+            }
+        }
         let tezos_listener = TezosListener::new(tezos_node_uri.to_string());
-        let low_latency = LowLatency::new(database.clone());
+        let low_latency = LowLatency::new(database.clone(), rollup_config);
         let injector = RollupBatcherInjector::new(rollup_node_uri.to_string());
         let sequencer = NativeBatcher::new();
-        let comm =
-            NativeNode::start::<K>(tezos_listener, low_latency, sequencer, database, injector);
+        let inbox_reader = TezosInboxReader::new(tezos_node_uri.to_string());
+        let comm = NativeNode::start::<K>(
+            tezos_listener,
+            low_latency,
+            sequencer,
+            database,
+            injector,
+            inbox_reader,
+        );
         NativeNode(comm)
     }
 }
@@ -49,11 +72,15 @@ impl Node for NativeNode {
         self.0.submit_operation(operation).await
     }
 
-    async fn get_value(&self, path: &str) -> Option<Vec<u8>> {
-        self.0.get_value(path).await
+    async fn get_value(&self, path: &str, confirmation: Confirmation) -> Option<Vec<u8>> {
+        self.0.get_value(path, confirmation).await
     }
 
-    async fn get_subkeys(&self, path: &str) -> Option<Vec<String>> {
-        self.0.get_subkeys(path).await
+    async fn get_subkeys(&self, path: &str, confirmation: Confirmation) -> Option<Vec<String>> {
+        self.0.get_subkeys(path, confirmation).await
+    }
+
+    async fn get_outbox(&self, level: u32) -> Option<Vec<Vec<u8>>> {
+        self.0.get_outbox(level).await
     }
 }